@@ -0,0 +1,297 @@
+//! Search for two-player zero-sum games with perfect information.
+//!
+//! Values are always given from the point of view of [`Player::Max`],
+//! who maximises them while [`Player::Min`] minimises them.
+
+/// Bound on all values, utilities and evaluations must lie strictly between `-INFINITY` and `INFINITY`.
+pub const INFINITY: i32 = i32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Player {
+    Max,
+    Min,
+}
+
+impl Player {
+    pub fn opponent(self) -> Self {
+        match self {
+            Player::Max => Player::Min,
+            Player::Min => Player::Max,
+        }
+    }
+
+    /// Checks if the player prefers `value` over `other`.
+    pub fn prefers(self, value: i32, other: i32) -> bool {
+        match self {
+            Player::Max => value > other,
+            Player::Min => value < other,
+        }
+    }
+
+    /// Value no result can be worse than for the player.
+    pub fn worst_value(self) -> i32 {
+        match self {
+            Player::Max => -INFINITY,
+            Player::Min => INFINITY,
+        }
+    }
+}
+
+pub trait GameState: Clone {
+    /// Representation of a move.
+    type Move: Clone;
+
+    /// Returns the player to move.
+    fn to_move(&self) -> Player;
+
+    /// Returns moves legal in self. Non-terminal states are expected to have at least one.
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// Returns state after making the move.
+    fn apply(&self, action: &Self::Move) -> Self;
+
+    /// Checks if the game is over.
+    fn is_terminal(&self) -> bool;
+
+    /// Value of a terminal state for the Max player.
+    fn utility(&self) -> i32;
+}
+
+/// Estimates value of non-terminal states where the search stops.
+pub trait Evaluation<S: GameState> {
+    fn evaluate(&self, state: &S) -> i32;
+}
+
+impl<S: GameState, F: Fn(&S) -> i32> Evaluation<S> for F {
+    fn evaluate(&self, state: &S) -> i32 {
+        self(state)
+    }
+}
+
+/// Evaluation that knows nothing about the game, all non-terminal states are worth `0`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Neutral;
+
+impl<S: GameState> Evaluation<S> for Neutral {
+    fn evaluate(&self, _state: &S) -> i32 {
+        0
+    }
+}
+
+/// Value of the searched state together with the moves both players are expected to make.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult<M> {
+    pub value: i32,
+    /// Principal variation, empty if the state is terminal or the depth is `0`.
+    pub principal_variation: Vec<M>,
+    /// Number of visited states.
+    pub nodes: u64,
+}
+
+impl<M> SearchResult<M> {
+    /// First move of the principal variation.
+    pub fn best_move(&self) -> Option<&M> {
+        self.principal_variation.first()
+    }
+}
+
+/// Value of a state where the search stops, `None` if the search goes on.
+fn leaf_value<S: GameState, E: Evaluation<S>>(state: &S, depth: usize, evaluation: &E) -> Option<i32> {
+    match (state.is_terminal(), depth) {
+        (true, _) => Some(state.utility()),
+        (false, 0) => Some(evaluation.evaluate(state)),
+        _ => None,
+    }
+}
+
+/// Plain minimax searching `depth` plies ahead, states at the depth limit are evaluated.
+pub fn minimax<S: GameState, E: Evaluation<S>>(state: &S, depth: usize, evaluation: &E) -> SearchResult<S::Move> {
+    fn search<S: GameState, E: Evaluation<S>>(state: &S, depth: usize, evaluation: &E, nodes: &mut u64) -> (i32, Vec<S::Move>) {
+        *nodes += 1;
+        if let Some(value) = leaf_value(state, depth, evaluation) {
+            return (value, Vec::new());
+        }
+        let player = state.to_move();
+        let mut best = (player.worst_value(), Vec::new());
+        for action in state.legal_moves() {
+            let (value, line) = search(&state.apply(&action), depth - 1, evaluation, nodes);
+            if best.1.is_empty() || player.prefers(value, best.0) {
+                best = (value, std::iter::once(action).chain(line).collect());
+            }
+        }
+        best
+    }
+
+    let mut nodes = 0;
+    let (value, principal_variation) = search(state, depth, evaluation, &mut nodes);
+    SearchResult { value, principal_variation, nodes }
+}
+
+/// Alpha-beta search with the full window, returns the same value as [`minimax`] visiting fewer states.
+pub fn alpha_beta<S: GameState, E: Evaluation<S>>(state: &S, depth: usize, evaluation: &E) -> SearchResult<S::Move> {
+    alpha_beta_window(state, depth, -INFINITY, INFINITY, evaluation)
+}
+
+/// Fail-soft alpha-beta search within the window `(alpha, beta)`.
+///
+/// If the value lies inside the window it is exact. Otherwise the result is a bound:
+/// a value `<= alpha` is an upper bound and a value `>= beta` is a lower bound on the real value,
+/// possibly tighter than the window itself.
+pub fn alpha_beta_window<S: GameState, E: Evaluation<S>>(state: &S, depth: usize, alpha: i32, beta: i32, evaluation: &E) -> SearchResult<S::Move> {
+    fn search<S: GameState, E: Evaluation<S>>(
+        state: &S, depth: usize, mut alpha: i32, mut beta: i32, evaluation: &E, nodes: &mut u64,
+    ) -> (i32, Vec<S::Move>) {
+        *nodes += 1;
+        if let Some(value) = leaf_value(state, depth, evaluation) {
+            return (value, Vec::new());
+        }
+        let player = state.to_move();
+        let mut best = (player.worst_value(), Vec::new());
+        for action in state.legal_moves() {
+            let (value, line) = search(&state.apply(&action), depth - 1, alpha, beta, evaluation, nodes);
+            if best.1.is_empty() || player.prefers(value, best.0) {
+                best = (value, std::iter::once(action).chain(line).collect());
+            }
+            match player {
+                Player::Max => alpha = alpha.max(best.0),
+                Player::Min => beta = beta.min(best.0),
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    let mut nodes = 0;
+    let (value, principal_variation) = search(state, depth, alpha, beta, evaluation, &mut nodes);
+    SearchResult { value, principal_variation, nodes }
+}
+
+#[cfg(test)]
+mod tests_adversarial {
+    use super::{alpha_beta, alpha_beta_window, minimax, GameState, Neutral, Player, INFINITY};
+
+    /// Explicit game tree, leaves hold utilities.
+    #[derive(Debug, Clone)]
+    enum Tree {
+        Leaf(i32),
+        Node(Player, Vec<Tree>),
+    }
+
+    impl GameState for Tree {
+        type Move = usize;
+
+        fn to_move(&self) -> Player {
+            match self {
+                Tree::Leaf(_) => Player::Max,
+                Tree::Node(player, _) => *player,
+            }
+        }
+
+        fn legal_moves(&self) -> Vec<Self::Move> {
+            match self {
+                Tree::Leaf(_) => Vec::new(),
+                Tree::Node(_, children) => (0..children.len()).collect(),
+            }
+        }
+
+        fn apply(&self, action: &Self::Move) -> Self {
+            match self {
+                Tree::Leaf(_) => unreachable!("Leaves have no moves."),
+                Tree::Node(_, children) => children[*action].clone(),
+            }
+        }
+
+        fn is_terminal(&self) -> bool {
+            matches!(self, Tree::Leaf(_))
+        }
+
+        fn utility(&self) -> i32 {
+            match self {
+                Tree::Leaf(value) => *value,
+                Tree::Node(..) => unreachable!("Only leaves are terminal."),
+            }
+        }
+    }
+
+    fn min_node(leaves: &[i32]) -> Tree {
+        Tree::Node(Player::Min, leaves.iter().map(|&value| Tree::Leaf(value)).collect())
+    }
+
+    /// Game tree of the two-ply example from Russell and Norvig.
+    fn two_ply() -> Tree {
+        Tree::Node(Player::Max, vec![min_node(&[3, 12, 8]), min_node(&[2, 4, 6]), min_node(&[14, 5, 2])])
+    }
+
+    /// Complete random tree, values come from a fixed xorshift sequence.
+    fn random_tree(state: &mut u64, player: Player, depth: usize, branching: usize) -> Tree {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        match depth {
+            0 => Tree::Leaf((*state % 201) as i32 - 100),
+            _ => Tree::Node(player, (0..branching).map(|_| random_tree(state, player.opponent(), depth - 1, branching)).collect()),
+        }
+    }
+
+    #[test]
+    fn test_two_ply_tree() {
+        let result = minimax(&two_ply(), 2, &Neutral);
+        assert_eq!(result.value, 3);
+        assert_eq!(result.principal_variation, [0, 0]);
+        assert_eq!(result.nodes, 13);
+
+        let pruned = alpha_beta(&two_ply(), 2, &Neutral);
+        assert_eq!((pruned.value, pruned.best_move()), (3, Some(&0)));
+        /* The second and third leaf of the middle node are pruned. */
+        assert_eq!(pruned.nodes, 11);
+    }
+
+    #[test]
+    fn test_pluggable_evaluation() {
+        /* Cut off after the first ply, the evaluation trusts the first leaf of each subtree and picks the last one. */
+        let evaluation = |state: &Tree| match state {
+            Tree::Node(_, children) => children[0].utility(),
+            Tree::Leaf(value) => *value,
+        };
+        let result = alpha_beta(&two_ply(), 1, &evaluation);
+        assert_eq!((result.value, result.principal_variation), (14, vec![2]));
+        assert_eq!(minimax(&two_ply(), 1, &evaluation).value, 14);
+    }
+
+    #[test]
+    fn test_alpha_beta_agrees_with_minimax() {
+        let mut seed = 0x2545_f491_4f6c_dd1d;
+        for depth in 1..=5 {
+            for _ in 0..10 {
+                let tree = random_tree(&mut seed, Player::Max, depth, 4);
+                let expected = minimax(&tree, depth, &Neutral);
+                let result = alpha_beta(&tree, depth, &Neutral);
+                assert_eq!(result.value, expected.value);
+                assert!(result.nodes <= expected.nodes);
+                /* The principal variation leads to a leaf of the searched value. */
+                let leaf = result.principal_variation.iter().fold(tree.clone(), |state, action| state.apply(action));
+                assert_eq!(leaf.utility(), result.value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fail_soft_bounds() {
+        let mut seed = 7;
+        for _ in 0..50 {
+            let tree = random_tree(&mut seed, Player::Min, 4, 3);
+            let exact = minimax(&tree, 4, &Neutral).value;
+            for (alpha, beta) in [(-20, 20), (-100, -50), (50, 100), (0, 1)] {
+                let value = alpha_beta_window(&tree, 4, alpha, beta, &Neutral).value;
+                match value {
+                    _ if value <= alpha => assert!(exact <= value),
+                    _ if value >= beta => assert!(exact >= value),
+                    _ => assert_eq!(value, exact),
+                }
+            }
+            assert!(alpha_beta_window(&tree, 4, -INFINITY, INFINITY, &Neutral).value == exact);
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! Game playing engine: iterative deepening principal variation search with a transposition table.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::adversarial::{Evaluation, GameState, Player, INFINITY};
+
+/// Nodes searched between checks of the time budget.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+/// Quiescence search stops after this many noisy moves in a row.
+const MAX_QUIESCENCE_DEPTH: usize = 32;
+
+/// Game state the engine can search. The engine keeps values for the player to move, so players must alternate.
+pub trait EngineState: GameState<Move: Eq + Hash> {
+    /// Zobrist key of the state, equal states must have equal keys. See [`Zobrist`].
+    fn hash_key(&self) -> u64;
+
+    /// Checks if the move changes the position sharply, like a capture in chess.
+    /// Noisy moves are searched further by quiescence search and never stored as killers.
+    fn is_noisy(&self, _action: &Self::Move) -> bool {
+        false
+    }
+}
+
+/// Table of random keys for Zobrist hashing. Key of a state is the xor of keys of its features,
+/// like a piece standing on a square, so it can be updated by a move with a few xors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zobrist {
+    keys: Vec<u64>,
+}
+
+impl Zobrist {
+    /// Generates `count` keys from a seed with the SplitMix64 generator.
+    pub fn new(count: usize, seed: u64) -> Self {
+        let mut state = seed;
+        let keys = (0..count).map(|_| {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut key = state;
+            key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            key ^ (key >> 31)
+        }).collect();
+        Self { keys }
+    }
+
+    pub fn key(&self, feature: usize) -> u64 {
+        self.keys[feature]
+    }
+}
+
+/// Meaning of a stored value, the search that produced it may have failed low or high.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// Real value is at least the stored one.
+    Lower,
+    /// Real value is at most the stored one.
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry<M> {
+    pub key: u64,
+    pub depth: usize,
+    /// Value for the player to move.
+    pub value: i32,
+    pub bound: Bound,
+    pub best_move: Option<M>,
+}
+
+/// Hash table of searched states indexed by the low bits of their keys.
+/// A slot keeps the entry searched deeper, unless the new entry is of another state.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable<M> {
+    slots: Vec<Option<Entry<M>>>,
+}
+
+impl<M: Clone> TranspositionTable<M> {
+    /// Creates table with at least `capacity` slots, rounded up to a power of two.
+    pub fn new(capacity: usize) -> Self {
+        Self { slots: vec![None; capacity.max(1).next_power_of_two()] }
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.slots.len() - 1)
+    }
+
+    pub fn probe(&self, key: u64) -> Option<&Entry<M>> {
+        self.slots[self.slot(key)].as_ref().filter(|entry| entry.key == key)
+    }
+
+    pub fn store(&mut self, entry: Entry<M>) {
+        let slot = self.slot(entry.key);
+        match &self.slots[slot] {
+            Some(old) if old.key == entry.key && old.depth > entry.depth => {}
+            _ => self.slots[slot] = Some(entry),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+    }
+}
+
+/// Limits of a single search, iterative deepening stops at whichever comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub max_depth: usize,
+    /// Time budget, the first iteration is always finished.
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    pub fn depth(max_depth: usize) -> Self {
+        Self { max_depth, time: None }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self { max_depth: usize::MAX, time: Some(time) }
+    }
+}
+
+/// Report of a finished iteration of iterative deepening.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Iteration<M> {
+    pub depth: usize,
+    /// Value for the Max player.
+    pub value: i32,
+    pub principal_variation: Vec<M>,
+    /// States visited during the iteration, including quiescence search.
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+/// Result of the deepest finished iteration together with reports of all iterations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineResult<M> {
+    pub value: i32,
+    pub principal_variation: Vec<M>,
+    pub iterations: Vec<Iteration<M>>,
+}
+
+impl<M> EngineResult<M> {
+    pub fn best_move(&self) -> Option<&M> {
+        self.principal_variation.first()
+    }
+
+    /// Depth of the deepest finished iteration.
+    pub fn depth(&self) -> usize {
+        self.iterations.last().map_or(0, |iteration| iteration.depth)
+    }
+}
+
+/// Search was stopped because the time ran out.
+struct Timeout;
+
+/// Iterative deepening alpha-beta engine.
+///
+/// - every iteration runs principal variation search: the first move is searched with the full window,
+///   the others with a null window and searched again only if they turn out better,
+/// - transposition table keeps values and best moves of searched states between iterations and searches,
+/// - moves are ordered by the best move from the table, then killer moves that caused a cutoff at the same ply,
+///   then by the history heuristic, which counts cutoffs caused by the move anywhere in the tree,
+/// - at the depth limit quiescence search goes on with noisy moves only, the player to move may stand pat.
+///
+/// Killers and history are kept between searches, call [`Engine::clear`] before searching another game.
+pub struct Engine<S: EngineState, E: Evaluation<S>> {
+    evaluation: E,
+    table: TranspositionTable<S::Move>,
+    killers: Vec<[Option<S::Move>; 2]>,
+    history: HashMap<S::Move, u64>,
+    nodes: u64,
+    deadline: Option<Instant>,
+}
+
+impl<S: EngineState, E: Evaluation<S>> Engine<S, E> {
+    pub const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+
+    pub fn new(evaluation: E) -> Self {
+        Self::with_table_size(evaluation, Self::DEFAULT_TABLE_SIZE)
+    }
+
+    pub fn with_table_size(evaluation: E, table_size: usize) -> Self {
+        Self {
+            evaluation,
+            table: TranspositionTable::new(table_size),
+            killers: Vec::new(),
+            history: HashMap::new(),
+            nodes: 0,
+            deadline: None,
+        }
+    }
+
+    pub fn table(&self) -> &TranspositionTable<S::Move> {
+        &self.table
+    }
+
+    /// Forgets the transposition table, killers and history.
+    pub fn clear(&mut self) {
+        self.table.clear();
+        self.killers.clear();
+        self.history.clear();
+    }
+
+    /// Searches the state deeper and deeper until the depth limit is reached or the time runs out.
+    pub fn search(&mut self, state: &S, limits: SearchLimits) -> EngineResult<S::Move> {
+        let start = Instant::now();
+        let mut result = EngineResult { value: 0, principal_variation: Vec::new(), iterations: Vec::new() };
+        for depth in 1..=limits.max_depth.max(1) {
+            self.nodes = 0;
+            /* The first iteration always finishes, so that there is a move to play. */
+            self.deadline = limits.time.filter(|_| depth > 1).map(|time| start + time);
+            let Ok((value, principal_variation)) = self.negamax(state, depth, 0, -INFINITY, INFINITY) else { break };
+            let value = sign(state.to_move()) * value;
+            result.iterations.push(Iteration {
+                depth,
+                value,
+                principal_variation: principal_variation.clone(),
+                nodes: self.nodes,
+                elapsed: start.elapsed(),
+            });
+            result.value = value;
+            result.principal_variation = principal_variation;
+            if state.is_terminal() || limits.time.is_some_and(|time| start.elapsed() >= time) {
+                break;
+            }
+        }
+        result
+    }
+
+    fn check_time(&mut self) -> Result<(), Timeout> {
+        self.nodes += 1;
+        match self.deadline {
+            Some(deadline) if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) && Instant::now() >= deadline => Err(Timeout),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail-soft principal variation search, values are given for the player to move.
+    fn negamax(&mut self, state: &S, depth: usize, ply: usize, mut alpha: i32, beta: i32) -> Result<(i32, Vec<S::Move>), Timeout> {
+        self.check_time()?;
+        if state.is_terminal() {
+            return Ok((sign(state.to_move()) * state.utility(), Vec::new()));
+        }
+        if depth == 0 {
+            return self.quiescence(state, 0, alpha, beta);
+        }
+
+        let key = state.hash_key();
+        let mut table_move = None;
+        if let Some(entry) = self.table.probe(key) {
+            table_move = entry.best_move.clone();
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.value >= beta,
+                Bound::Upper => entry.value <= alpha,
+            };
+            if ply > 0 && entry.depth >= depth && cutoff {
+                return Ok((entry.value, entry.best_move.iter().cloned().collect()));
+            }
+        }
+
+        let moves = self.order_moves(state, state.legal_moves(), table_move.as_ref(), ply);
+        if moves.is_empty() {
+            return Ok((self.evaluate(state), Vec::new()));
+        }
+        let original_alpha = alpha;
+        let mut best = (-INFINITY, Vec::new());
+        for (index, action) in moves.into_iter().enumerate() {
+            let child = state.apply(&action);
+            let (value, line) = match index {
+                0 => self.child_value(state, &child, depth, ply, alpha, beta)?,
+                _ => {
+                    /* Null window only tells if the move beats the best one so far. */
+                    match self.child_value(state, &child, depth, ply, alpha, alpha + 1)? {
+                        (value, _) if value > alpha && value < beta => self.child_value(state, &child, depth, ply, alpha, beta)?,
+                        result => result,
+                    }
+                }
+            };
+            if index == 0 || value > best.0 {
+                best = (value, std::iter::once(action.clone()).chain(line).collect());
+            }
+            alpha = alpha.max(best.0);
+            if alpha >= beta {
+                if !state.is_noisy(&action) {
+                    self.store_killer(action.clone(), ply);
+                    *self.history.entry(action).or_insert(0) += (depth * depth) as u64;
+                }
+                break;
+            }
+        }
+
+        let bound = match best.0 {
+            value if value <= original_alpha => Bound::Upper,
+            value if value >= beta => Bound::Lower,
+            _ => Bound::Exact,
+        };
+        self.table.store(Entry { key, depth, value: best.0, bound, best_move: best.1.first().cloned() });
+        Ok(best)
+    }
+
+    /// Value of the child for the player to move in the parent. Values of terminal states come straight
+    /// from their utility, so terminal states do not need to know whose turn it would be.
+    fn child_value(&mut self, state: &S, child: &S, depth: usize, ply: usize, alpha: i32, beta: i32) -> Result<(i32, Vec<S::Move>), Timeout> {
+        if child.is_terminal() {
+            self.check_time()?;
+            return Ok((sign(state.to_move()) * child.utility(), Vec::new()));
+        }
+        let (value, line) = self.negamax(child, depth - 1, ply + 1, -beta, -alpha)?;
+        Ok((-value, line))
+    }
+
+    /// Searches noisy moves only. The player to move may also stop, taking the evaluation of the state.
+    fn quiescence(&mut self, state: &S, depth: usize, mut alpha: i32, beta: i32) -> Result<(i32, Vec<S::Move>), Timeout> {
+        let stand_pat = self.evaluate(state);
+        let mut best = (stand_pat, Vec::new());
+        if stand_pat >= beta || depth == MAX_QUIESCENCE_DEPTH {
+            return Ok(best);
+        }
+        alpha = alpha.max(stand_pat);
+        for action in state.legal_moves().into_iter().filter(|action| state.is_noisy(action)) {
+            self.check_time()?;
+            let child = state.apply(&action);
+            let (value, line) = match child.is_terminal() {
+                true => (sign(state.to_move()) * child.utility(), Vec::new()),
+                false => self.quiescence(&child, depth + 1, -beta, -alpha).map(|(value, line)| (-value, line))?,
+            };
+            if value > best.0 {
+                best = (value, std::iter::once(action).chain(line).collect());
+            }
+            alpha = alpha.max(best.0);
+            if alpha >= beta {
+                break;
+            }
+        }
+        Ok(best)
+    }
+
+    /// Evaluation for the player to move.
+    fn evaluate(&self, state: &S) -> i32 {
+        sign(state.to_move()) * self.evaluation.evaluate(state)
+    }
+
+    fn store_killer(&mut self, action: S::Move, ply: usize) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(&action) {
+            killers[1] = killers[0].replace(action);
+        }
+    }
+
+    fn order_moves(&self, state: &S, mut moves: Vec<S::Move>, table_move: Option<&S::Move>, ply: usize) -> Vec<S::Move> {
+        let killers = self.killers.get(ply);
+        let rank = |action: &S::Move| {
+            if Some(action) == table_move {
+                return (0, 0);
+            }
+            if state.is_noisy(action) {
+                return (1, 0);
+            }
+            match killers.and_then(|killers| killers.iter().position(|killer| killer.as_ref() == Some(action))) {
+                Some(index) => (2, index as u64),
+                None => (3, u64::MAX - self.history.get(action).copied().unwrap_or(0)),
+            }
+        };
+        moves.sort_by_cached_key(rank);
+        moves
+    }
+}
+
+/// Multiplier turning values for the Max player into values for the given player.
+fn sign(player: Player) -> i32 {
+    match player {
+        Player::Max => 1,
+        Player::Min => -1,
+    }
+}
+
+#[cfg(test)]
+mod tests_engine {
+    use std::time::Duration;
+
+    use super::{Engine, EngineState, SearchLimits, Zobrist};
+    use crate::adversarial::{alpha_beta, minimax, GameState, Neutral, Player};
+    use crate::test_games::TicTacToe;
+
+    /// Explicit game tree, every move may be noisy.
+    #[derive(Debug, Clone)]
+    enum Tree {
+        Leaf(i32),
+        Node { id: u64, player: Player, estimate: i32, children: Vec<(bool, Tree)> },
+    }
+
+    impl GameState for Tree {
+        type Move = usize;
+
+        fn to_move(&self) -> Player {
+            match self {
+                Tree::Leaf(_) => Player::Max,
+                Tree::Node { player, .. } => *player,
+            }
+        }
+
+        fn legal_moves(&self) -> Vec<Self::Move> {
+            match self {
+                Tree::Leaf(_) => Vec::new(),
+                Tree::Node { children, .. } => (0..children.len()).collect(),
+            }
+        }
+
+        fn apply(&self, action: &Self::Move) -> Self {
+            match self {
+                Tree::Leaf(_) => unreachable!("Leaves have no moves."),
+                Tree::Node { children, .. } => children[*action].1.clone(),
+            }
+        }
+
+        fn is_terminal(&self) -> bool {
+            matches!(self, Tree::Leaf(_))
+        }
+
+        fn utility(&self) -> i32 {
+            match self {
+                Tree::Leaf(value) => *value,
+                Tree::Node { .. } => unreachable!("Only leaves are terminal."),
+            }
+        }
+    }
+
+    impl EngineState for Tree {
+        fn hash_key(&self) -> u64 {
+            match self {
+                Tree::Leaf(value) => *value as u64,
+                Tree::Node { id, .. } => *id,
+            }
+        }
+
+        fn is_noisy(&self, action: &Self::Move) -> bool {
+            matches!(self, Tree::Node { children, .. } if children[*action].0)
+        }
+    }
+
+    fn estimate(state: &Tree) -> i32 {
+        match state {
+            Tree::Leaf(value) => *value,
+            Tree::Node { estimate, .. } => *estimate,
+        }
+    }
+
+    fn random_tree(seed: &mut u64, player: Player, depth: usize) -> Tree {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        let (id, value) = (*seed, (*seed % 201) as i32 - 100);
+        match depth {
+            0 => Tree::Leaf(value),
+            _ => {
+                let children = (0..3).map(|_| (false, random_tree(seed, player.opponent(), depth - 1))).collect();
+                Tree::Node { id, player, estimate: value, children }
+            }
+        }
+    }
+
+    #[test]
+    fn test_zobrist_keys() {
+        let keys = Zobrist::new(64, 1);
+        assert_eq!(keys, Zobrist::new(64, 1));
+        let mut all = (0..64).map(|feature| keys.key(feature)).collect::<Vec<_>>();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 64);
+    }
+
+    #[test]
+    fn test_tic_tac_toe_is_a_draw() {
+        let mut engine = Engine::new(Neutral);
+        let result = engine.search(&TicTacToe::new(), SearchLimits::depth(9));
+        assert_eq!(result.value, 0);
+        assert_eq!(result.depth(), 9);
+        assert_eq!(result.iterations.iter().map(|iteration| iteration.depth).collect::<Vec<_>>(), (1..=9).collect::<Vec<_>>());
+        /* The transposition table saves most of the work of plain alpha-beta. */
+        let plain = alpha_beta(&TicTacToe::new(), 9, &Neutral);
+        assert!(result.iterations.last().unwrap().nodes * 4 < plain.nodes);
+
+        /* Crosses win after a blunder of noughts. */
+        let state = TicTacToe::from_moves(&[4, 1]);
+        let result = engine.search(&state, SearchLimits::depth(7));
+        assert_eq!(result.value, 1);
+        let end = result.principal_variation.iter().fold(state, |state, action| state.apply(action));
+        assert_eq!(end.winner(), Some(1));
+    }
+
+    #[test]
+    fn test_agrees_with_minimax() {
+        let mut seed = 0x9e37_79b9;
+        for depth in 1..=5 {
+            for _ in 0..10 {
+                let tree = random_tree(&mut seed, Player::Max, depth);
+                let mut engine = Engine::new(estimate);
+                let result = engine.search(&tree, SearchLimits::depth(depth));
+                assert_eq!(result.value, minimax(&tree, depth, &estimate).value);
+                assert!(result.iterations.iter().all(|iteration| iteration.nodes > 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_quiescence() {
+        let node = |id, player, estimate, children| Tree::Node { id, player, estimate, children };
+        /* The second move looks better at the horizon, but the opponent has a capture there. */
+        let tree = node(1, Player::Max, 0, vec![
+            (false, node(2, Player::Min, 5, vec![(false, Tree::Leaf(5))])),
+            (false, node(3, Player::Min, 20, vec![(false, Tree::Leaf(10)), (true, Tree::Leaf(-50))])),
+        ]);
+        assert_eq!(minimax(&tree, 1, &estimate).value, 20);
+        let result = Engine::new(estimate).search(&tree, SearchLimits::depth(1));
+        assert_eq!((result.value, result.best_move()), (5, Some(&0)));
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let mut engine = Engine::new(Neutral);
+        let result = engine.search(&TicTacToe::new(), SearchLimits::time(Duration::ZERO));
+        assert_eq!(result.depth(), 1);
+        assert!(result.best_move().is_some());
+    }
+}
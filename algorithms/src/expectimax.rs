@@ -0,0 +1,350 @@
+//! Search for two-player zero-sum games with chance, like dice rolls or dealing cards.
+//!
+//! As in [`crate::adversarial`] values are given from the point of view of [`Player::Max`].
+//! Values of chance nodes are expected values, so they are not integers anymore.
+
+use std::ops::RangeInclusive;
+
+use crate::adversarial::{Evaluation, GameState, Player};
+
+/// Game state that may be decided by chance instead of a player.
+///
+/// In chance states [`GameState::to_move`] and [`GameState::legal_moves`] are not used,
+/// outcomes are applied with [`GameState::apply`] like moves.
+pub trait StochasticState: GameState {
+    /// Checks if the next move is decided by chance.
+    fn is_chance(&self) -> bool;
+
+    /// Possible outcomes of a chance state with their probabilities, which must sum up to `1`.
+    fn outcomes(&self) -> Vec<(Self::Move, f64)>;
+}
+
+/// Expected value of the searched state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectimaxResult<M> {
+    pub value: f64,
+    /// Best move of the player to move, `None` in terminal and chance states or if the depth is `0`.
+    pub best_move: Option<M>,
+    /// Number of visited states.
+    pub nodes: u64,
+}
+
+/// Value of a state where the search stops, `None` if the search goes on.
+fn leaf_value<S: StochasticState, E: Evaluation<S>>(state: &S, depth: usize, evaluation: &E) -> Option<f64> {
+    match (state.is_terminal(), depth) {
+        (true, _) => Some(state.utility() as f64),
+        (false, 0) => Some(evaluation.evaluate(state) as f64),
+        _ => None,
+    }
+}
+
+fn worst_value(player: Player) -> f64 {
+    match player {
+        Player::Max => f64::NEG_INFINITY,
+        Player::Min => f64::INFINITY,
+    }
+}
+
+fn prefers(player: Player, value: f64, other: f64) -> bool {
+    match player {
+        Player::Max => value > other,
+        Player::Min => value < other,
+    }
+}
+
+/// Expectimax searching `depth` moves of the players ahead, chance nodes do not count into the depth.
+pub fn expectimax<S: StochasticState, E: Evaluation<S>>(state: &S, depth: usize, evaluation: &E) -> ExpectimaxResult<S::Move> {
+    fn search<S: StochasticState, E: Evaluation<S>>(state: &S, depth: usize, evaluation: &E, nodes: &mut u64) -> (f64, Option<S::Move>) {
+        *nodes += 1;
+        if let Some(value) = leaf_value(state, depth, evaluation) {
+            return (value, None);
+        }
+        if state.is_chance() {
+            let value = state.outcomes().iter()
+                .map(|(outcome, probability)| probability * search(&state.apply(outcome), depth, evaluation, nodes).0)
+                .sum();
+            return (value, None);
+        }
+        let player = state.to_move();
+        let mut best = (worst_value(player), None);
+        for action in state.legal_moves() {
+            let (value, _) = search(&state.apply(&action), depth - 1, evaluation, nodes);
+            if best.1.is_none() || prefers(player, value, best.0) {
+                best = (value, Some(action));
+            }
+        }
+        best
+    }
+
+    let mut nodes = 0;
+    let (value, best_move) = search(state, depth, evaluation, &mut nodes);
+    ExpectimaxResult { value, best_move, nodes }
+}
+
+/// Star1 pruning: *-minimax returning the same value as [`expectimax`].
+///
+/// All utilities and evaluations must lie within `bounds`. Chance nodes are cut off
+/// as soon as the bounds show that their value lies outside the window of alpha-beta.
+pub fn star1<S: StochasticState, E: Evaluation<S>>(state: &S, depth: usize, bounds: RangeInclusive<i32>, evaluation: &E) -> ExpectimaxResult<S::Move> {
+    star(state, depth, bounds, evaluation, false)
+}
+
+/// Star2 pruning: Star1 that first probes children of chance nodes.
+///
+/// A probe searches only the first move of a child, which bounds its value from one side
+/// and often cuts the chance node off without searching any child fully.
+/// Works best when the move ordering puts good moves first.
+pub fn star2<S: StochasticState, E: Evaluation<S>>(state: &S, depth: usize, bounds: RangeInclusive<i32>, evaluation: &E) -> ExpectimaxResult<S::Move> {
+    star(state, depth, bounds, evaluation, true)
+}
+
+struct Star<'a, E> {
+    evaluation: &'a E,
+    lower: f64,
+    upper: f64,
+    probing: bool,
+    nodes: u64,
+}
+
+fn star<S: StochasticState, E: Evaluation<S>>(state: &S, depth: usize, bounds: RangeInclusive<i32>, evaluation: &E, probing: bool) -> ExpectimaxResult<S::Move> {
+    let mut context = Star { evaluation, lower: *bounds.start() as f64, upper: *bounds.end() as f64, probing, nodes: 0 };
+    let (lower, upper) = (context.lower, context.upper);
+    let (value, best_move) = context.search(state, depth, lower, upper);
+    ExpectimaxResult { value, best_move, nodes: context.nodes }
+}
+
+impl<E> Star<'_, E> {
+    /// Fail-soft search within the window `[alpha, beta]`, same as [`crate::adversarial::alpha_beta_window`].
+    fn search<S: StochasticState>(&mut self, state: &S, depth: usize, mut alpha: f64, mut beta: f64) -> (f64, Option<S::Move>)
+    where
+        E: Evaluation<S>,
+    {
+        self.nodes += 1;
+        if let Some(value) = leaf_value(state, depth, self.evaluation) {
+            debug_assert!((self.lower..=self.upper).contains(&value), "Value {value} lies outside of the bounds.");
+            return (value, None);
+        }
+        if state.is_chance() {
+            return (self.chance(state, depth, alpha, beta), None);
+        }
+        let player = state.to_move();
+        let mut best = (worst_value(player), None);
+        for action in state.legal_moves() {
+            let (value, _) = self.search(&state.apply(&action), depth - 1, alpha, beta);
+            if best.1.is_none() || prefers(player, value, best.0) {
+                best = (value, Some(action));
+            }
+            match player {
+                Player::Max => alpha = alpha.max(best.0),
+                Player::Min => beta = beta.min(best.0),
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Value of a chance node, a bound if it lies outside of the window.
+    fn chance<S: StochasticState>(&mut self, state: &S, depth: usize, alpha: f64, beta: f64) -> f64
+    where
+        E: Evaluation<S>,
+    {
+        let (children, probabilities): (Vec<_>, Vec<_>) = state.outcomes().into_iter()
+            .map(|(outcome, probability)| (state.apply(&outcome), probability))
+            .unzip();
+        /* Bounds on values of the children, tightened by the probes. */
+        let mut lower = vec![self.lower; children.len()];
+        let mut upper = vec![self.upper; children.len()];
+        /* Expected value of the children in the range, using given values. */
+        let expected = |values: &[f64], range: std::ops::Range<usize>| {
+            range.map(|index| values[index] * probabilities[index]).sum::<f64>()
+        };
+        let all = 0..children.len();
+
+        if self.probing {
+            for (index, child) in children.iter().enumerate() {
+                /* Values the child must reach to cut the chance node off, given bounds of the others. */
+                let others_lower = expected(&lower, all.clone()) - probabilities[index] * lower[index];
+                let others_upper = expected(&upper, all.clone()) - probabilities[index] * upper[index];
+                let low = ((alpha - others_upper) / probabilities[index]).max(lower[index]);
+                let high = ((beta - others_lower) / probabilities[index]).min(upper[index]);
+                if let Some(value) = leaf_value(child, depth, self.evaluation) {
+                    self.nodes += 1;
+                    (lower[index], upper[index]) = (value, value);
+                } else if !child.is_chance() && low < high {
+                    let first = child.legal_moves().into_iter().next().expect("Non-terminal states have moves.");
+                    self.nodes += 1;
+                    let (value, _) = self.search(&child.apply(&first), depth - 1, low, high);
+                    /* The first move bounds the value of the child from the side of the player to move. */
+                    match child.to_move() {
+                        Player::Max if value > low => lower[index] = value,
+                        Player::Min if value < high => upper[index] = value,
+                        _ => {}
+                    }
+                }
+                if expected(&lower, all.clone()) >= beta {
+                    return expected(&lower, all.clone());
+                }
+                if expected(&upper, all.clone()) <= alpha {
+                    return expected(&upper, all.clone());
+                }
+            }
+        }
+
+        /* Expected value of the children searched so far. */
+        let mut exact = 0.0;
+        for (index, child) in children.iter().enumerate() {
+            let probability = probabilities[index];
+            let rest_lower = expected(&lower, index + 1..children.len());
+            let rest_upper = expected(&upper, index + 1..children.len());
+            /* Below `low` the chance node fails low even if the rest reaches upper bounds, above `high` it fails high. */
+            let low = (alpha - exact - rest_upper) / probability;
+            let high = (beta - exact - rest_lower) / probability;
+            let value = if upper[index] <= low {
+                upper[index]
+            } else if lower[index] >= high || lower[index] == upper[index] {
+                lower[index]
+            } else {
+                self.search(child, depth, low.max(lower[index]), high.min(upper[index])).0
+            };
+            if value <= low {
+                return exact + probability * value + rest_upper;
+            }
+            if value >= high {
+                return exact + probability * value + rest_lower;
+            }
+            exact += probability * value;
+        }
+        exact
+    }
+}
+
+#[cfg(test)]
+mod tests_expectimax {
+    use super::{expectimax, star1, star2, StochasticState};
+    use crate::adversarial::{GameState, Neutral, Player};
+    use crate::rng::XorShift64;
+
+    /// Explicit game tree with chance nodes, leaves hold utilities.
+    #[derive(Debug, Clone)]
+    enum Tree {
+        Leaf(i32),
+        Node(Player, Vec<Tree>),
+        Chance(Vec<(f64, Tree)>),
+    }
+
+    impl GameState for Tree {
+        type Move = usize;
+
+        fn to_move(&self) -> Player {
+            match self {
+                Tree::Node(player, _) => *player,
+                _ => Player::Max,
+            }
+        }
+
+        fn legal_moves(&self) -> Vec<Self::Move> {
+            match self {
+                Tree::Node(_, children) => (0..children.len()).collect(),
+                _ => Vec::new(),
+            }
+        }
+
+        fn apply(&self, action: &Self::Move) -> Self {
+            match self {
+                Tree::Leaf(_) => unreachable!("Leaves have no moves."),
+                Tree::Node(_, children) => children[*action].clone(),
+                Tree::Chance(outcomes) => outcomes[*action].1.clone(),
+            }
+        }
+
+        fn is_terminal(&self) -> bool {
+            matches!(self, Tree::Leaf(_))
+        }
+
+        fn utility(&self) -> i32 {
+            match self {
+                Tree::Leaf(value) => *value,
+                _ => unreachable!("Only leaves are terminal."),
+            }
+        }
+    }
+
+    impl StochasticState for Tree {
+        fn is_chance(&self) -> bool {
+            matches!(self, Tree::Chance(_))
+        }
+
+        fn outcomes(&self) -> Vec<(Self::Move, f64)> {
+            match self {
+                Tree::Chance(outcomes) => outcomes.iter().enumerate().map(|(index, (probability, _))| (index, *probability)).collect(),
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    /// Max either takes `safe` or rolls a four-sided die, after which Min picks the rolled number or `cap`.
+    fn dice_game(safe: i32, cap: i32) -> Tree {
+        let roll = (1..=4).map(|face| (0.25, Tree::Node(Player::Min, vec![Tree::Leaf(face), Tree::Leaf(cap)]))).collect();
+        Tree::Node(Player::Max, vec![Tree::Leaf(safe), Tree::Chance(roll)])
+    }
+
+    /// Complete random tree alternating player nodes with chance nodes of random probabilities.
+    fn random_tree(rng: &mut XorShift64, player: Player, depth: usize, branching: usize) -> Tree {
+        if depth == 0 {
+            return Tree::Leaf(rng.below(201) as i32 - 100);
+        }
+        let children = (0..branching).map(|_| {
+            let outcomes = (0..branching).map(|_| 1.0 + rng.below(4) as f64).collect::<Vec<_>>();
+            let total = outcomes.iter().sum::<f64>();
+            Tree::Chance(outcomes.iter().map(|weight| (weight / total, random_tree(rng, player.opponent(), depth - 1, branching))).collect())
+        });
+        Tree::Node(player, children.collect())
+    }
+
+    #[test]
+    fn test_dice_game() {
+        /* Rolling is worth (1 + 2 + 3 + 3) / 4 = 2.25. */
+        let result = expectimax(&dice_game(2, 3), 2, &Neutral);
+        assert_eq!((result.value, result.best_move), (2.25, Some(1)));
+        assert_eq!(result.nodes, 1 + 1 + 1 + 4 * 3);
+        let result = expectimax(&dice_game(3, 3), 2, &Neutral);
+        assert_eq!((result.value, result.best_move), (3.0, Some(0)));
+
+        for search in [star1, star2] {
+            let result = search(&dice_game(2, 3), 2, 1..=4, &Neutral);
+            assert_eq!((result.value, result.best_move), (2.25, Some(1)));
+            /* Taking 4 safely, rolling can not be better. */
+            let result = search(&dice_game(4, 3), 2, 1..=4, &Neutral);
+            assert_eq!((result.value, result.best_move), (4.0, Some(0)));
+            assert!(result.nodes < 5);
+        }
+    }
+
+    #[test]
+    fn test_star_agrees_with_expectimax() {
+        let mut rng = XorShift64::new(3);
+        let mut nodes = [0; 3];
+        for depth in 1..=4 {
+            for _ in 0..10 {
+                let tree = random_tree(&mut rng, Player::Max, depth, 3);
+                let expected = expectimax(&tree, depth, &Neutral);
+                for (index, result) in [expected.clone(), star1(&tree, depth, -100..=100, &Neutral), star2(&tree, depth, -100..=100, &Neutral)].into_iter().enumerate() {
+                    assert!((result.value - expected.value).abs() < 1e-9, "{} != {}", result.value, expected.value);
+                    nodes[index] += result.nodes;
+                }
+            }
+        }
+        assert!(nodes[1] < nodes[0] && nodes[2] < nodes[0], "{nodes:?}");
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        /* Cut off before the roll, the evaluation is taken for the whole chance node. */
+        let evaluation = |state: &Tree| if matches!(state, Tree::Chance(_)) { 5 } else { 0 };
+        let result = expectimax(&dice_game(2, 3), 1, &evaluation);
+        assert_eq!((result.value, result.best_move), (5.0, Some(1)));
+        assert_eq!(star2(&dice_game(2, 3), 1, 0..=5, &evaluation).value, 5.0);
+    }
+}
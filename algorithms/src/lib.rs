@@ -1,3 +1,11 @@
+pub mod adversarial;
+pub mod engine;
+pub mod expectimax;
+pub mod mcts;
+pub mod rng;
+#[cfg(test)]
+mod test_games;
+
 use std::cmp::Ordering;
 use std::hash::Hash;
 use std::collections::{BinaryHeap, HashSet, VecDeque};
@@ -8,6 +16,7 @@ pub struct ActionSequence<T: UninformedState>(Option<T>);
 impl<T: UninformedState> Iterator for ActionSequence<T> {
         type Item = T;
 
+        #[allow(clippy::mem_replace_option_with_some)]
         fn next(&mut self) -> Option<Self::Item> {
             match self.0 {
                 None => None,
@@ -140,6 +149,7 @@ impl<T: InformedState> PartialEq<Self> for StateOrdering<T> {
 }
 
 
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl<T: InformedState> PartialOrd<Self> for StateOrdering<T> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.0.cmp(&other.0))
@@ -157,6 +167,7 @@ impl<T: InformedState> std::cmp::Ord for StateOrdering<T> {
 pub trait InformedState : UninformedState {
     fn heuristic(&self) -> i32;
 
+    #[allow(unused_mut, unused_variables)]
     fn a_star_search(self) -> Option<ActionSequence<Self>> {
         if self.goal_test() { return Some(ActionSequence(Some(self))) }
         let mut priority_queue = BinaryHeap::<StateOrdering<Self>>::from(
@@ -0,0 +1,359 @@
+//! Monte Carlo tree search with UCT selection.
+
+use std::time::{Duration, Instant};
+
+use crate::adversarial::{GameState, Player};
+use crate::rng::XorShift64;
+
+/// Picks moves of random playouts.
+pub trait RolloutPolicy<S: GameState> {
+    /// Returns index of the chosen move. `moves` are the legal moves of the state and are never empty.
+    fn choose(&self, state: &S, moves: &[S::Move], rng: &mut XorShift64) -> usize;
+}
+
+impl<S: GameState, F: Fn(&S, &[S::Move], &mut XorShift64) -> usize> RolloutPolicy<S> for F {
+    fn choose(&self, state: &S, moves: &[S::Move], rng: &mut XorShift64) -> usize {
+        self(state, moves, rng)
+    }
+}
+
+/// Policy choosing uniformly random moves.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomRollout;
+
+impl<S: GameState> RolloutPolicy<S> for RandomRollout {
+    fn choose(&self, _state: &S, moves: &[S::Move], rng: &mut XorShift64) -> usize {
+        rng.below(moves.len())
+    }
+}
+
+/// When to stop the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    Iterations(u64),
+    /// At least one iteration is always made.
+    Time(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MctsConfig {
+    /// Exploration constant of UCT, `√2` in theory, smaller values prefer exploitation.
+    pub exploration: f64,
+    /// Playouts longer than this are scored as a draw.
+    pub max_rollout_length: usize,
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self { exploration: std::f64::consts::SQRT_2, max_rollout_length: 1000, seed: 0 }
+    }
+}
+
+/// Statistics of a move of the root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveStats<M> {
+    pub action: M,
+    pub visits: u64,
+    /// Average reward of the player making the move, from `0` for a loss to `1` for a win.
+    pub mean_reward: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Node<M> {
+    action: Option<M>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<M>,
+    visits: u64,
+    /// Sum of rewards of the player who made the move leading here.
+    reward: f64,
+}
+
+/// Reward of Max for a finished playout: `1` for a win, `0.5` for a draw and `0` for a loss.
+fn max_reward<S: GameState>(state: &S) -> f64 {
+    match state.utility() {
+        0 => 0.5,
+        utility if utility > 0 => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// Monte Carlo tree search.
+///
+/// Every iteration descends the tree choosing children by UCT, expands one untried move,
+/// plays the game out with the rollout policy and propagates the result back to the root.
+/// Only the sign of the utility matters: positive is a win of Max, negative a win of Min, zero a draw.
+/// Players must alternate.
+///
+/// The tree is kept between searches, [`Mcts::advance`] moves its root to the subtree of the played move.
+pub struct Mcts<S: GameState, R: RolloutPolicy<S>> {
+    state: S,
+    nodes: Vec<Node<S::Move>>,
+    policy: R,
+    config: MctsConfig,
+    rng: XorShift64,
+}
+
+impl<S: GameState, R: RolloutPolicy<S>> Mcts<S, R> where S::Move: PartialEq {
+    const ROOT: usize = 0;
+
+    pub fn new(state: S, policy: R, config: MctsConfig) -> Self {
+        let root = Self::leaf(&state, None, None);
+        Self { state, nodes: vec![root], policy, config, rng: XorShift64::new(config.seed) }
+    }
+
+    fn leaf(state: &S, action: Option<S::Move>, parent: Option<usize>) -> Node<S::Move> {
+        let untried = if state.is_terminal() { Vec::new() } else { state.legal_moves() };
+        Node { action, parent, children: Vec::new(), untried, visits: 0, reward: 0.0 }
+    }
+
+    /// State at the root of the tree.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Number of iterations that went through the root, including those of searches before [`Mcts::advance`].
+    pub fn root_visits(&self) -> u64 {
+        self.nodes[Self::ROOT].visits
+    }
+
+    /// Runs iterations until the budget is spent. Returns number of iterations made.
+    pub fn search(&mut self, budget: Budget) -> u64 {
+        let start = Instant::now();
+        let mut iterations = 0;
+        loop {
+            let done = match budget {
+                Budget::Iterations(limit) => iterations >= limit,
+                Budget::Time(time) => iterations > 0 && start.elapsed() >= time,
+            };
+            if done {
+                return iterations;
+            }
+            self.iterate();
+            iterations += 1;
+        }
+    }
+
+    fn iterate(&mut self) {
+        let mut node = Self::ROOT;
+        let mut state = self.state.clone();
+        let mut depth = 0;
+
+        /* Selection. */
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select_child(node);
+            state = state.apply(self.nodes[node].action.as_ref().expect("Only the root has no move."));
+            depth += 1;
+        }
+        /* Expansion. */
+        if !self.nodes[node].untried.is_empty() {
+            let index = self.rng.below(self.nodes[node].untried.len());
+            let action = self.nodes[node].untried.swap_remove(index);
+            state = state.apply(&action);
+            let child = self.nodes.len();
+            self.nodes.push(Self::leaf(&state, Some(action), Some(node)));
+            self.nodes[node].children.push(child);
+            node = child;
+            depth += 1;
+        }
+        /* Playout. */
+        let mut length = 0;
+        while !state.is_terminal() && length < self.config.max_rollout_length {
+            let moves = state.legal_moves();
+            let choice = self.policy.choose(&state, &moves, &mut self.rng);
+            state = state.apply(&moves[choice]);
+            length += 1;
+        }
+        let reward = if state.is_terminal() { max_reward(&state) } else { 0.5 };
+
+        /* Backpropagation, every node is scored for the player who moved into it. */
+        let mut mover = match depth % 2 {
+            0 => self.state.to_move().opponent(),
+            _ => self.state.to_move(),
+        };
+        let mut current = Some(node);
+        while let Some(index) = current {
+            self.nodes[index].visits += 1;
+            self.nodes[index].reward += match mover {
+                Player::Max => reward,
+                Player::Min => 1.0 - reward,
+            };
+            current = self.nodes[index].parent;
+            mover = mover.opponent();
+        }
+    }
+
+    /// Child maximising the upper confidence bound of its reward.
+    fn select_child(&self, node: usize) -> usize {
+        let log_visits = (self.nodes[node].visits.max(1) as f64).ln();
+        let uct = |child: usize| {
+            let child = &self.nodes[child];
+            let visits = child.visits as f64;
+            child.reward / visits + self.config.exploration * (log_visits / visits).sqrt()
+        };
+        self.nodes[node].children.iter().copied()
+            .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+            .expect("Node has children.")
+    }
+
+    /// Statistics of the explored moves of the root, most visited first.
+    pub fn root_stats(&self) -> Vec<MoveStats<S::Move>> {
+        let mut stats = self.nodes[Self::ROOT].children.iter().map(|&child| {
+            let child = &self.nodes[child];
+            MoveStats {
+                action: child.action.clone().expect("Only the root has no move."),
+                visits: child.visits,
+                mean_reward: child.reward / child.visits.max(1) as f64,
+            }
+        }).collect::<Vec<_>>();
+        stats.sort_by_key(|stats| std::cmp::Reverse(stats.visits));
+        stats
+    }
+
+    /// Most visited move of the root.
+    pub fn best_move(&self) -> Option<S::Move> {
+        self.root_stats().into_iter().next().map(|stats| stats.action)
+    }
+
+    /// Makes the move, keeping the subtree below it for the next search.
+    pub fn advance(&mut self, action: &S::Move) {
+        self.state = self.state.apply(action);
+        let child = self.nodes[Self::ROOT].children.iter().copied()
+            .find(|&child| self.nodes[child].action.as_ref() == Some(action));
+        let Some(child) = child else {
+            self.nodes = vec![Self::leaf(&self.state, None, None)];
+            return;
+        };
+
+        /* Copy the subtree to a fresh arena, the rest of the tree is dropped. */
+        let mut nodes: Vec<Node<S::Move>> = Vec::new();
+        let mut stack = vec![(child, None)];
+        while let Some((old, parent)) = stack.pop() {
+            let index = nodes.len();
+            let node = &self.nodes[old];
+            nodes.push(Node {
+                action: parent.and(node.action.clone()),
+                parent,
+                children: Vec::new(),
+                untried: node.untried.clone(),
+                visits: node.visits,
+                reward: node.reward,
+            });
+            if let Some(parent) = parent {
+                nodes[parent].children.push(index);
+            }
+            stack.extend(node.children.iter().map(|&child| (child, Some(index))));
+        }
+        self.nodes = nodes;
+    }
+}
+
+/// Root-parallel search: independent trees are searched on `threads` threads with consecutive seeds
+/// and statistics of the root moves are summed. Returns the merged statistics, most visited first.
+pub fn root_parallel<S, R>(state: &S, policy: &R, config: MctsConfig, budget: Budget, threads: usize) -> Vec<MoveStats<S::Move>>
+where
+    S: GameState + Sync,
+    S::Move: PartialEq + Send,
+    R: RolloutPolicy<S> + Clone + Send,
+{
+    let results = std::thread::scope(|scope| {
+        let handles = (0..threads.max(1) as u64).map(|thread| {
+            let config = MctsConfig { seed: config.seed.wrapping_add(thread), ..config };
+            let policy = policy.clone();
+            scope.spawn(move || {
+                let mut mcts = Mcts::new(state.clone(), policy, config);
+                mcts.search(budget);
+                mcts.root_stats()
+            })
+        }).collect::<Vec<_>>();
+        handles.into_iter().map(|handle| handle.join().expect("Search thread panicked.")).collect::<Vec<_>>()
+    });
+
+    let mut merged: Vec<MoveStats<S::Move>> = Vec::new();
+    for stats in results.into_iter().flatten() {
+        match merged.iter_mut().find(|other| other.action == stats.action) {
+            Some(other) => {
+                let visits = other.visits + stats.visits;
+                let reward = other.mean_reward * other.visits as f64 + stats.mean_reward * stats.visits as f64;
+                other.mean_reward = reward / visits.max(1) as f64;
+                other.visits = visits;
+            }
+            None => merged.push(stats),
+        }
+    }
+    merged.sort_by_key(|stats| std::cmp::Reverse(stats.visits));
+    merged
+}
+
+#[cfg(test)]
+mod tests_mcts {
+    use std::time::Duration;
+
+    use super::{root_parallel, Budget, Mcts, MctsConfig, RandomRollout};
+    use crate::adversarial::GameState;
+    use crate::rng::XorShift64;
+    use crate::test_games::TicTacToe;
+
+    fn config(seed: u64) -> MctsConfig {
+        MctsConfig { seed, ..MctsConfig::default() }
+    }
+
+    #[test]
+    fn test_finds_winning_and_blocking_moves() {
+        /* Crosses on 0 and 4, noughts on 1 and 2: crosses win on 8. */
+        let mut mcts = Mcts::new(TicTacToe::from_moves(&[0, 1, 4, 2]), RandomRollout, config(1));
+        mcts.search(Budget::Iterations(2000));
+        assert_eq!(mcts.best_move(), Some(8));
+        assert!(mcts.root_stats()[0].mean_reward > 0.9);
+
+        /* Noughts must block crosses on 8. */
+        let mut mcts = Mcts::new(TicTacToe::from_moves(&[0, 1, 4]), RandomRollout, config(2));
+        mcts.search(Budget::Iterations(3000));
+        assert_eq!(mcts.best_move(), Some(8));
+    }
+
+    #[test]
+    fn test_reproducible_with_seed() {
+        let run = |seed| {
+            let mut mcts = Mcts::new(TicTacToe::new(), RandomRollout, config(seed));
+            mcts.search(Budget::Iterations(500));
+            mcts.root_stats().into_iter().map(|stats| (stats.action, stats.visits)).collect::<Vec<_>>()
+        };
+        assert_eq!(run(5), run(5));
+        assert_ne!(run(5), run(6));
+    }
+
+    #[test]
+    fn test_tree_reuse() {
+        let mut mcts = Mcts::new(TicTacToe::new(), RandomRollout, config(3));
+        assert_eq!(mcts.search(Budget::Iterations(1000)), 1000);
+        let best = mcts.best_move().unwrap();
+        let visits = mcts.root_stats()[0].visits;
+        mcts.advance(&best);
+        assert_eq!(mcts.root_visits(), visits);
+        assert_eq!(mcts.state(), &TicTacToe::from_moves(&[best]));
+        mcts.search(Budget::Iterations(100));
+        assert_eq!(mcts.root_visits(), visits + 100);
+        /* Every visit of the new root but the one that created it goes through one of its children. */
+        assert_eq!(mcts.root_stats().iter().map(|stats| stats.visits).sum::<u64>(), visits + 100 - 1);
+    }
+
+    #[test]
+    fn test_pluggable_policy_and_time_budget() {
+        /* Policy always taking the first legal move still finds the immediate win. */
+        let first = |_: &TicTacToe, _: &[usize], _: &mut XorShift64| 0;
+        let mut mcts = Mcts::new(TicTacToe::from_moves(&[0, 1, 4, 2]), first, config(4));
+        assert!(mcts.search(Budget::Time(Duration::from_millis(20))) > 0);
+        assert_eq!(mcts.best_move(), Some(8));
+    }
+
+    #[test]
+    fn test_root_parallel() {
+        let state = TicTacToe::from_moves(&[0, 1, 4]);
+        let stats = root_parallel(&state, &RandomRollout, config(7), Budget::Iterations(1000), 4);
+        assert_eq!(stats[0].action, 8);
+        assert_eq!(stats.iter().map(|stats| stats.visits).sum::<u64>(), 4 * 1000);
+        assert!(stats.iter().all(|stats| state.legal_moves().contains(&stats.action)));
+    }
+}
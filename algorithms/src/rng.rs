@@ -0,0 +1,61 @@
+/// Small seedable pseudo random number generator (xorshift64*).
+/// Good enough for random playouts and generated puzzles, where reproducibility matters more than quality.
+#[derive(Debug, Clone)]
+pub struct XorShift64(u64);
+
+impl XorShift64 {
+    pub fn new(seed: u64) -> Self {
+        /* State must not be zero, scramble the seed with one splitmix64 round. */
+        let mut state = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Self((state ^ (state >> 31)).max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniformly distributed number in range `[0, bound)`, `bound` must be positive.
+    pub fn below(&mut self, bound: usize) -> usize {
+        /* Multiply-shift maps numbers to the range, rejecting the few low products that would make it biased. */
+        let bound = bound as u64;
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = u128::from(self.next_u64()) * u128::from(bound);
+            if product as u64 >= threshold {
+                return (product >> 64) as usize;
+            }
+        }
+    }
+
+    /// Uniformly distributed number in range `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns true with given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+#[cfg(test)]
+mod tests_rng {
+    use super::XorShift64;
+
+    #[test]
+    fn test_below_is_uniform() {
+        let mut rng = XorShift64::new(1);
+        let mut counts = [0; 3];
+        for _ in 0..30_000 {
+            counts[rng.below(3)] += 1;
+        }
+        assert!(counts.iter().all(|&count| (9_500..10_500).contains(&count)), "{:?}", counts);
+        assert!((0..100).all(|_| rng.below(1) == 0));
+        assert!((0..100).all(|_| rng.below(usize::MAX) < usize::MAX));
+    }
+}
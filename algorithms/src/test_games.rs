@@ -0,0 +1,75 @@
+//! Small games shared by tests of the search algorithms.
+
+use crate::adversarial::{GameState, Player};
+use crate::engine::{EngineState, Zobrist};
+
+/// Tic-tac-toe, Max plays crosses. Board holds `0` for empty squares, `1` for crosses and `2` for noughts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TicTacToe {
+    pub board: [u8; 9],
+    key: u64,
+}
+
+const LINES: [[usize; 3]; 8] = [[0, 1, 2], [3, 4, 5], [6, 7, 8], [0, 3, 6], [1, 4, 7], [2, 5, 8], [0, 4, 8], [2, 4, 6]];
+
+thread_local! {
+    static KEYS: Zobrist = Zobrist::new(18, 42);
+}
+
+impl TicTacToe {
+    pub fn new() -> Self {
+        Self { board: [0; 9], key: 0 }
+    }
+
+    /// Position after the moves, made alternately from the empty board.
+    pub fn from_moves(moves: &[usize]) -> Self {
+        moves.iter().fold(Self::new(), |state, action| state.apply(action))
+    }
+
+    pub fn winner(&self) -> Option<u8> {
+        LINES.iter().map(|line| line.map(|square| self.board[square]))
+            .find(|line| line[0] != 0 && line[0] == line[1] && line[1] == line[2])
+            .map(|line| line[0])
+    }
+}
+
+impl GameState for TicTacToe {
+    type Move = usize;
+
+    fn to_move(&self) -> Player {
+        match self.board.iter().filter(|&&square| square != 0).count() % 2 {
+            0 => Player::Max,
+            _ => Player::Min,
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<Self::Move> {
+        (0..9).filter(|&square| self.board[square] == 0).collect()
+    }
+
+    fn apply(&self, action: &Self::Move) -> Self {
+        let piece = if self.to_move() == Player::Max { 1 } else { 2 };
+        let mut next = *self;
+        next.board[*action] = piece;
+        next.key ^= KEYS.with(|keys| keys.key(*action * 2 + piece as usize - 1));
+        next
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.winner().is_some() || self.board.iter().all(|&square| square != 0)
+    }
+
+    fn utility(&self) -> i32 {
+        match self.winner() {
+            Some(1) => 1,
+            Some(_) => -1,
+            None => 0,
+        }
+    }
+}
+
+impl EngineState for TicTacToe {
+    fn hash_key(&self) -> u64 {
+        self.key
+    }
+}
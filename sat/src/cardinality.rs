@@ -0,0 +1,98 @@
+//! Cardinality constraints encoded with sequential counters (Sinz, 2005).
+//!
+//! Counter for `n` literals and bound `k` uses `(n - 1) * k` auxiliary variables and about `2nk` clauses,
+//! and unit propagation over it detects every violation of the bound.
+
+use crate::cnf::Cnf;
+use crate::lit::Lit;
+
+/// At most `k` of the literals are true.
+pub fn at_most(cnf: &mut Cnf, lits: &[Lit], k: usize) {
+    let n = lits.len();
+    if k >= n {
+        return;
+    }
+    if k == 0 {
+        for &lit in lits {
+            cnf.add_clause([!lit]);
+        }
+        return;
+    }
+    /* counters[i][j] - at least j + 1 of the first i + 1 literals are true. */
+    let counters = (0..n - 1).map(|_| cnf.new_vars(k)).collect::<Vec<_>>();
+    cnf.add_clause([!lits[0], counters[0][0].positive()]);
+    for counter in &counters[0][1..] {
+        cnf.add_clause([counter.negative()]);
+    }
+    for i in 1..n - 1 {
+        cnf.add_clause([!lits[i], counters[i][0].positive()]);
+        cnf.add_clause([counters[i - 1][0].negative(), counters[i][0].positive()]);
+        for j in 1..k {
+            cnf.add_clause([!lits[i], counters[i - 1][j - 1].negative(), counters[i][j].positive()]);
+            cnf.add_clause([counters[i - 1][j].negative(), counters[i][j].positive()]);
+        }
+        cnf.add_clause([!lits[i], counters[i - 1][k - 1].negative()]);
+    }
+    cnf.add_clause([!lits[n - 1], counters[n - 2][k - 1].negative()]);
+}
+
+/// At least `k` of the literals are true, that is at most `n - k` of them are false.
+pub fn at_least(cnf: &mut Cnf, lits: &[Lit], k: usize) {
+    match k {
+        0 => {}
+        1 => cnf.add_clause(lits.iter().copied()),
+        _ if k > lits.len() => cnf.add_clause([]),
+        _ => at_most(cnf, &lits.iter().map(|&lit| !lit).collect::<Vec<_>>(), lits.len() - k),
+    }
+}
+
+/// Exactly `k` of the literals are true.
+pub fn exactly(cnf: &mut Cnf, lits: &[Lit], k: usize) {
+    at_most(cnf, lits, k);
+    at_least(cnf, lits, k);
+}
+
+/// Exactly one of the literals is true.
+pub fn exactly_one(cnf: &mut Cnf, lits: &[Lit]) {
+    exactly(cnf, lits, 1);
+}
+
+#[cfg(test)]
+mod tests_cardinality {
+    use super::{at_least, at_most, exactly};
+    use crate::cnf::Cnf;
+    use crate::lit::{Lit, Var};
+    use crate::solver::{SolveResult, Solver};
+
+    /// Counts assignments of the first `n` variables that extend to a model.
+    fn count_projected(cnf: &Cnf, n: usize) -> usize {
+        let mut solver = Solver::from_cnf(cnf);
+        let mut count = 0;
+        while let SolveResult::Sat(model) = solver.solve() {
+            count += 1;
+            solver.add_clause(model.lits().take(n).map(|lit| !lit));
+        }
+        count
+    }
+
+    fn binomial(n: usize, k: usize) -> usize {
+        (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+    }
+
+    #[test]
+    fn test_counts_of_models() {
+        let n = 6;
+        let lits = (0..n as u32).map(|var| Var(var).positive()).collect::<Vec<Lit>>();
+        for k in 0..=n + 1 {
+            let encode = |constraint: fn(&mut Cnf, &[Lit], usize)| {
+                let mut cnf = Cnf::with_vars(n);
+                constraint(&mut cnf, &lits, k);
+                count_projected(&cnf, n)
+            };
+            let choose = |k| if k <= n { binomial(n, k) } else { 0 };
+            assert_eq!(encode(at_most), (0..=k).map(choose).sum::<usize>(), "at most {}", k);
+            assert_eq!(encode(at_least), (k..=n).map(choose).sum::<usize>(), "at least {}", k);
+            assert_eq!(encode(exactly), choose(k), "exactly {}", k);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::lit::{Lit, Var};
+
+/// Formula in conjunctive normal form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cnf {
+    var_count: usize,
+    clauses: Vec<Vec<Lit>>,
+}
+
+impl Cnf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates formula with `var_count` variables and no clauses.
+    pub fn with_vars(var_count: usize) -> Self {
+        Self { var_count, clauses: Vec::new() }
+    }
+
+    pub fn new_var(&mut self) -> Var {
+        self.var_count += 1;
+        Var(self.var_count as u32 - 1)
+    }
+
+    /// Creates `count` consecutive variables.
+    pub fn new_vars(&mut self, count: usize) -> Vec<Var> {
+        (0..count).map(|_| self.new_var()).collect()
+    }
+
+    /// Adds clause, growing the number of variables if the clause uses new ones.
+    pub fn add_clause(&mut self, clause: impl IntoIterator<Item=Lit>) {
+        let clause = clause.into_iter().collect::<Vec<_>>();
+        if let Some(max) = clause.iter().map(|lit| lit.var().index() + 1).max() {
+            self.var_count = self.var_count.max(max);
+        }
+        self.clauses.push(clause);
+    }
+
+    pub fn var_count(&self) -> usize {
+        self.var_count
+    }
+
+    pub fn clauses(&self) -> &[Vec<Lit>] {
+        &self.clauses
+    }
+
+    /// Checks if the assignment, indexed by variables, satisfies every clause.
+    pub fn is_satisfied_by(&self, values: &[bool]) -> bool {
+        self.clauses.iter().all(|clause| clause.iter().any(|lit| values[lit.var().index()] == lit.is_positive()))
+    }
+}
@@ -0,0 +1,203 @@
+//! DIMACS CNF format used by SAT solvers and competitions.
+//!
+//! ```text
+//! c comment
+//! p cnf 3 2
+//! 1 -2 0
+//! 2 3 -1 0
+//! ```
+//!
+//! Clauses are terminated by `0` and may span several lines. A line starting with `%` ends the formula,
+//! as in the SATLIB benchmarks.
+
+use std::fmt::{Display, Formatter};
+
+use crate::cnf::Cnf;
+use crate::lit::Lit;
+use crate::solver::SolveResult;
+
+/// Reason why the formula could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Clause appears before the `p cnf` line.
+    MissingHeader,
+    /// Problem line is not `p cnf <variables> <clauses>`.
+    InvalidHeader,
+    /// Second problem line.
+    DuplicateHeader,
+    /// Token is not an integer.
+    InvalidLiteral(String),
+    /// Literal uses variable above the number declared in the header.
+    VarOutOfRange { var: u64, var_count: usize },
+    /// Number of clauses differs from the header.
+    ClauseCountMismatch { expected: usize, found: usize },
+    /// Last clause is not terminated by `0`.
+    UnterminatedClause,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::MissingHeader => write!(f, "expected 'p cnf' line before clauses"),
+            ParseErrorKind::InvalidHeader => write!(f, "expected 'p cnf <variables> <clauses>'"),
+            ParseErrorKind::DuplicateHeader => write!(f, "second 'p cnf' line"),
+            ParseErrorKind::InvalidLiteral(token) => write!(f, "expected literal, got: {:?}", token),
+            ParseErrorKind::VarOutOfRange { var, var_count } => {
+                write!(f, "variable {} is out of range, header declares {} variables", var, var_count)
+            }
+            ParseErrorKind::ClauseCountMismatch { expected, found } => {
+                write!(f, "header declares {} clauses, found: {}", expected, found)
+            }
+            ParseErrorKind::UnterminatedClause => write!(f, "last clause is not terminated by 0"),
+        }
+    }
+}
+
+/// Parse error with position of its cause. Lines and columns are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, kind: ParseErrorKind) -> Self {
+        Self { line, column, kind }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Whitespace separated tokens of the line together with their columns.
+fn tokens(line: &str) -> impl Iterator<Item=(usize, &str)> {
+    line.split_whitespace().map(move |token| {
+        let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+        (line[..offset].chars().count() + 1, token)
+    })
+}
+
+/// Parses formula in the DIMACS CNF format.
+pub fn parse_dimacs(text: &str) -> Result<Cnf, ParseError> {
+    let mut header = None;
+    let mut cnf = Cnf::new();
+    let mut clause = Vec::new();
+    let mut position = (1, 1);
+
+    for (line_number, line) in text.lines().enumerate().map(|(index, line)| (index + 1, line)) {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('c') || trimmed.is_empty() {
+            continue;
+        }
+        if trimmed.starts_with('%') {
+            break;
+        }
+        if trimmed.starts_with('p') {
+            if header.is_some() {
+                return Err(ParseError::new(line_number, 1, ParseErrorKind::DuplicateHeader));
+            }
+            let words = tokens(line).map(|(_, word)| word).collect::<Vec<_>>();
+            let (var_count, clause_count) = match words.as_slice() {
+                ["p", "cnf", var_count, clause_count] => var_count.parse::<usize>().ok().zip(clause_count.parse::<usize>().ok()),
+                _ => None,
+            }.ok_or(ParseError::new(line_number, 1, ParseErrorKind::InvalidHeader))?;
+            header = Some((var_count, clause_count, line_number));
+            cnf = Cnf::with_vars(var_count);
+            continue;
+        }
+        for (column, token) in tokens(line) {
+            let &(var_count, _, _) = header.as_ref().ok_or(ParseError::new(line_number, column, ParseErrorKind::MissingHeader))?;
+            let number = token.parse::<i64>()
+                .map_err(|_| ParseError::new(line_number, column, ParseErrorKind::InvalidLiteral(token.to_string())))?;
+            position = (line_number, column);
+            if number == 0 {
+                cnf.add_clause(std::mem::take(&mut clause));
+                continue;
+            }
+            let var = number.unsigned_abs();
+            let lit = Lit::from_dimacs(number).filter(|_| var <= var_count as u64)
+                .ok_or(ParseError::new(line_number, column, ParseErrorKind::VarOutOfRange { var, var_count }))?;
+            clause.push(lit);
+        }
+    }
+
+    if !clause.is_empty() {
+        return Err(ParseError::new(position.0, position.1, ParseErrorKind::UnterminatedClause));
+    }
+    let (_, expected, header_line) = header.ok_or(ParseError::new(text.lines().count().max(1), 1, ParseErrorKind::MissingHeader))?;
+    if cnf.clauses().len() != expected {
+        let kind = ParseErrorKind::ClauseCountMismatch { expected, found: cnf.clauses().len() };
+        return Err(ParseError::new(header_line, 1, kind));
+    }
+    Ok(cnf)
+}
+
+/// Writes formula in the DIMACS CNF format.
+pub fn write_dimacs(cnf: &Cnf) -> String {
+    let mut dimacs = format!("p cnf {} {}\n", cnf.var_count(), cnf.clauses().len());
+    for clause in cnf.clauses() {
+        for lit in clause {
+            dimacs.push_str(&format!("{} ", lit));
+        }
+        dimacs.push_str("0\n");
+    }
+    dimacs
+}
+
+/// Writes result in the SAT competition output format: the `s` status line and `v` lines with the model.
+pub fn write_result(result: &SolveResult) -> String {
+    match result {
+        SolveResult::Sat(model) => {
+            let mut output = String::from("s SATISFIABLE\n");
+            let lits = model.lits().map(|lit| lit.to_string()).chain(["0".to_string()]).collect::<Vec<_>>();
+            for line in lits.chunks(10) {
+                output.push_str(&format!("v {}\n", line.join(" ")));
+            }
+            output
+        }
+        SolveResult::Unsat => String::from("s UNSATISFIABLE\n"),
+        SolveResult::Unknown => String::from("s UNKNOWN\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests_dimacs {
+    use super::{parse_dimacs, write_dimacs, write_result, ParseError, ParseErrorKind};
+    use crate::solver::{SolveResult, Solver};
+
+    #[test]
+    fn test_round_trip() {
+        let text = "c example\np cnf 3 2\n1 -2 0\n2 3\n-1 0\n%\n0\n";
+        let cnf = parse_dimacs(text).unwrap();
+        assert_eq!(cnf.var_count(), 3);
+        assert_eq!(write_dimacs(&cnf), "p cnf 3 2\n1 -2 0\n2 3 -1 0\n");
+        assert_eq!(parse_dimacs(&write_dimacs(&cnf)).unwrap(), cnf);
+    }
+
+    #[test]
+    fn test_write_result() {
+        let cnf = parse_dimacs("p cnf 2 2\n1 0\n-2 0\n").unwrap();
+        assert_eq!(write_result(&Solver::from_cnf(&cnf).solve()), "s SATISFIABLE\nv 1 -2 0\n");
+        assert_eq!(write_result(&SolveResult::Unsat), "s UNSATISFIABLE\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_dimacs("1 0\n"), Err(ParseError::new(1, 1, ParseErrorKind::MissingHeader)));
+        assert_eq!(parse_dimacs("p cnf x 1\n"), Err(ParseError::new(1, 1, ParseErrorKind::InvalidHeader)));
+        assert_eq!(parse_dimacs("p cnf 1 1\np cnf 1 1\n"), Err(ParseError::new(2, 1, ParseErrorKind::DuplicateHeader)));
+        let kind = ParseErrorKind::InvalidLiteral(String::from("a"));
+        assert_eq!(parse_dimacs("p cnf 1 1\n1 a 0\n"), Err(ParseError::new(2, 3, kind)));
+        let kind = ParseErrorKind::VarOutOfRange { var: 2, var_count: 1 };
+        assert_eq!(parse_dimacs("p cnf 1 1\n1 -2 0\n"), Err(ParseError::new(2, 3, kind)));
+        let kind = ParseErrorKind::ClauseCountMismatch { expected: 2, found: 1 };
+        assert_eq!(parse_dimacs("c\np cnf 1 2\n1 0\n"), Err(ParseError::new(2, 1, kind)));
+        assert_eq!(parse_dimacs("p cnf 1 1\n1\n"), Err(ParseError::new(2, 1, ParseErrorKind::UnterminatedClause)));
+    }
+}
@@ -0,0 +1,103 @@
+/// Max-heap of variables ordered by activity, with positions kept for fast updates.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct VarHeap {
+    heap: Vec<u32>,
+    /// Position of every variable in the heap, `usize::MAX` if it is not there.
+    positions: Vec<usize>,
+}
+
+impl VarHeap {
+    pub fn grow(&mut self, var_count: usize) {
+        self.positions.resize(var_count, usize::MAX);
+    }
+
+    pub fn contains(&self, var: usize) -> bool {
+        self.positions[var] != usize::MAX
+    }
+
+    pub fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            return;
+        }
+        self.positions[var] = self.heap.len();
+        self.heap.push(var as u32);
+        self.sift_up(self.heap.len() - 1, activity);
+    }
+
+    /// Restores heap order after the activity of the variable increased.
+    pub fn increased(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            self.sift_up(self.positions[var], activity);
+        }
+    }
+
+    pub fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let top = *self.heap.first()? as usize;
+        let last = self.heap.pop().expect("Heap is not empty.");
+        self.positions[top] = usize::MAX;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.positions[last as usize] = 0;
+            self.sift_down(0, activity);
+        }
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut position: usize, activity: &[f64]) {
+        let var = self.heap[position];
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if activity[self.heap[parent] as usize] >= activity[var as usize] {
+                break;
+            }
+            self.heap[position] = self.heap[parent];
+            self.positions[self.heap[position] as usize] = position;
+            position = parent;
+        }
+        self.heap[position] = var;
+        self.positions[var as usize] = position;
+    }
+
+    fn sift_down(&mut self, mut position: usize, activity: &[f64]) {
+        let var = self.heap[position];
+        loop {
+            let left = 2 * position + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = match right < self.heap.len() && activity[self.heap[right] as usize] > activity[self.heap[left] as usize] {
+                true => right,
+                false => left,
+            };
+            if activity[self.heap[child] as usize] <= activity[var as usize] {
+                break;
+            }
+            self.heap[position] = self.heap[child];
+            self.positions[self.heap[position] as usize] = position;
+            position = child;
+        }
+        self.heap[position] = var;
+        self.positions[var as usize] = position;
+    }
+}
+
+#[cfg(test)]
+mod tests_heap {
+    use super::VarHeap;
+
+    #[test]
+    fn test_order() {
+        let mut activity = vec![3.0, 1.0, 4.0, 1.5, 9.0, 2.6];
+        let mut heap = VarHeap::default();
+        heap.grow(activity.len());
+        for var in 0..activity.len() {
+            heap.insert(var, &activity);
+        }
+        activity[1] = 10.0;
+        heap.increased(1, &activity);
+        let order = std::iter::from_fn(|| heap.pop(&activity)).collect::<Vec<_>>();
+        assert_eq!(order, [1, 4, 2, 0, 5, 3]);
+        assert!(!heap.contains(1));
+    }
+}
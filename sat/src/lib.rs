@@ -0,0 +1,8 @@
+pub mod cardinality;
+pub mod cnf;
+pub mod dimacs;
+mod heap;
+pub mod lit;
+pub mod model;
+pub mod proof;
+pub mod solver;
@@ -0,0 +1,92 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Not;
+
+/// Boolean variable, numbered from 0. DIMACS numbers variables from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Var(pub u32);
+
+impl Var {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn positive(self) -> Lit {
+        Lit::new(self, true)
+    }
+
+    pub fn negative(self) -> Lit {
+        Lit::new(self, false)
+    }
+}
+
+/// Variable or its negation, stored as `2 * var + negated` so literals can index arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: Var, positive: bool) -> Self {
+        Self(var.0 << 1 | u32::from(!positive))
+    }
+
+    pub fn var(self) -> Var {
+        Var(self.0 >> 1)
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 & 1 == 0
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Literal written as a DIMACS integer, `None` for `0` which terminates clauses.
+    pub fn from_dimacs(number: i64) -> Option<Self> {
+        let var = u32::try_from(number.unsigned_abs().checked_sub(1)?).ok().filter(|&var| var < u32::MAX >> 1)?;
+        Some(Self::new(Var(var), number > 0))
+    }
+
+    pub fn to_dimacs(self) -> i64 {
+        let number = i64::from(self.var().0) + 1;
+        if self.is_positive() { number } else { -number }
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Self::Output {
+        Lit(self.0 ^ 1)
+    }
+}
+
+impl From<Var> for Lit {
+    fn from(var: Var) -> Self {
+        var.positive()
+    }
+}
+
+/// Writes literal as a DIMACS integer.
+impl Display for Lit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_dimacs())
+    }
+}
+
+#[cfg(test)]
+mod tests_lit {
+    use super::{Lit, Var};
+
+    #[test]
+    fn test_encoding() {
+        let lit = Var(3).negative();
+        assert_eq!(lit.var(), Var(3));
+        assert!(!lit.is_positive());
+        assert_eq!(!lit, Var(3).positive());
+        assert_eq!(lit.to_dimacs(), -4);
+        assert_eq!(Lit::from_dimacs(-4), Some(lit));
+        assert_eq!(Lit::from_dimacs(1).map(Lit::index), Some(0));
+        assert_eq!(Lit::from_dimacs(0), None);
+        assert_eq!(lit.to_string(), "-4");
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+use sat::dimacs::{parse_dimacs, write_result};
+use sat::solver::{SolveResult, Solver};
+
+/// Exit codes used by SAT competitions.
+const EXIT_SAT: i32 = 10;
+const EXIT_UNSAT: i32 = 20;
+
+fn usage() -> ! {
+    eprintln!("Usage: sat [FILE.cnf] [--proof FILE.drat]\nReads the formula from standard input if no file is given.");
+    process::exit(1);
+}
+
+fn main() {
+    let mut input = None;
+    let mut proof_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--proof" => proof_path = Some(args.next().unwrap_or_else(|| usage())),
+            "-h" | "--help" => usage(),
+            _ if input.is_none() => input = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let text = match &input {
+        Some(path) => fs::read_to_string(path),
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).map(|_| text)
+        }
+    }.unwrap_or_else(|error| {
+        eprintln!("Could not read the formula: {}", error);
+        process::exit(1);
+    });
+    let cnf = parse_dimacs(&text).unwrap_or_else(|error| {
+        eprintln!("Could not parse the formula: {}", error);
+        process::exit(1);
+    });
+
+    let mut solver = Solver::from_cnf(&cnf);
+    if proof_path.is_some() {
+        solver.enable_proof();
+    }
+    let result = solver.solve();
+    let stats = solver.stats();
+    println!("c decisions {} propagations {} conflicts {} restarts {}", stats.decisions, stats.propagations, stats.conflicts, stats.restarts);
+    print!("{}", write_result(&result));
+    if let (Some(path), Some(proof)) = (proof_path, solver.proof()) {
+        fs::write(&path, proof.to_drat()).unwrap_or_else(|error| eprintln!("Could not write {}: {}", path, error));
+    }
+    process::exit(match result {
+        SolveResult::Sat(_) => EXIT_SAT,
+        SolveResult::Unsat => EXIT_UNSAT,
+        SolveResult::Unknown => 0,
+    });
+}
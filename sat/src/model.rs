@@ -0,0 +1,32 @@
+use crate::lit::{Lit, Var};
+
+/// Satisfying assignment of all variables of the formula.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Model {
+    values: Vec<bool>,
+}
+
+impl Model {
+    pub(crate) fn new(values: Vec<bool>) -> Self {
+        Self { values }
+    }
+
+    pub fn value(&self, var: Var) -> bool {
+        self.values[var.index()]
+    }
+
+    /// Checks if the literal is true in the model.
+    pub fn satisfies(&self, lit: Lit) -> bool {
+        self.value(lit.var()) == lit.is_positive()
+    }
+
+    /// Values of the variables, indexed by variables.
+    pub fn values(&self) -> &[bool] {
+        &self.values
+    }
+
+    /// True literal of every variable.
+    pub fn lits(&self) -> impl Iterator<Item=Lit> + '_ {
+        self.values.iter().enumerate().map(|(var, &value)| Lit::new(Var(var as u32), value))
+    }
+}
@@ -0,0 +1,155 @@
+use crate::cnf::Cnf;
+use crate::lit::Lit;
+
+/// Step of a clausal proof of unsatisfiability.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofStep {
+    /// Learnt clause, implied by the clauses known so far.
+    Add(Vec<Lit>),
+    /// Clause that is no longer needed.
+    Delete(Vec<Lit>),
+}
+
+/// Clausal proof recorded by the solver, ending with the empty clause if the formula is unsatisfiable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Proof {
+    steps: Vec<ProofStep>,
+}
+
+impl Proof {
+    pub fn steps(&self) -> &[ProofStep] {
+        &self.steps
+    }
+
+    pub(crate) fn add(&mut self, clause: &[Lit]) {
+        self.steps.push(ProofStep::Add(clause.to_vec()));
+    }
+
+    pub(crate) fn delete(&mut self, clause: &[Lit]) {
+        self.steps.push(ProofStep::Delete(clause.to_vec()));
+    }
+
+    /// Writes the proof in the DRAT format, understood by checkers like `drat-trim`.
+    pub fn to_drat(&self) -> String {
+        let mut drat = String::new();
+        for step in &self.steps {
+            let (prefix, clause) = match step {
+                ProofStep::Add(clause) => ("", clause),
+                ProofStep::Delete(clause) => ("d ", clause),
+            };
+            drat.push_str(prefix);
+            for lit in clause {
+                drat.push_str(&format!("{} ", lit));
+            }
+            drat.push_str("0\n");
+        }
+        drat
+    }
+
+    /// Checks that every added clause follows from the formula and previous clauses by unit propagation
+    /// and that the proof derives the empty clause.
+    ///
+    /// The check propagates over all clauses without watches, which is fine for small formulas only.
+    pub fn verify(&self, cnf: &Cnf) -> bool {
+        let mut clauses = cnf.clauses().to_vec();
+        let mut var_count = cnf.var_count();
+        for step in &self.steps {
+            match step {
+                ProofStep::Add(clause) => {
+                    var_count = clause.iter().map(|lit| lit.var().index() + 1).fold(var_count, usize::max);
+                    if !has_unit_refutation(&clauses, clause, var_count) {
+                        return false;
+                    }
+                    if clause.is_empty() {
+                        return true;
+                    }
+                    clauses.push(clause.clone());
+                }
+                ProofStep::Delete(clause) => {
+                    let mut sorted = clause.clone();
+                    sorted.sort_unstable();
+                    if let Some(index) = clauses.iter().position(|other| {
+                        let mut other = other.clone();
+                        other.sort_unstable();
+                        other == sorted
+                    }) {
+                        clauses.swap_remove(index);
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Checks if assuming negation of the clause leads to conflict by unit propagation.
+fn has_unit_refutation(clauses: &[Vec<Lit>], clause: &[Lit], var_count: usize) -> bool {
+    let mut values = vec![None; var_count];
+    for &lit in clause {
+        match values[lit.var().index()] {
+            Some(positive) if positive == lit.is_positive() => return true,
+            _ => values[lit.var().index()] = Some(!lit.is_positive()),
+        }
+    }
+    loop {
+        let mut progress = false;
+        for clause in clauses {
+            let mut unassigned = None;
+            let mut open = 0;
+            let mut satisfied = false;
+            for &lit in clause {
+                match values.get(lit.var().index()).copied().flatten() {
+                    Some(value) if value == lit.is_positive() => satisfied = true,
+                    Some(_) => {}
+                    None if unassigned == Some(lit) => {}
+                    None => {
+                        open += 1;
+                        unassigned = Some(lit);
+                    }
+                }
+            }
+            match (satisfied, open, unassigned) {
+                (true, _, _) => {}
+                (false, 0, _) => return true,
+                (false, 1, Some(lit)) => {
+                    values[lit.var().index()] = Some(lit.is_positive());
+                    progress = true;
+                }
+                _ => {}
+            }
+        }
+        if !progress {
+            return false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_proof {
+    use super::Proof;
+    use crate::cnf::Cnf;
+    use crate::lit::Lit;
+
+    fn clause(numbers: &[i64]) -> Vec<Lit> {
+        numbers.iter().map(|&number| Lit::from_dimacs(number).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_verify_and_write() {
+        let mut cnf = Cnf::new();
+        for numbers in [[1, 2], [1, -2], [-1, 2], [-1, -2]] {
+            cnf.add_clause(clause(&numbers));
+        }
+        let mut proof = Proof::default();
+        proof.add(&clause(&[1]));
+        proof.delete(&clause(&[1, 2]));
+        proof.add(&[]);
+        assert!(proof.verify(&cnf));
+        assert_eq!(proof.to_drat(), "1 0\nd 1 2 0\n0\n");
+
+        let mut wrong = Proof::default();
+        wrong.add(&clause(&[2, 3]));
+        wrong.add(&[]);
+        assert!(!wrong.verify(&Cnf::new()));
+    }
+}
@@ -0,0 +1,671 @@
+use crate::cnf::Cnf;
+use crate::heap::VarHeap;
+use crate::lit::{Lit, Var};
+use crate::model::Model;
+use crate::proof::Proof;
+
+/// Outcome of [`Solver::solve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveResult {
+    Sat(Model),
+    Unsat,
+    /// Conflict limit was reached before the answer was found.
+    Unknown,
+}
+
+impl SolveResult {
+    pub fn model(&self) -> Option<&Model> {
+        match self {
+            SolveResult::Sat(model) => Some(model),
+            _ => None,
+        }
+    }
+}
+
+/// Counters collected during solving.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub decisions: u64,
+    pub propagations: u64,
+    pub conflicts: u64,
+    pub restarts: u64,
+    pub learnt_clauses: u64,
+    pub deleted_clauses: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Clause {
+    lits: Vec<Lit>,
+    learnt: bool,
+    activity: f64,
+    deleted: bool,
+}
+
+/// Clause watching a literal. Blocker is some other literal of the clause, if it is true the clause is skipped.
+#[derive(Debug, Clone, Copy)]
+struct Watcher {
+    clause: usize,
+    blocker: Lit,
+}
+
+/// Conflicts between restarts are this number times the Luby sequence.
+const RESTART_BASE: u64 = 100;
+const VAR_DECAY: f64 = 0.95;
+const CLAUSE_DECAY: f64 = 0.999;
+const RESCALE_LIMIT: f64 = 1e100;
+
+fn lit_value(values: &[Option<bool>], lit: Lit) -> Option<bool> {
+    values[lit.var().index()].map(|value| value == lit.is_positive())
+}
+
+/// Element `index` of the Luby sequence `1, 1, 2, 1, 1, 2, 4, 1, ...`.
+fn luby(mut index: u64) -> u64 {
+    let (mut size, mut power) = (1, 0);
+    while size < index + 1 {
+        power += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != index {
+        size = (size - 1) / 2;
+        power -= 1;
+        index %= size;
+    }
+    1 << power
+}
+
+/// Conflict driven clause learning SAT solver.
+///
+/// - unit propagation uses two watched literals per clause with blocking literals,
+/// - decisions pick the unassigned variable of the highest VSIDS activity with its saved phase,
+/// - conflicts are analysed up to the first unique implication point, learnt clauses are minimised
+///   by dropping literals implied by the other ones,
+/// - restarts follow the Luby sequence and the least active half of the learnt clauses is dropped
+///   when there are too many of them.
+///
+/// Clauses may be added between calls to [`Solver::solve`], so blocking clauses can enumerate all models.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    clauses: Vec<Clause>,
+    /// Watchers by literal, clauses in `watches[lit]` watch `!lit` and are visited once `lit` becomes true.
+    watches: Vec<Vec<Watcher>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_limits: Vec<usize>,
+    queue_head: usize,
+    activity: Vec<f64>,
+    var_increment: f64,
+    clause_increment: f64,
+    heap: VarHeap,
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+    learnt_count: usize,
+    max_learnts: f64,
+    unsat: bool,
+    conflict_limit: Option<u64>,
+    proof: Option<Proof>,
+    stats: Stats,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            clauses: Vec::new(),
+            watches: Vec::new(),
+            values: Vec::new(),
+            levels: Vec::new(),
+            reasons: Vec::new(),
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            queue_head: 0,
+            activity: Vec::new(),
+            var_increment: 1.0,
+            clause_increment: 1.0,
+            heap: VarHeap::default(),
+            phases: Vec::new(),
+            seen: Vec::new(),
+            learnt_count: 0,
+            max_learnts: 0.0,
+            unsat: false,
+            conflict_limit: None,
+            proof: None,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Creates solver with variables and clauses of the formula.
+    pub fn from_cnf(cnf: &Cnf) -> Self {
+        let mut solver = Self::new();
+        solver.reserve_vars(cnf.var_count());
+        for clause in cnf.clauses() {
+            solver.add_clause(clause.iter().copied());
+        }
+        solver
+    }
+
+    pub fn new_var(&mut self) -> Var {
+        let var = self.values.len();
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.phases.push(false);
+        self.seen.push(false);
+        self.watches.extend([Vec::new(), Vec::new()]);
+        self.heap.grow(var + 1);
+        self.heap.insert(var, &self.activity);
+        Var(var as u32)
+    }
+
+    /// Makes sure that variables up to `var_count - 1` exist.
+    pub fn reserve_vars(&mut self, var_count: usize) {
+        while self.var_count() < var_count {
+            self.new_var();
+        }
+    }
+
+    pub fn var_count(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Starts recording a clausal proof. Clauses added so far are treated as the input formula.
+    pub fn enable_proof(&mut self) {
+        if self.proof.is_none() {
+            self.proof = Some(Proof::default());
+            /* Unit propagation over the input already failed, the empty clause follows from it. */
+            if self.unsat {
+                self.log_add(&[]);
+            }
+        }
+    }
+
+    pub fn proof(&self) -> Option<&Proof> {
+        self.proof.as_ref()
+    }
+
+    /// Limits the number of conflicts of each call to [`Solver::solve`], `None` for no limit.
+    pub fn set_conflict_limit(&mut self, limit: Option<u64>) {
+        self.conflict_limit = limit;
+    }
+
+    /// Adds clause, creating variables it mentions. Returns `false` if the formula became unsatisfiable.
+    pub fn add_clause(&mut self, clause: impl IntoIterator<Item=Lit>) -> bool {
+        self.cancel_until(0);
+        let mut lits = clause.into_iter().collect::<Vec<_>>();
+        if let Some(max) = lits.iter().map(|lit| lit.var().index() + 1).max() {
+            self.reserve_vars(max);
+        }
+        if self.unsat {
+            return false;
+        }
+        lits.sort_unstable();
+        lits.dedup();
+        let original_length = lits.len();
+        if lits.windows(2).any(|pair| pair[0] == !pair[1]) || lits.iter().any(|&lit| self.value(lit) == Some(true)) {
+            return true;
+        }
+        lits.retain(|&lit| self.value(lit).is_none());
+        if lits.len() < original_length {
+            self.log_add(&lits);
+        }
+
+        match lits.len() {
+            0 => self.set_unsat(),
+            1 => {
+                self.enqueue(lits[0], None);
+                if self.propagate().is_some() {
+                    self.set_unsat();
+                }
+            }
+            _ => {
+                self.attach(lits, false);
+            }
+        }
+        !self.unsat
+    }
+
+    /// Value of the literal under the current assignment.
+    fn value(&self, lit: Lit) -> Option<bool> {
+        lit_value(&self.values, lit)
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    fn set_unsat(&mut self) {
+        self.unsat = true;
+        self.log_add(&[]);
+    }
+
+    fn log_add(&mut self, clause: &[Lit]) {
+        if let Some(proof) = &mut self.proof {
+            proof.add(clause);
+        }
+    }
+
+    fn attach(&mut self, lits: Vec<Lit>, learnt: bool) -> usize {
+        let clause = self.clauses.len();
+        self.watches[(!lits[0]).index()].push(Watcher { clause, blocker: lits[1] });
+        self.watches[(!lits[1]).index()].push(Watcher { clause, blocker: lits[0] });
+        self.clauses.push(Clause { lits, learnt, activity: 0.0, deleted: false });
+        if learnt {
+            self.learnt_count += 1;
+            self.stats.learnt_clauses += 1;
+        }
+        clause
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var().index();
+        self.values[var] = Some(lit.is_positive());
+        self.levels[var] = self.decision_level();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Propagates enqueued literals. Returns conflicting clause, if any.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.queue_head < self.trail.len() {
+            let lit = self.trail[self.queue_head];
+            self.queue_head += 1;
+            self.stats.propagations += 1;
+            let false_lit = !lit;
+            let mut watchers = std::mem::take(&mut self.watches[lit.index()]);
+            let (mut read, mut write) = (0, 0);
+            let mut conflict = None;
+
+            while read < watchers.len() {
+                let watcher = watchers[read];
+                read += 1;
+                if lit_value(&self.values, watcher.blocker) == Some(true) {
+                    watchers[write] = watcher;
+                    write += 1;
+                    continue;
+                }
+                let clause = &mut self.clauses[watcher.clause];
+                if clause.deleted {
+                    continue;
+                }
+                /* Keep the false literal in the second position. */
+                if clause.lits[0] == false_lit {
+                    clause.lits.swap(0, 1);
+                }
+                let first = clause.lits[0];
+                let kept = Watcher { clause: watcher.clause, blocker: first };
+                if first != watcher.blocker && lit_value(&self.values, first) == Some(true) {
+                    watchers[write] = kept;
+                    write += 1;
+                    continue;
+                }
+
+                let replacement = (2..clause.lits.len()).find(|&index| lit_value(&self.values, clause.lits[index]) != Some(false));
+                if let Some(index) = replacement {
+                    clause.lits.swap(1, index);
+                    self.watches[(!clause.lits[1]).index()].push(kept);
+                    continue;
+                }
+
+                watchers[write] = kept;
+                write += 1;
+                if lit_value(&self.values, first) == Some(false) {
+                    conflict = Some(watcher.clause);
+                    while read < watchers.len() {
+                        watchers[write] = watchers[read];
+                        write += 1;
+                        read += 1;
+                    }
+                } else {
+                    self.enqueue(first, Some(watcher.clause));
+                }
+            }
+            watchers.truncate(write);
+            self.watches[lit.index()] = watchers;
+            if conflict.is_some() {
+                self.queue_head = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump_var(&mut self, var: usize) {
+        self.activity[var] += self.var_increment;
+        if self.activity[var] > RESCALE_LIMIT {
+            self.activity.iter_mut().for_each(|activity| *activity /= RESCALE_LIMIT);
+            self.var_increment /= RESCALE_LIMIT;
+        }
+        self.heap.increased(var, &self.activity);
+    }
+
+    fn bump_clause(&mut self, clause: usize) {
+        self.clauses[clause].activity += self.clause_increment;
+        if self.clauses[clause].activity > RESCALE_LIMIT {
+            self.clauses.iter_mut().filter(|clause| clause.learnt).for_each(|clause| clause.activity /= RESCALE_LIMIT);
+            self.clause_increment /= RESCALE_LIMIT;
+        }
+    }
+
+    /// Learns clause from the conflict. Returns the clause with the asserting literal first,
+    /// and the level to jump back to, where the clause becomes unit.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![Lit::new(Var(0), true)];
+        let mut open = 0;
+        let mut clause = conflict;
+        let mut index = self.trail.len();
+        let mut implied = None;
+
+        loop {
+            if self.clauses[clause].learnt {
+                self.bump_clause(clause);
+            }
+            /* The first literal of a reason is the one it implied. */
+            let start = usize::from(implied.is_some());
+            for position in start..self.clauses[clause].lits.len() {
+                let lit = self.clauses[clause].lits[position];
+                let var = lit.var().index();
+                if !self.seen[var] && self.levels[var] > 0 {
+                    self.seen[var] = true;
+                    self.bump_var(var);
+                    if self.levels[var] >= self.decision_level() {
+                        open += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var().index()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[lit.var().index()] = false;
+            implied = Some(lit);
+            open -= 1;
+            if open == 0 {
+                break;
+            }
+            clause = self.reasons[lit.var().index()].expect("Only the decision has no reason.");
+        }
+        learnt[0] = !implied.expect("Conflict has a literal on the current level.");
+
+        /* Drop literals whose reason consists of literals already in the clause. */
+        let candidates = learnt.clone();
+        learnt.retain(|&lit| match self.reasons[lit.var().index()] {
+            Some(reason) if lit != candidates[0] => !self.clauses[reason].lits[1..].iter()
+                .all(|other| self.seen[other.var().index()] || self.levels[other.var().index()] == 0),
+            _ => true,
+        });
+        for lit in &candidates {
+            self.seen[lit.var().index()] = false;
+        }
+
+        let level = match learnt.len() {
+            1 => 0,
+            _ => {
+                let highest = (1..learnt.len()).max_by_key(|&index| self.levels[learnt[index].var().index()]).expect("Clause has a second literal.");
+                learnt.swap(1, highest);
+                self.levels[learnt[1].var().index()]
+            }
+        };
+        (learnt, level)
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let limit = self.trail_limits[level];
+        for lit in self.trail.drain(limit..) {
+            let var = lit.var().index();
+            self.values[var] = None;
+            self.reasons[var] = None;
+            self.phases[var] = lit.is_positive();
+            self.heap.insert(var, &self.activity);
+        }
+        self.queue_head = limit;
+        self.trail_limits.truncate(level);
+    }
+
+    /// Unassigned variable of the highest activity, with its saved phase.
+    fn pick_branch(&mut self) -> Option<Lit> {
+        while let Some(var) = self.heap.pop(&self.activity) {
+            if self.values[var].is_none() {
+                return Some(Lit::new(Var(var as u32), self.phases[var]));
+            }
+        }
+        None
+    }
+
+    /// Deletes the least active half of the learnt clauses that are not reasons of current assignments.
+    fn reduce_learnts(&mut self) {
+        let is_locked = |solver: &Self, clause: usize| {
+            let first = solver.clauses[clause].lits[0];
+            solver.reasons[first.var().index()] == Some(clause) && solver.value(first) == Some(true)
+        };
+        let mut learnts = (0..self.clauses.len())
+            .filter(|&clause| self.clauses[clause].learnt && !self.clauses[clause].deleted && self.clauses[clause].lits.len() > 2)
+            .filter(|&clause| !is_locked(self, clause))
+            .collect::<Vec<_>>();
+        learnts.sort_by(|&a, &b| self.clauses[a].activity.total_cmp(&self.clauses[b].activity));
+        for &clause in &learnts[..learnts.len() / 2] {
+            let lits = std::mem::take(&mut self.clauses[clause].lits);
+            if let Some(proof) = &mut self.proof {
+                proof.delete(&lits);
+            }
+            self.clauses[clause].deleted = true;
+            self.learnt_count -= 1;
+            self.stats.deleted_clauses += 1;
+        }
+    }
+
+    /// Decides satisfiability of the clauses added so far.
+    pub fn solve(&mut self) -> SolveResult {
+        if self.unsat {
+            return SolveResult::Unsat;
+        }
+        self.cancel_until(0);
+        if self.propagate().is_some() {
+            self.set_unsat();
+            return SolveResult::Unsat;
+        }
+        self.max_learnts = (self.clauses.len() as f64 / 3.0).max(100.0);
+        let conflict_limit = self.conflict_limit.map(|limit| self.stats.conflicts + limit);
+        for restart in 0.. {
+            if let Some(result) = self.search(luby(restart) * RESTART_BASE, conflict_limit) {
+                self.cancel_until(0);
+                return result;
+            }
+            self.stats.restarts += 1;
+            self.max_learnts *= 1.1;
+        }
+        unreachable!("Search returns a result or restarts.")
+    }
+
+    /// Searches until a result is found or `budget` conflicts happen, in which case it returns `None`.
+    fn search(&mut self, budget: u64, conflict_limit: Option<u64>) -> Option<SolveResult> {
+        let mut conflicts = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.stats.conflicts += 1;
+                conflicts += 1;
+                if self.decision_level() == 0 {
+                    self.set_unsat();
+                    return Some(SolveResult::Unsat);
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.cancel_until(level);
+                self.log_add(&learnt);
+                let asserting = learnt[0];
+                match learnt.len() {
+                    1 => self.enqueue(asserting, None),
+                    _ => {
+                        let clause = self.attach(learnt, true);
+                        self.bump_clause(clause);
+                        self.enqueue(asserting, Some(clause));
+                    }
+                }
+                self.var_increment /= VAR_DECAY;
+                self.clause_increment /= CLAUSE_DECAY;
+                if conflict_limit.is_some_and(|limit| self.stats.conflicts >= limit) {
+                    return Some(SolveResult::Unknown);
+                }
+            } else {
+                if conflicts >= budget {
+                    self.cancel_until(0);
+                    return None;
+                }
+                if self.learnt_count as f64 >= self.max_learnts + self.trail.len() as f64 {
+                    self.reduce_learnts();
+                }
+                match self.pick_branch() {
+                    None => {
+                        let values = self.values.iter().map(|value| value.expect("All variables are assigned.")).collect();
+                        return Some(SolveResult::Sat(Model::new(values)));
+                    }
+                    Some(lit) => {
+                        self.stats.decisions += 1;
+                        self.trail_limits.push(self.trail.len());
+                        self.enqueue(lit, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_solver {
+    use super::{luby, SolveResult, Solver};
+    use crate::cnf::Cnf;
+    use crate::lit::{Lit, Var};
+
+    fn clause(numbers: &[i64]) -> Vec<Lit> {
+        numbers.iter().map(|&number| Lit::from_dimacs(number).unwrap()).collect()
+    }
+
+    fn cnf(clauses: &[&[i64]]) -> Cnf {
+        let mut cnf = Cnf::new();
+        for numbers in clauses {
+            cnf.add_clause(clause(numbers));
+        }
+        cnf
+    }
+
+    /// Pigeons `0..=holes` in `holes` holes, each hole holding at most one pigeon.
+    fn pigeonhole(holes: usize) -> Cnf {
+        let mut cnf = Cnf::new();
+        let var = |pigeon: usize, hole: usize| Var((pigeon * holes + hole) as u32);
+        for pigeon in 0..=holes {
+            cnf.add_clause((0..holes).map(|hole| var(pigeon, hole).positive()));
+        }
+        for hole in 0..holes {
+            for first in 0..=holes {
+                for second in first + 1..=holes {
+                    cnf.add_clause([var(first, hole).negative(), var(second, hole).negative()]);
+                }
+            }
+        }
+        cnf
+    }
+
+    /// Random 3-SAT formula from a fixed xorshift sequence.
+    fn random_cnf(seed: u64, var_count: usize, clause_count: usize) -> Cnf {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut cnf = Cnf::with_vars(var_count);
+        for _ in 0..clause_count {
+            cnf.add_clause((0..3).map(|_| Lit::new(Var((next() % var_count as u64) as u32), next() % 2 == 0)));
+        }
+        cnf
+    }
+
+    fn brute_force(cnf: &Cnf) -> bool {
+        (0..1u32 << cnf.var_count()).any(|bits| {
+            let values = (0..cnf.var_count()).map(|var| bits & 1 << var != 0).collect::<Vec<_>>();
+            cnf.is_satisfied_by(&values)
+        })
+    }
+
+    #[test]
+    fn test_luby() {
+        assert_eq!((0..15).map(luby).collect::<Vec<_>>(), [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn test_small_formulas() {
+        let formula = cnf(&[&[1, 2], &[-1, 3], &[-2, -3], &[-3]]);
+        let result = Solver::from_cnf(&formula).solve();
+        let model = result.model().unwrap();
+        assert!(formula.is_satisfied_by(model.values()));
+        assert_eq!(model.lits().map(Lit::to_dimacs).collect::<Vec<_>>(), [-1, 2, -3]);
+
+        assert_eq!(Solver::from_cnf(&cnf(&[&[1], &[-1, 2], &[-2]])).solve(), SolveResult::Unsat);
+        assert_eq!(Solver::from_cnf(&cnf(&[&[]])).solve(), SolveResult::Unsat);
+        assert!(Solver::from_cnf(&cnf(&[&[1, -1]])).solve().model().is_some());
+    }
+
+    #[test]
+    fn test_random_formulas_against_brute_force() {
+        for seed in 1..200 {
+            let formula = random_cnf(seed, 10, 43);
+            let mut solver = Solver::from_cnf(&formula);
+            solver.enable_proof();
+            match solver.solve() {
+                SolveResult::Sat(model) => assert!(formula.is_satisfied_by(model.values())),
+                SolveResult::Unsat => {
+                    assert!(!brute_force(&formula), "seed {}", seed);
+                    assert!(solver.proof().unwrap().verify(&formula));
+                }
+                SolveResult::Unknown => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_pigeonhole() {
+        let formula = pigeonhole(6);
+        let mut solver = Solver::from_cnf(&formula);
+        solver.enable_proof();
+        assert_eq!(solver.solve(), SolveResult::Unsat);
+        assert!(solver.stats().conflicts > 0);
+        assert!(solver.proof().unwrap().verify(&formula));
+    }
+
+    #[test]
+    fn test_conflict_limit() {
+        let mut solver = Solver::from_cnf(&pigeonhole(8));
+        solver.set_conflict_limit(Some(10));
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+    }
+
+    #[test]
+    fn test_enumerate_models_with_blocking_clauses() {
+        /* Exactly one of three variables is true. */
+        let mut solver = Solver::from_cnf(&cnf(&[&[1, 2, 3], &[-1, -2], &[-1, -3], &[-2, -3]]));
+        let mut models = 0;
+        while let SolveResult::Sat(model) = solver.solve() {
+            models += 1;
+            solver.add_clause(model.lits().map(|lit| !lit));
+        }
+        assert_eq!(models, 3);
+    }
+}
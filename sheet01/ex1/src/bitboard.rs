@@ -0,0 +1,161 @@
+//! Sets of tiles as 64-bit masks, with attack tables of all pieces.
+//!
+//! Bit `n` stands for the tile with encoded position `n`, see [`State::encode_position`],
+//! so moving one row up adds 1 to the index and moving one column right adds 8.
+
+use crate::position::{Piece, KNIGHT_MOVES};
+use crate::{Color, Pos, State, KING_MOVES};
+
+pub type Bitboard = u64;
+
+/// Bitboard with just the tile.
+pub const fn bit(square: Pos) -> Bitboard {
+    1 << square
+}
+
+/// Tiles of the bitboard, from the lowest index.
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Pos> {
+    std::iter::from_fn(move || {
+        (bitboard != 0).then(|| {
+            let square = bitboard.trailing_zeros() as Pos;
+            bitboard &= bitboard - 1;
+            square
+        })
+    })
+}
+
+/// Tile reached from the square with the step, `None` off the board.
+const fn offset(square: usize, (step_col, step_row): (Pos, Pos)) -> Option<Pos> {
+    let (col, row) = State::decode_position(square as Pos);
+    let (col, row) = (col + step_col, row + step_row);
+    if 0 <= col && col < 8 && 0 <= row && row < 8 {
+        Some(State::encode_position(col, row))
+    } else {
+        None
+    }
+}
+
+/// Tiles reached with one of the steps from each square.
+const fn step_attacks(steps: &[(Pos, Pos)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut index = 0;
+        while index < steps.len() {
+            if let Some(to) = offset(square, steps[index]) {
+                table[square] |= bit(to);
+            }
+            index += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Tiles from each square to the edge in each of [`KING_MOVES`] directions, without the square itself.
+const fn rays() -> [[Bitboard; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let mut current = square;
+            while let Some(to) = offset(current, KING_MOVES[direction]) {
+                table[direction][square] |= bit(to);
+                current = to as usize;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+pub const KING_ATTACKS: [Bitboard; 64] = step_attacks(&KING_MOVES);
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = step_attacks(&KNIGHT_MOVES);
+/// Tiles pawns of each color capture on, white first.
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [step_attacks(&[(-1, 1), (1, 1)]), step_attacks(&[(-1, -1), (1, -1)])];
+const RAYS: [[Bitboard; 64]; 8] = rays();
+
+/// Indices of [`KING_MOVES`] along columns and rows.
+const ROOK_RAYS: [usize; 4] = [1, 3, 4, 6];
+/// Indices of [`KING_MOVES`] along diagonals.
+const BISHOP_RAYS: [usize; 4] = [0, 2, 5, 7];
+
+/// Tiles of the ray up to and including the first occupied one.
+fn ray_attacks(square: Pos, direction: usize, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][square as usize];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    // rays going to higher indices meet their lowest blocker first.
+    let (step_col, step_row) = KING_MOVES[direction];
+    let blocker = if step_col * 8 + step_row > 0 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray & !RAYS[direction][blocker as usize]
+}
+
+fn sliding_attacks(square: Pos, directions: &[usize], occupied: Bitboard) -> Bitboard {
+    directions.iter().fold(0, |attacks, &direction| attacks | ray_attacks(square, direction, occupied))
+}
+
+pub fn rook_attacks(square: Pos, occupied: Bitboard) -> Bitboard {
+    sliding_attacks(square, &ROOK_RAYS, occupied)
+}
+
+pub fn bishop_attacks(square: Pos, occupied: Bitboard) -> Bitboard {
+    sliding_attacks(square, &BISHOP_RAYS, occupied)
+}
+
+/// Tiles the piece standing on the square attacks, sliding pieces stop at occupied tiles.
+/// Pawns attack only the tiles they capture on.
+pub fn attacks(piece: Piece, color: Color, square: Pos, occupied: Bitboard) -> Bitboard {
+    match piece {
+        Piece::King => KING_ATTACKS[square as usize],
+        Piece::Knight => KNIGHT_ATTACKS[square as usize],
+        Piece::Rook => rook_attacks(square, occupied),
+        Piece::Bishop => bishop_attacks(square, occupied),
+        Piece::Queen => rook_attacks(square, occupied) | bishop_attacks(square, occupied),
+        Piece::Pawn => PAWN_ATTACKS[match color {
+            Color::White => 0,
+            Color::Black => 1,
+        }][square as usize],
+    }
+}
+
+#[cfg(test)]
+mod tests_bitboard {
+    use super::{attacks, bishop_attacks, bit, rook_attacks, squares, Bitboard, KING_ATTACKS, KNIGHT_ATTACKS};
+    use crate::position::{square_from_str, Piece};
+    use crate::{Color, State};
+
+    fn board(tiles: &str) -> Bitboard {
+        tiles.split_whitespace().fold(0, |board, tile| board | bit(square_from_str(tile).unwrap()))
+    }
+
+    fn tiles(bitboard: Bitboard) -> String {
+        squares(bitboard).map(State::position_to_string).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn test_step_attacks() {
+        assert_eq!(tiles(KING_ATTACKS[0]), "a2 b1 b2");
+        assert_eq!(KING_ATTACKS[square_from_str("d4").unwrap() as usize].count_ones(), 8);
+        assert_eq!(tiles(KNIGHT_ATTACKS[0]), "b3 c2");
+        assert_eq!(tiles(attacks(Piece::Pawn, Color::White, square_from_str("e4").unwrap(), 0)), "d5 f5");
+        assert_eq!(tiles(attacks(Piece::Pawn, Color::Black, square_from_str("a5").unwrap(), 0)), "b4");
+    }
+
+    #[test]
+    fn test_sliding_attacks() {
+        let square = square_from_str("d4").unwrap();
+        assert_eq!(rook_attacks(square, 0).count_ones(), 14);
+        assert_eq!(tiles(rook_attacks(square, board("d2 d6 b4 g4"))), "b4 c4 d2 d3 d5 d6 e4 f4 g4");
+        assert_eq!(tiles(bishop_attacks(square, board("b2 f6 c5"))), "b2 c3 c5 e3 e5 f2 f6 g1");
+        assert_eq!(attacks(Piece::Queen, Color::White, square, 0), rook_attacks(square, 0) | bishop_attacks(square, 0));
+    }
+}
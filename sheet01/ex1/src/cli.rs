@@ -0,0 +1,253 @@
+//! Command line of the solver: puzzles are read one per line and answered one per line,
+//! a line that can not be solved is reported without stopping the others.
+
+use std::io::{self, BufRead, Write};
+
+use crate::endgame::EndgameTablebase;
+use crate::notation::{self, FenError};
+use crate::position::Position;
+use crate::tablebase::Tablebase;
+use crate::{Format, Mode, State, TraversalResult};
+
+pub const USAGE: &str = "Usage: main [--input FILE|-] [--output FILE|-] [--mode helpmate|optimal] \
+    [--format count|path|san|lan|pgn] [--tablebase FILE] [--endgames DIR] [--debug]";
+
+/// Path standing for the standard input or output.
+pub const STDIO: &str = "-";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub input: String,
+    pub output: String,
+    pub mode: Mode,
+    pub format: Format,
+    /// File the KRK tablebase is loaded from or saved to, see [`Tablebase::load_or_generate`].
+    pub tablebase: Option<String>,
+    /// Directory tables of other endgames are loaded from or saved to, see [`EndgameTablebase::with_directory`].
+    pub endgames: Option<String>,
+    /// Prints the board after each move of every solution.
+    pub debug: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            input: String::from("zad1_input.txt"),
+            output: String::from("zad1_output.txt"),
+            mode: Mode::Helpmate,
+            format: Format::Count,
+            tablebase: None,
+            endgames: None,
+            debug: false,
+        }
+    }
+}
+
+impl Options {
+    /// Parses arguments without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("missing value after {}", arg));
+            match arg.as_str() {
+                "--input" | "-i" => options.input = value()?,
+                "--output" | "-o" => options.output = value()?,
+                "--mode" => options.mode = value()?.parse()?,
+                "--format" => options.format = value()?.parse()?,
+                "--tablebase" => options.tablebase = Some(value()?),
+                "--endgames" => options.endgames = Some(value()?),
+                "--debug" => options.debug = true,
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Position of one line of the input.
+enum Puzzle {
+    Krk(State),
+    Other(Position),
+}
+
+/// Reads positions in FEN, with piece letters like `white Kc4 Qd1 black Kh8` or as KRK tiles like `white c4 c8 h3`.
+/// KRK positions keep using the KRK solvers and tablebase.
+fn parse_puzzle(line: &str) -> Result<Puzzle, String> {
+    if line.contains('/') {
+        return match State::from_fen(line) {
+            Ok(state) => Ok(Puzzle::Krk(state)),
+            Err(FenError::NotKrk) => Position::from_fen(line).map(Puzzle::Other).map_err(|error| error.to_string()),
+            Err(error) => Err(format!("invalid FEN: {}", error)),
+        };
+    }
+    if line.split_whitespace().nth(1).is_some_and(|token| token.starts_with(char::is_uppercase)) {
+        let position = line.parse::<Position>().map_err(|error| format!("invalid position: {}", error))?;
+        return Ok(match State::try_from(&position) {
+            Ok(state) => Puzzle::Krk(state),
+            Err(_) => Puzzle::Other(position),
+        });
+    }
+    line.parse().map(Puzzle::Krk).map_err(|error| format!("invalid position: {}", error))
+}
+
+/// Solves puzzles one after another, sharing tablebases between them.
+pub struct Solver {
+    mode: Mode,
+    tablebase_path: Option<String>,
+    krk: Option<Tablebase>,
+    endgames: EndgameTablebase,
+}
+
+impl Solver {
+    pub fn new(mode: Mode, tablebase_path: Option<String>, endgames_path: Option<String>) -> Self {
+        let endgames = match endgames_path {
+            Some(path) => EndgameTablebase::with_directory(path),
+            None => EndgameTablebase::new(),
+        };
+        Self { mode, tablebase_path, krk: None, endgames }
+    }
+
+    /// KRK tablebase, loaded or generated on first use.
+    /// Without a path the table is generated for this run only.
+    fn krk(&mut self) -> Result<&Tablebase, String> {
+        if self.krk.is_none() {
+            let tablebase = match &self.tablebase_path {
+                Some(path) => Tablebase::load_or_generate(path).map_err(|error| format!("cannot use tablebase {}: {}", path, error))?,
+                None => Tablebase::generate(),
+            };
+            self.krk = Some(tablebase);
+        }
+        Ok(self.krk.as_ref().expect("Tablebase was just set."))
+    }
+
+    /// Line to the mate of the position on the line of input.
+    pub fn solve(&mut self, line: &str) -> Result<TraversalResult<Position>, String> {
+        let result = match (parse_puzzle(line)?, self.mode) {
+            (Puzzle::Krk(state), Mode::Helpmate) => state.solve(),
+            (Puzzle::Krk(state), Mode::Optimal) => state.solve_optimal(self.krk()?),
+            (Puzzle::Other(position), Mode::Helpmate) => return Ok(position.solve()),
+            (Puzzle::Other(position), Mode::Optimal) => return Ok(match self.endgames.line(&position).map_err(|error| error.to_string())? {
+                None => TraversalResult::Inf,
+                Some(line) => TraversalResult::CheckMate(line),
+            }),
+        };
+        Ok(match result {
+            TraversalResult::Inf => TraversalResult::Inf,
+            TraversalResult::CheckMate(path) => TraversalResult::CheckMate(path.iter().map(Position::from).collect()),
+        })
+    }
+}
+
+/// The answer in the format, INF if there is no mate.
+pub fn answer(result: &TraversalResult<Position>, format: Format) -> String {
+    match result {
+        TraversalResult::Inf => String::from("INF"),
+        TraversalResult::CheckMate(path) => match format {
+            Format::Count => (path.len() - 1).to_string(),
+            Format::Path => path.iter().map(Position::to_string).collect::<Vec<_>>().join("; "),
+            Format::Line(notation) => notation::line_to_string(path, notation),
+            Format::Pgn => notation::to_pgn(path),
+        },
+    }
+}
+
+/// Prints the start position and the board after each move of the line.
+fn write_debug(path: &[Position], log: &mut impl Write) -> io::Result<()> {
+    if let Some(first) = path.first() {
+        writeln!(log, "{}\n{}", first.to_fen(), first.diagram())?;
+    }
+    for pair in path.windows(2) {
+        let move_text = notation::to_lan(&pair[0], &pair[1]).unwrap_or_default();
+        writeln!(log, "{} {}\n{}", pair[0].turn(), move_text, pair[1].diagram())?;
+    }
+    Ok(())
+}
+
+/// Answers each non-empty line of the input with one line of the output, see [`answer`].
+/// Lines that fail get an `ERROR` answer and a message in the log, the number of such lines is returned.
+pub fn run(options: &Options, input: impl BufRead, mut output: impl Write, mut log: impl Write) -> io::Result<usize> {
+    let mut solver = Solver::new(options.mode, options.tablebase.clone(), options.endgames.clone());
+    let mut failed = 0;
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match solver.solve(&line) {
+            Ok(result) => {
+                if let (true, TraversalResult::CheckMate(path)) = (options.debug, &result) {
+                    write_debug(path, &mut log)?;
+                }
+                writeln!(output, "{}", answer(&result, options.format))?;
+            }
+            Err(error) => {
+                failed += 1;
+                writeln!(log, "line {}: {}", number + 1, error)?;
+                writeln!(output, "ERROR {}", error)?;
+            }
+        }
+    }
+    output.flush()?;
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests_cli {
+    use super::{run, Options};
+    use crate::notation::Notation;
+    use crate::{Format, Mode};
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn solve(options: &Options, input: &str) -> (String, String, usize) {
+        let (mut output, mut log) = (Vec::new(), Vec::new());
+        let failed = run(options, input.as_bytes(), &mut output, &mut log).unwrap();
+        (String::from_utf8(output).unwrap(), String::from_utf8(log).unwrap(), failed)
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(Options::parse(args("")), Ok(Options::default()));
+        let options = Options::parse(args("-i - --output out.txt --mode optimal --format lan --tablebase krk.bin --endgames tables --debug")).unwrap();
+        assert_eq!(options, Options {
+            input: String::from("-"),
+            output: String::from("out.txt"),
+            mode: Mode::Optimal,
+            format: Format::Line(Notation::Lan),
+            tablebase: Some(String::from("krk.bin")),
+            endgames: Some(String::from("tables")),
+            debug: true,
+        });
+        assert!(Options::parse(args("--mode")).is_err());
+        assert!(Options::parse(args("--format tree")).is_err());
+        assert!(Options::parse(args("--verbose")).is_err());
+    }
+
+    #[test]
+    fn test_lines_and_errors() {
+        let input = "black g8 h1 c4\n\nwhite c4 c4 h3\nblack c8 h7 a8\nwhite Kb6 Qh7 black Ka8\nk7/7Q/1K6/8/8/8/8/8 w\nblack c4 c8\n";
+        let (output, log, failed) = solve(&Options::default(), input);
+        assert_eq!(output, "10\nERROR invalid position: illegal position\nINF\n1\n1\nERROR invalid position: expected 3 tiles, found 2\n");
+        assert_eq!(log, "line 3: invalid position: illegal position\nline 7: invalid position: expected 3 tiles, found 2\n");
+        assert_eq!(failed, 2);
+
+        let options = Options { format: Format::Path, ..Options::default() };
+        let (output, _, _) = solve(&options, "white Kb6 Qh7 black Ka8");
+        assert_eq!(output, "white Kb6 Qh7 black Ka8; black Ka8 white Kb6 Qa7\n");
+        let options = Options { format: Format::Line(Notation::San), ..Options::default() };
+        assert_eq!(solve(&options, "2R5/8/8/8/2K5/7k/8/8 w KQ").0, "ERROR invalid FEN: castling and en passant are not supported: KQ\n");
+    }
+
+    #[test]
+    fn test_debug_boards() {
+        let options = Options { debug: true, ..Options::default() };
+        let (output, log, _) = solve(&options, "white Kb6 Qh7 black Ka8");
+        assert_eq!(output, "1\n");
+        assert!(log.starts_with("k7/7Q/1K6/8/8/8/8/8 w - - 0 1\n8 k . . . . . . .\n7 . . . . . . . Q\n"), "{}", log);
+        assert!(log.contains("white Qh7-a7#\n8 k . . . . . . .\n7 Q . . . . . . .\n"), "{}", log);
+        assert!(log.ends_with("  a b c d e f g h\n\n"), "{}", log);
+    }
+}
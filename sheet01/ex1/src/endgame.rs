@@ -0,0 +1,551 @@
+//! Retrograde analysis of endgames with any material, the generalisation of [`crate::tablebase`].
+//!
+//! Tables are generated on demand for each material signature. Captures and promotions lead
+//! to positions with other material, whose tables are generated first.
+//! Positions equal up to a symmetry of the board share their entry, see [`Layout`].
+//! Tables may be kept in a directory, one file per material, like the KRK table of [`crate::tablebase`].
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::bitboard::{bit, KING_ATTACKS};
+use crate::position::{Piece, Position};
+use crate::{Color, Pos, State};
+
+/// Number of squares of the board.
+const SQUARES: usize = 64;
+
+/// Most pieces, kings included, of materials whose tables are generated.
+/// Tables of five pieces would take hundreds of megabytes and far too long to generate.
+pub const MAX_PIECES: usize = 4;
+
+const ILLEGAL: u8 = u8::MAX;
+/// Draws and positions not decided yet during generation.
+const DRAW: u8 = u8::MAX - 1;
+/// Marks positions with a move to a drawn position with other material.
+const ESCAPE: u8 = u8::MAX;
+
+const MAGIC: &[u8] = b"ENDGAME DTM 1\n";
+
+/// Pieces of a position regardless of their tiles, sorted the same way as pieces of positions.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub struct Material(Vec<(Color, Piece)>);
+
+impl Material {
+    pub fn of(position: &Position) -> Self {
+        Self(position.pieces().iter().map(|&(color, piece, _)| (color, piece)).collect())
+    }
+
+    /// Checks if the position has exactly these pieces.
+    fn matches(&self, position: &Position) -> bool {
+        position.pieces().len() == self.0.len()
+            && position.pieces().iter().zip(&self.0).all(|(&(color, piece, _), &other)| (color, piece) == other)
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.0.iter().any(|&(_, piece)| piece == Piece::Pawn)
+    }
+}
+
+/// Lists white pieces and black pieces, like `KRvK`.
+impl Display for Material {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for color in [Color::White, Color::Black] {
+            if color == Color::Black {
+                write!(f, "v")?;
+            }
+            for &(_, piece) in self.0.iter().filter(|&&(other, _)| other == color) {
+                write!(f, "{}", piece.letter())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndgameError {
+    /// Material with more than [`MAX_PIECES`] pieces.
+    UnsupportedMaterial(Material),
+    /// Table file that can not be read or written, with the reason.
+    File(PathBuf, String),
+}
+
+impl Display for EndgameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EndgameError::UnsupportedMaterial(material) => {
+                write!(f, "endgames of {} pieces like {} are not supported, at most {} pieces are", material.0.len(), material, MAX_PIECES)
+            }
+            EndgameError::File(path, reason) => write!(f, "cannot use endgame table {}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for EndgameError {}
+
+/// Value of a position for the side to move, with optimal play of both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Illegal,
+    Draw,
+    /// The side to move mates in given number of plies.
+    Win(u8),
+    /// The side to move is mated in given number of plies, `0` if it already is.
+    Loss(u8),
+}
+
+impl Value {
+    fn decode(value: u8) -> Self {
+        match value {
+            ILLEGAL => Value::Illegal,
+            DRAW => Value::Draw,
+            plies if plies % 2 == 1 => Value::Win(plies),
+            plies => Value::Loss(plies),
+        }
+    }
+}
+
+/// Symmetries of the board, bit 0 mirrors columns, bit 1 mirrors rows and bit 2 swaps columns with rows.
+const ALL_SYMMETRIES: [u8; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+/// Pawns move along columns in one direction only, so only mirroring columns keeps their moves.
+const PAWN_SYMMETRIES: [u8; 2] = [0, 1];
+
+/// Tile the square is moved to by the symmetry.
+fn transform(square: Pos, symmetry: u8) -> Pos {
+    let (col, row) = State::decode_position(square);
+    let (col, row) = if symmetry & 4 != 0 { (row, col) } else { (col, row) };
+    let col = if symmetry & 1 != 0 { 7 - col } else { col };
+    let row = if symmetry & 2 != 0 { 7 - row } else { row };
+    State::encode_position(col, row)
+}
+
+/// Numbering of positions of one material.
+///
+/// Kings take one of the pairs of tiles that are not next to each other and that no symmetry of the board maps
+/// to a smaller pair, other pieces take any tile. Of all symmetric placements the one with the smallest index is used,
+/// so each entry stands for positions that are equal up to a symmetry and have the same value.
+struct Layout {
+    symmetries: &'static [u8],
+    /// Tiles of the black and the white king of each pair.
+    king_pairs: Vec<(Pos, Pos)>,
+    /// Number of the pair with the black king on tile `n / 64` and the white king on tile `n % 64`.
+    pair_numbers: Vec<Option<usize>>,
+    /// Index of the white king among the pieces, the black king is always the first piece.
+    white_king: usize,
+    /// Number of pieces other than kings.
+    others: usize,
+}
+
+impl Layout {
+    /// Layout of the material, which must have exactly one king of each color.
+    fn new(material: &Material) -> Self {
+        let symmetries: &'static [u8] = if material.has_pawns() { &PAWN_SYMMETRIES } else { &ALL_SYMMETRIES };
+        let mut king_pairs = Vec::new();
+        let mut pair_numbers = vec![None; SQUARES * SQUARES];
+        for black_king in 0..SQUARES as Pos {
+            for white_king in 0..SQUARES as Pos {
+                let apart = KING_ATTACKS[black_king as usize] & bit(white_king) == 0 && black_king != white_king;
+                let smallest = symmetries.iter()
+                    .all(|&symmetry| (black_king, white_king) <= (transform(black_king, symmetry), transform(white_king, symmetry)));
+                if apart && smallest {
+                    pair_numbers[black_king as usize * SQUARES + white_king as usize] = Some(king_pairs.len());
+                    king_pairs.push((black_king, white_king));
+                }
+            }
+        }
+        let white_king = material.0.iter().position(|&piece| piece == (Color::White, Piece::King)).expect("White has a king.");
+        Self { symmetries, king_pairs, pair_numbers, white_king, others: material.0.len() - 2 }
+    }
+
+    /// Number of entries of the table, including illegal and repeated ones.
+    fn table_size(&self) -> usize {
+        2 * self.king_pairs.len() * SQUARES.pow(self.others as u32)
+    }
+
+    /// Index of the legal position in the table.
+    fn index_of(&self, position: &Position) -> usize {
+        let turn = match position.turn() {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+        let pieces = position.pieces();
+        let (black_king, white_king) = (pieces[0].2, pieces[self.white_king].2);
+        self.symmetries.iter().filter_map(|&symmetry| {
+            let pair = self.pair_numbers[transform(black_king, symmetry) as usize * SQUARES + transform(white_king, symmetry) as usize]?;
+            /* Identical pieces may swap their order, the kings keep their places. */
+            let mut moved = [(Color::White, Piece::King, 0); MAX_PIECES];
+            for (target, &(color, piece, square)) in moved.iter_mut().zip(pieces) {
+                *target = (color, piece, transform(square, symmetry));
+            }
+            moved[..pieces.len()].sort_unstable();
+            let others = moved[..pieces.len()].iter().enumerate().filter(|&(index, _)| index != 0 && index != self.white_king);
+            Some(others.fold(turn * self.king_pairs.len() + pair, |index, (_, &(_, _, square))| index * SQUARES + square as usize))
+        }).min().expect("Some symmetry moves the kings to one of the pairs.")
+    }
+
+    /// Legal position stored at the index of the table, `None` for indices not used by [`Layout::index_of`].
+    /// Identical pieces must be listed in order of their tiles, other orders are skipped.
+    fn position_at(&self, material: &Material, index: usize) -> Option<Position> {
+        let mut rest = index;
+        let mut squares = Vec::with_capacity(self.others);
+        for _ in 0..self.others {
+            squares.push((rest % SQUARES) as Pos);
+            rest /= SQUARES;
+        }
+        let (black_king, white_king) = self.king_pairs[rest % self.king_pairs.len()];
+        let turn = if rest < self.king_pairs.len() { Color::White } else { Color::Black };
+        let pieces = material.0.iter().enumerate().map(|(piece_index, &(color, piece))| match piece_index {
+            0 => (color, piece, black_king),
+            _ if piece_index == self.white_king => (color, piece, white_king),
+            _ => (color, piece, squares.pop().expect("Each other piece has a tile.")),
+        }).collect::<Vec<_>>();
+        let canonical = pieces.windows(2).all(|pair| pair[0] < pair[1]);
+        let position = Position::new(pieces, turn);
+        (canonical && position.is_legal() && self.index_of(&position) == index).then_some(position)
+    }
+}
+
+/// Distances to mate of all positions of one material, one byte per entry of its layout.
+struct Table {
+    layout: Layout,
+    values: Vec<u8>,
+}
+
+impl Table {
+    fn load(material: &Material, path: &Path) -> io::Result<Self> {
+        let layout = Layout::new(material);
+        let bytes = fs::read(path)?;
+        match bytes.strip_prefix(MAGIC) {
+            Some(values) if values.len() == layout.table_size() => Ok(Self { layout, values: values.to_vec() }),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("Not an endgame table of {}.", material))),
+        }
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, [MAGIC, &self.values].concat())
+    }
+}
+
+/// Distances to mate of all positions of the materials met so far.
+#[derive(Default)]
+pub struct EndgameTablebase {
+    tables: HashMap<Material, Table>,
+    /// Directory tables are loaded from, tables missing there are saved to it once generated.
+    directory: Option<PathBuf>,
+}
+
+impl EndgameTablebase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tablebase keeping its tables in the directory, in files named after the material like `KRvKR.dtm`.
+    pub fn with_directory(directory: impl Into<PathBuf>) -> Self {
+        Self { tables: HashMap::new(), directory: Some(directory.into()) }
+    }
+
+    /// Table of the material, loaded from the directory or generated and saved there if it is not in the directory yet.
+    fn load_or_generate(&mut self, material: &Material) -> Result<Table, EndgameError> {
+        let Some(path) = self.directory.as_ref().map(|directory| directory.join(format!("{}.dtm", material))) else {
+            return self.generate(material);
+        };
+        let file_error = |error: io::Error| EndgameError::File(path.clone(), error.to_string());
+        match Table::load(material, &path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let table = self.generate(material)?;
+                table.save(&path).map_err(file_error)?;
+                Ok(table)
+            }
+            result => result.map_err(file_error),
+        }
+    }
+
+    /// Value of the position, loading or generating tables it depends on first.
+    /// Fails for positions with more than [`MAX_PIECES`] pieces and for table files that can not be used.
+    pub fn probe(&mut self, position: &Position) -> Result<Value, EndgameError> {
+        if !position.is_legal() {
+            return Ok(Value::Illegal);
+        }
+        let material = Material::of(position);
+        if material.0.len() > MAX_PIECES {
+            return Err(EndgameError::UnsupportedMaterial(material));
+        }
+        if !self.tables.contains_key(&material) {
+            let table = self.load_or_generate(&material)?;
+            self.tables.insert(material.clone(), table);
+        }
+        let table = &self.tables[&material];
+        Ok(Value::decode(table.values[table.layout.index_of(position)]))
+    }
+
+    /// Computes the table of the material by retrograde analysis, the same way as [`crate::tablebase::Tablebase::generate`].
+    ///
+    /// Positions are decided in order of their distance. Moves to other material are looked up in their tables,
+    /// moves within the material are followed backwards with [`Position::unmoves`].
+    /// Winning distances are odd and losing ones even, so the parity alone tells what was decided.
+    /// Entries rather than moves are counted, as several symmetric moves may lead to the same entry.
+    fn generate(&mut self, material: &Material) -> Result<Table, EndgameError> {
+        let layout = Layout::new(material);
+        let size = layout.table_size();
+        let mut values = vec![ILLEGAL; size];
+        /* Entries of the material reached with a move, not known to lose yet. */
+        let mut unresolved = vec![0u8; size];
+        /* Longest loss through moves to other material plus one, or `ESCAPE`. */
+        let mut other = vec![0u8; size];
+        /* Positions with a move to other material that wins, they are never lost. */
+        let mut winning_exit = vec![false; size];
+        /* Positions to decide, by distance. Entries decided before with shorter distance are skipped,
+           the last bucket only collects distances too long to be stored. Four pieces fit in `u32` indices. */
+        let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); DRAW as usize + 1];
+
+        for index in 0..size {
+            let Some(position) = layout.position_at(material, index) else {
+                continue;
+            };
+            values[index] = DRAW;
+            let substates = position.substates();
+            if position.is_checkmate(substates.len()) {
+                buckets[0].push(index as u32);
+            }
+            let mut successors = Vec::new();
+            for substate in substates {
+                if material.matches(&substate) {
+                    successors.push(layout.index_of(&substate));
+                    continue;
+                }
+                match self.probe(&substate)? {
+                    Value::Illegal => unreachable!("Moves lead to legal positions."),
+                    Value::Draw => other[index] = ESCAPE,
+                    Value::Loss(plies) => {
+                        winning_exit[index] = true;
+                        buckets[plies as usize + 1].push(index as u32);
+                    }
+                    Value::Win(plies) if other[index] != ESCAPE => other[index] = other[index].max(plies + 1),
+                    Value::Win(_) => {}
+                }
+            }
+            successors.sort_unstable();
+            successors.dedup();
+            unresolved[index] = successors.len() as u8;
+            if unresolved[index] == 0 && other[index] != ESCAPE && other[index] > 0 && !winning_exit[index] {
+                buckets[other[index] as usize].push(index as u32);
+            }
+        }
+
+        for distance in 0..DRAW as usize {
+            let bucket = std::mem::take(&mut buckets[distance]);
+            for index in bucket {
+                let index = index as usize;
+                if values[index] != DRAW {
+                    continue;
+                }
+                values[index] = distance as u8;
+                let position = layout.position_at(material, index).expect("Decided positions are legal.");
+                let mut predecessors = position.unmoves().iter().map(|predecessor| layout.index_of(predecessor)).collect::<Vec<_>>();
+                predecessors.sort_unstable();
+                predecessors.dedup();
+                for predecessor in predecessors {
+                    if values[predecessor] != DRAW {
+                        continue;
+                    }
+                    if distance % 2 == 0 {
+                        /* Moving here wins. */
+                        buckets[distance + 1].push(predecessor as u32);
+                    } else {
+                        /* Moving here loses, the predecessor is lost once no other move helps. */
+                        unresolved[predecessor] -= 1;
+                        if unresolved[predecessor] == 0 && other[predecessor] != ESCAPE && !winning_exit[predecessor] {
+                            buckets[(distance + 1).max(other[predecessor] as usize)].push(predecessor as u32);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Table { layout, values })
+    }
+
+    /// Best move of the side to move, `None` if the game is over.
+    /// The winning side mates as fast as possible, the losing one delays the mate, in drawn positions the draw is kept.
+    pub fn best_move(&mut self, position: &Position) -> Result<Option<Position>, EndgameError> {
+        let wanted = match self.probe(position)? {
+            Value::Illegal | Value::Loss(0) => return Ok(None),
+            Value::Win(plies) => Value::Loss(plies - 1),
+            Value::Loss(plies) => Value::Win(plies - 1),
+            Value::Draw => Value::Draw,
+        };
+        for substate in position.substates() {
+            if self.probe(&substate)? == wanted {
+                return Ok(Some(substate));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Line of best moves starting with the position and ending with the mate, `None` if the position is drawn.
+    pub fn line(&mut self, position: &Position) -> Result<Option<Vec<Position>>, EndgameError> {
+        let (Value::Win(_) | Value::Loss(_)) = self.probe(position)? else {
+            return Ok(None);
+        };
+        let mut line = vec![position.clone()];
+        while let Some(next) = self.best_move(line.last().expect("Line is never empty."))? {
+            line.push(next);
+        }
+        Ok(Some(line))
+    }
+}
+
+#[cfg(test)]
+mod tests_endgame {
+    use super::{EndgameError, EndgameTablebase, Layout, Material, Value};
+    use crate::position::{Piece, Position};
+    use crate::tablebase::{Outcome, Tablebase};
+    use crate::{Color, Pos, State};
+
+    fn position(text: &str) -> Position {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_material() {
+        assert_eq!(Material::of(&position("black Kh8 Rb2 white Kc4 Qd1 Nc3")).to_string(), "KQNvKR");
+        let material = Material::of(&position("black Kh8 Rb2 white Kc4 Nc5"));
+        let layout = Layout::new(&material);
+        let index = layout.index_of(&position("white Kc4 Nc5 black Kh8 Rb2"));
+        let stored = layout.position_at(&material, index).unwrap();
+        assert_eq!(layout.index_of(&stored), index);
+        /* The same position mirrored along the diagonal a1-h8. */
+        assert_eq!(layout.index_of(&position("white Kd3 Ne3 black Kh8 Rb2")), index);
+    }
+
+    #[test]
+    fn test_layout_sizes() {
+        /* Kings not next to each other, up to the 8 symmetries of the board or mirroring columns with pawns. */
+        assert_eq!(Layout::new(&Material::of(&position("white Ka1 Qb1 black Kh8"))).table_size(), 2 * 462 * 64);
+        assert_eq!(Layout::new(&Material::of(&position("white Ka1 Pb2 black Kh8"))).table_size(), 2 * 1806 * 64);
+    }
+
+    #[test]
+    fn test_agrees_with_krk_tablebase() {
+        let krk = Tablebase::generate();
+        let mut tablebase = EndgameTablebase::new();
+        let mut compared = 0;
+        for turn in [Color::White, Color::Black] {
+            for index in 0..64 * 64 * 64 {
+                let (white_king, rook, black_king) = ((index / 4096) as Pos, (index / 64 % 64) as Pos, (index % 64) as Pos);
+                let pieces = vec![(Color::White, Piece::King, white_king), (Color::White, Piece::Rook, rook), (Color::Black, Piece::King, black_king)];
+                let position = Position::new(pieces, turn);
+                if !position.is_legal() {
+                    continue;
+                }
+                let expected = match (krk.probe(&State::new(white_king, rook, black_king, turn)), turn) {
+                    (Outcome::Mate(plies), Color::White) => Value::Win(plies),
+                    (Outcome::Mate(plies), Color::Black) => Value::Loss(plies),
+                    (Outcome::Draw, _) => Value::Draw,
+                    (Outcome::Illegal, _) => Value::Illegal,
+                };
+                assert_eq!(tablebase.probe(&position), Ok(expected), "{}", position);
+                compared += 1;
+            }
+        }
+        /* Every legal KRK position with either side to move. */
+        assert_eq!(compared, 399_112);
+    }
+
+    #[test]
+    fn test_queen_endgame() {
+        let mut tablebase = EndgameTablebase::new();
+        let material = Material::of(&position("white Ka1 Qb1 black Kh8"));
+        let layout = Layout::new(&material);
+        let longest = (0..layout.table_size())
+            .filter_map(|index| layout.position_at(&material, index))
+            .filter_map(|position| match tablebase.probe(&position) {
+                Ok(Value::Win(plies)) => Some(plies),
+                _ => None,
+            })
+            .max();
+        /* The longest KQK mate takes 10 moves. */
+        assert_eq!(longest, Some(19));
+
+        let start = position("white Kb6 Qh7 black Ka8");
+        assert_eq!(tablebase.probe(&start), Ok(Value::Win(1)));
+        let line = tablebase.line(&start).unwrap().unwrap();
+        assert_eq!(line.len(), 2);
+        assert!(line[1].is_checkmate(0) && line[1].substates().is_empty());
+        /* Stalemate with queen next to the king. */
+        assert_eq!(tablebase.probe(&position("black Ka8 white Kc6 Qb6")), Ok(Value::Draw));
+    }
+
+    /// Value following from values of the moves, as minimax over a single ply.
+    fn minimax(tablebase: &mut EndgameTablebase, position: &Position) -> Value {
+        let substates = position.substates();
+        if substates.is_empty() {
+            return if position.is_checkmate(0) { Value::Loss(0) } else { Value::Draw };
+        }
+        let values = substates.iter().map(|substate| tablebase.probe(substate).unwrap()).collect::<Vec<_>>();
+        let fastest_win = values.iter().filter_map(|&value| match value {
+            Value::Loss(plies) => Some(plies + 1),
+            _ => None,
+        }).min();
+        let slowest_loss = values.iter().map(|&value| match value {
+            Value::Win(plies) => Some(plies + 1),
+            _ => None,
+        }).collect::<Option<Vec<_>>>().and_then(|losses| losses.into_iter().max());
+        match (fastest_win, slowest_loss) {
+            (Some(plies), _) => Value::Win(plies),
+            (None, Some(plies)) => Value::Loss(plies),
+            (None, None) => Value::Draw,
+        }
+    }
+
+    #[test]
+    fn test_captures_of_both_sides() {
+        /* Both sides may win here, often by capturing the other rook. */
+        let mut tablebase = EndgameTablebase::new();
+        let material = Material::of(&position("white Ka1 Rb1 black Kh8 Rg8"));
+        let layout = Layout::new(&material);
+        for position in (0..layout.table_size()).filter_map(|index| layout.position_at(&material, index)) {
+            let value = tablebase.probe(&position).unwrap();
+            assert_eq!(value, minimax(&mut tablebase, &position), "{}", position);
+        }
+        /* Taking the rook with check wins, although other moves lose. */
+        let start = position("white Ka7 Rc3 black Ka1 Ra3");
+        assert!(matches!(tablebase.probe(&start), Ok(Value::Win(_))));
+        assert_eq!(tablebase.best_move(&start), Ok(Some(position("black Ka1 white Ka7 Ra3"))));
+    }
+
+    #[test]
+    fn test_saved_tables() {
+        let directory = std::env::temp_dir().join(format!("endgame-tables-{}", std::process::id()));
+        let start = position("white Kb6 Qh7 black Ka8");
+        let mut tablebase = EndgameTablebase::with_directory(&directory);
+        assert_eq!(tablebase.probe(&start), Ok(Value::Win(1)));
+        let path = directory.join("KQvK.dtm");
+        assert!(path.exists());
+        /* Tables found in the directory are loaded instead of generated. */
+        let mut loaded = EndgameTablebase::with_directory(&directory);
+        assert_eq!(loaded.probe(&start), Ok(Value::Win(1)));
+        let material = Material::of(&start);
+        assert!(loaded.tables[&material].values == tablebase.tables[&material].values);
+
+        std::fs::write(&path, b"garbage").unwrap();
+        let reason = String::from("Not an endgame table of KQvK.");
+        assert_eq!(EndgameTablebase::with_directory(&directory).probe(&start), Err(EndgameError::File(path, reason)));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_too_many_pieces() {
+        let mut tablebase = EndgameTablebase::new();
+        let start = position("white Kc4 Qd1 Nc3 black Kh8 Rb2");
+        let error = EndgameError::UnsupportedMaterial(Material::of(&start));
+        assert_eq!(tablebase.probe(&start), Err(error.clone()));
+        assert_eq!(tablebase.line(&start), Err(error.clone()));
+        assert_eq!(error.to_string(), "endgames of 5 pieces like KQNvKR are not supported, at most 4 pieces are");
+    }
+}
@@ -1,9 +1,25 @@
+mod bitboard;
+mod cli;
+mod endgame;
+mod notation;
+mod position;
+mod tablebase;
+#[cfg(test)]
+#[allow(clippy::module_inception, clippy::useless_vec)]
 mod tests;
 
 use std::collections::{ HashSet, VecDeque };
 use std::fmt::{Debug, Display, Formatter};
-use std::fs;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::ops::RangeInclusive;
+use std::process;
+use std::str::FromStr;
+
+use bitboard::{bit, squares, Bitboard, KING_ATTACKS};
+use notation::Notation;
+use position::ParseError;
+use tablebase::Tablebase;
 
 const KING_MOVES: [(Pos, Pos); 8] = [
     (-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)
@@ -15,7 +31,7 @@ const BOARD_BOUNDS: RangeInclusive<Pos> = MIN_BOARD_POS..=MAX_BOARD_POS;
 
 
 /// Enumeration of pawn colors.
-#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
 enum Color {
     Black,
     White,
@@ -40,20 +56,59 @@ impl Display for Color {
     }
 }
 
-impl From<&str> for Color {
-    fn from(color: &str) -> Self {
-        match color {
-            "black" => Self::Black,
-            "white" => Self::White,
-            _ => panic!("Invalid starting color encoding: {}", color),
+/// How Black plays while White tries to mate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    /// Black cooperates, the shortest sequence of moves leading to any checkmate is searched.
+    Helpmate,
+    /// Black delays the mate as long as possible, the result is the distance to mate of the tablebase.
+    Optimal,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "helpmate" => Ok(Self::Helpmate),
+            "optimal" => Ok(Self::Optimal),
+            _ => Err(format!("invalid mode: {}, expected helpmate or optimal", mode)),
+        }
+    }
+}
+
+/// What is written for each solved position.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Format {
+    /// Number of plies to the mate.
+    Count,
+    /// Positions of the line, one after another.
+    Path,
+    /// Numbered moves of the line, see [`Notation`].
+    Line(Notation),
+    /// Whole game with the start position, see [`notation::to_pgn`].
+    Pgn,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "count" => Ok(Self::Count),
+            "path" => Ok(Self::Path),
+            "san" => Ok(Self::Line(Notation::San)),
+            "lan" => Ok(Self::Line(Notation::Lan)),
+            "pgn" => Ok(Self::Pgn),
+            _ => Err(format!("invalid format: {}, expected count, path, san, lan or pgn", format)),
         }
     }
 }
 
 #[derive(Debug)]
-enum TraversalResult {
+enum TraversalResult<S = State> {
     Inf,
-    CheckMate(Vec<State>)
+    CheckMate(Vec<S>)
 }
 
 type Pos = i8;
@@ -64,6 +119,7 @@ type Pos = i8;
 /// similarly encoded tile column number.
 /// Fields are numbered from 0-7 increasing just as in typical chess board that is:
 /// from left bottom corner upwards and rightwards.
+/// Rook captured by the black king shares its tile, such state ends the game with a draw.
 #[derive(Hash, Eq, PartialEq, Clone)]
 struct State {
     black_king_pos: Pos,
@@ -82,20 +138,13 @@ impl State {
         String::from_utf8(vec![col as u8 + b'a', row as u8 + b'1']).unwrap()
     }
 
-    pub fn from_file(path: &str) -> Self {
-        let state = String::from(
-            fs::read_to_string(path).expect(format!("Cannot read data from: {}", path).as_str())
-        );
-        State::from(state.as_str())
-    }
-
     pub const fn new(white_king_pos: Pos, white_rook_pos: Pos, black_king_pos: Pos, turn: Color) -> Self {
         Self {black_king_pos, white_king_pos, white_rook_pos, turn}
     }
 
     /// Gets pawn's position from compressed representation.
     const fn decode_position(position: Pos) -> (Pos, Pos) {
-        (position >> State::POSITION_ENCODING_OFFSET, position & (1 << State::POSITION_ENCODING_OFFSET) - 1)
+        (position >> State::POSITION_ENCODING_OFFSET, position & State::ROW_BITMASK)
     }
 
     /// Encodes the pawn's position.
@@ -103,80 +152,54 @@ impl State {
         col << State::POSITION_ENCODING_OFFSET & State::COL_BITMASK | row & State::ROW_BITMASK
     }
 
-    /// Checks if given pawn position is within board bounds.
-    fn is_position_within_bounds(&self, col: Pos, row: Pos) -> bool {
-        BOARD_BOUNDS.contains(&col) && BOARD_BOUNDS.contains(&row)
+    /// Checks if the black king took the rook.
+    pub fn is_rook_captured(&self) -> bool {
+        self.white_rook_pos == self.black_king_pos
     }
 
-    /// Checks if current current state is a checkmate.
-    pub fn is_checkmate(&self, possible_moves: usize) -> bool {
+    /// Tiles the rook attacks. Only the white king blocks its lines, the black king can not
+    /// escape along a line it is checked on.
+    fn rook_attacks(&self) -> Bitboard {
+        if self.is_rook_captured() {
+            return 0;
+        }
+        bitboard::rook_attacks(self.white_rook_pos, bit(self.white_king_pos))
+    }
+
+    /// Checks if the black king is attacked by the rook.
+    pub fn is_black_in_check(&self) -> bool {
+        self.rook_attacks() & bit(self.black_king_pos) != 0
+    }
+
+    /// Checks if the state may occur in a game: pawns occupy different tiles, kings do not touch
+    /// and Black is not in check when White is to move.
+    /// The rook may share the tile with the black king only right after it was captured.
+    pub fn is_legal(&self) -> bool {
         let (black_king_col, black_king_row) = State::decode_position(self.black_king_pos);
         let (white_king_col, white_king_row) = State::decode_position(self.white_king_pos);
-        let (rook_col, rook_row) = State::decode_position(self.white_rook_pos);
-        let king_check = (black_king_col - white_king_col).abs() < 2 && (black_king_row - white_king_row).abs() < 2;
-        let rook_check = black_king_col == rook_col || black_king_row == rook_row;
-        (king_check || rook_check) && possible_moves == 0
+        let kings_touch = (black_king_col - white_king_col).abs() < 2 && (black_king_row - white_king_row).abs() < 2;
+        let overlap = self.white_rook_pos == self.white_king_pos || self.is_rook_captured() && self.turn == Color::Black;
+        let illegal_check = self.turn == Color::White && self.is_black_in_check();
+        !(kings_touch || overlap || illegal_check)
     }
 
-    fn rook_moves(&self) -> impl Iterator<Item=State> + '_ {
-        // rook can move to any tile that is not physically occupied by some other pawn.
-        // even if the rook is places next to the black king it will not create any issues
-        // because of how black king moves are defined.
+    /// Checks if current state is a checkmate, `possible_moves` is the number of its substates.
+    pub fn is_checkmate(&self, possible_moves: usize) -> bool {
+        self.turn == Color::Black && self.is_black_in_check() && possible_moves == 0
+    }
 
-        let (rook_col, rook_row) = State::decode_position(self.white_rook_pos);
-        let (white_king_col, white_king_row) = State::decode_position(self.white_king_pos);
-        let (black_king_col, black_king_row) = State::decode_position(self.black_king_pos);
+    /// Checks if current state is a stalemate, `possible_moves` is the number of its substates.
+    pub fn is_stalemate(&self, possible_moves: usize) -> bool {
+        self.turn == Color::Black && !self.is_black_in_check() && possible_moves == 0
+    }
 
-        let mut left_range = MIN_BOARD_POS;
-        let mut right_range = MAX_BOARD_POS;
-        let mut bottom_range = MIN_BOARD_POS;
-        let mut top_range = MAX_BOARD_POS;
-
-        use std::cmp::Ordering;
-
-        for (other_col, other_row) in [
-            (black_king_col, black_king_row), (white_king_col, white_king_row)]
-        {
-            if other_row == rook_row {
-                match rook_col.cmp(&other_col) {
-                    Ordering::Less => { right_range = right_range.min(other_col - 1); }
-                    Ordering::Equal => { panic!("Two pawns share the same tile."); }
-                    Ordering::Greater => { left_range = left_range.max(other_col + 1); }
-                }
-            }
-            if other_col == rook_col {
-                match rook_row.cmp(&other_row) {
-                    Ordering::Less => { top_range = top_range.min(other_row - 1); }
-                    Ordering::Equal => { panic!("Two pawns share the same tile."); }
-                    Ordering::Greater => { bottom_range = bottom_range.max(other_row + 1); }
-                }
-            }
-        }
-        let horizontal_rook_moves = (left_range..=right_range).filter_map(move |col|{
-            if col == rook_col || (col - black_king_col).abs() < 2 && (rook_row - black_king_row).abs() < 2 {
-                None
-            } else {
-                Some(State::new(
-                    self.white_king_pos,
-                    State::encode_position(col, rook_row),
-                    self.black_king_pos,
-                    self.turn.flip()
-                ))
-            }
-        });
-        let vertical_rook_moves = (bottom_range..=top_range).filter_map(move |row|{
-            if row == rook_row || (rook_col - black_king_col).abs() < 2 && (row - black_king_row).abs() < 2 {
-                None
-            } else {
-                Some(State::new(
-                    self.white_king_pos,
-                    State::encode_position(rook_col, row),
-                    self.black_king_pos,
-                    self.turn.flip()
-                ))
-            }
-        });
-        horizontal_rook_moves.chain(vertical_rook_moves)
+    fn rook_moves(&self) -> impl Iterator<Item=State> + '_ {
+        // rook can move to any tile that is not physically occupied by some other pawn.
+        // moving it next to the black king is legal, if the white king does not defend it
+        // the black king may take it.
+        let kings = bit(self.white_king_pos) | bit(self.black_king_pos);
+        let targets = bitboard::rook_attacks(self.white_rook_pos, kings) & !kings;
+        squares(targets).map(move |to| State::new(self.white_king_pos, to, self.black_king_pos, self.turn.flip()))
     }
 
     /// Produces all valid substates of the current state.
@@ -184,81 +207,39 @@ impl State {
         // Common fail conditions:
         // - Any pawn tries to move into a tile that is already occupied.
         // Rook:
-        // - cannot move "over" any pawn. That is if any other pawn has the same col or row
-        //   position as the rook, rook can only move up to the tile that is occupied.
+        // - cannot move "over" any pawn, its lines end before the first occupied tile.
         // White king:
-        // - both column and row positions must differ by at least 2 so anything below that
-        //   should be considered incorrect. This check also includes physical conflict scenario.
+        // - kings never stand on neighbouring tiles, which also rules out sharing one.
         // Black king:
-        // - similarly as with white king it must not be in two tile vicinity with regard to
+        // - similarly as with white king it must not step next to the white king and it must not
+        //   step on a tile attacked by the rook, unless the white king blocks the rook's line.
+        //   It may take the rook if the white king does not guard it.
+        // No moves are left once the rook is taken, the game ends with a draw.
 
+        if self.is_rook_captured() {
+            return Vec::new();
+        }
         match self.turn {
             Color::Black => {
-                let (col, row) = State::decode_position(self.black_king_pos);
-                let substates = KING_MOVES.into_iter().filter_map(move |(mut new_col, mut new_row)| {
-                    // move pawn to new position
-                    new_col += col;
-                    new_row += row;
-                    // check if position is valid:
-                    // 1. within board
-                    if !self.is_position_within_bounds(new_col, new_row) {
-                        return None;
-                    } else {
-                        // 2. not in position guarded by other pawn.
-                        let (rook_col, rook_row) = State::decode_position(self.white_rook_pos);
-                        let (white_king_col, white_king_row) = State::decode_position(self.white_king_pos);
-
-                        // check if king blocks the currently considered tile.
-                        if (new_col - white_king_col).abs() < 2 && (new_row - white_king_row).abs() < 2 {
-                            return None;
-                        }
-                        // check if rook blocks the currently considered tile.
-                        if new_col == rook_col || new_row == rook_row {
-                            return None;
-                        }
-                        Some(State::new(
-                                self.white_king_pos,
-                                self.white_rook_pos,
-                                State::encode_position(new_col, new_row),
-                                self.turn.flip()
-                        ))
-                    }
-                });
-                substates.collect()
+                let targets = KING_ATTACKS[self.black_king_pos as usize]
+                    & !KING_ATTACKS[self.white_king_pos as usize]
+                    & !self.rook_attacks();
+                squares(targets)
+                    .map(|to| State::new(self.white_king_pos, self.white_rook_pos, to, self.turn.flip()))
+                    .collect()
             }
             Color::White => {
-                let (rook_col, rook_row) = State::decode_position(self.white_rook_pos);
-                let (white_king_col, white_king_row) = State::decode_position(self.white_king_pos);
-                let (black_king_col, black_king_row) = State::decode_position(self.black_king_pos);
-
-                // possible king moves
-                let king_moves = KING_MOVES.iter().filter_map(move |(mut new_col, mut new_row)| {
-                    new_col += white_king_col;
-                    new_row += white_king_row;
-                    if !self.is_position_within_bounds(new_col, new_row) {
-                        return None;
-                    } else {
-                        if (new_col - black_king_col).abs() < 2 && (new_row - black_king_row).abs() < 2 {
-                            return None;
-                        }
-                        if new_col == rook_col && new_row == rook_row {
-                            return None;
-                        }
-                        Some(State::new(
-                                State::encode_position(new_col, new_row),
-                                self.white_rook_pos,
-                                self.black_king_pos,
-                                self.turn.flip()
-                        ))
-                    }
-                });
-                let rook_moves = self.rook_moves();
-                let substates = king_moves.chain(rook_moves);
-                substates.collect()
+                let targets = KING_ATTACKS[self.white_king_pos as usize]
+                    & !KING_ATTACKS[self.black_king_pos as usize]
+                    & !bit(self.white_rook_pos);
+                let king_moves = squares(targets)
+                    .map(|to| State::new(to, self.white_rook_pos, self.black_king_pos, self.turn.flip()));
+                king_moves.chain(self.rook_moves()).collect()
             }
         }
     }
 
+    /// Finds the shortest sequence of moves of both sides leading to a checkmate, see [`Mode::Helpmate`].
     pub fn solve(self) -> TraversalResult {
         let mut memo = HashSet::<State>::with_capacity(64 * 63 * 62 * 2);
         let path = Vec::new();
@@ -268,8 +249,9 @@ impl State {
             let substates = state.substates();
             if state.is_checkmate(substates.len()) {
                 return TraversalResult::CheckMate(path);
-            } else {
-                for substate in state.substates() {
+            } else if !state.is_stalemate(substates.len()) {
+                // stalemate ends the game with a draw, just as taking the rook does.
+                for substate in substates {
                     if !memo.contains(&substate) {
                         memo.insert(substate.clone());
                         lifo.push_back((substate, path.clone()));
@@ -279,6 +261,14 @@ impl State {
         }
         TraversalResult::Inf
     }
+
+    /// Finds the line of optimal play of both sides, see [`Mode::Optimal`].
+    pub fn solve_optimal(self, tablebase: &Tablebase) -> TraversalResult {
+        match tablebase.line(&self) {
+            None => TraversalResult::Inf,
+            Some(line) => TraversalResult::CheckMate(line),
+        }
+    }
 }
 
 impl Debug for State {
@@ -304,43 +294,80 @@ impl Display for State {
     }
 }
 
-impl From<&str> for State {
-    fn from(initial_state: &str) -> Self {
+/// Parses the side to move followed by tiles of the white king, the rook and the black king, like `white c4 c8 h3`.
+impl FromStr for State {
+    type Err = ParseError;
+
+    fn from_str(initial_state: &str) -> Result<Self, Self::Err> {
         let tokens = initial_state.split_whitespace().take(4).collect::<Vec<&str>>();
-        match tokens[..] {
-            [color, ref str_positions @ ..] => {
-                let converted_positions = str_positions
-                    .iter()
-                    .map(|&pos| {
-                        match pos.to_ascii_lowercase().as_bytes() {
-                            [col, row] if (b'a'..=b'h').contains(col) && (b'1'..=b'8').contains(row) => {
-                                 State::encode_position((col - b'a') as Pos, (row - b'1') as Pos)
-                            }
-                            _ => panic!("Invalid position encoding: {}", pos)
-                        }
-                    }).collect::<Vec<Pos>>();
-                if let &[white_king, white_rook, black_king] = &converted_positions[..] {
-                    State::new(
-                        white_king,
-                        white_rook,
-                        black_king,
-                        Color::from(color)
-                    )
-                } else {
-                    panic!("Incorrect position encodings. Expected 3 positions fround {:?} in {}", converted_positions, converted_positions.len())
+        let [color, ref str_positions @ ..] = tokens[..] else {
+            return Err(ParseError::TileCount(0));
+        };
+        let turn = match color {
+            "black" => Color::Black,
+            "white" => Color::White,
+            _ => return Err(ParseError::InvalidColor(color.to_string())),
+        };
+        let converted_positions = str_positions
+            .iter()
+            .map(|&pos| {
+                match pos.to_ascii_lowercase().as_bytes() {
+                    [col, row] if (b'a'..=b'h').contains(col) && (b'1'..=b'8').contains(row) => {
+                        Ok(State::encode_position((col - b'a') as Pos, (row - b'1') as Pos))
+                    }
+                    _ => Err(ParseError::InvalidPiece(pos.to_string()))
                 }
-            }
-            _ => panic!("Incorrect state encoding. Expected 4 tokens fround {:?} in {}", tokens, tokens.len())
+            }).collect::<Result<Vec<Pos>, _>>()?;
+        let &[white_king, white_rook, black_king] = &converted_positions[..] else {
+            return Err(ParseError::TileCount(converted_positions.len()));
+        };
+        let state = State::new(white_king, white_rook, black_king, turn);
+        // pawns must not overlap, kings must not touch and black can not be left in check.
+        if !state.is_legal() || state.is_rook_captured() {
+            return Err(ParseError::IllegalPosition);
         }
+        Ok(state)
+    }
+}
 
+impl From<&str> for State {
+    fn from(initial_state: &str) -> Self {
+        initial_state.parse().unwrap_or_else(|error| panic!("Invalid position {}: {}", initial_state.trim(), error))
     }
 }
 
 fn main() {
-    let state = State::from_file("zad1_input.txt");
-    let outcome = match state.solve() {
-        TraversalResult::Inf => { String::from("INF") }
-        TraversalResult::CheckMate(path) => { (path.len() - 1).to_string() }
+    let options = cli::Options::parse(std::env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, cli::USAGE);
+        process::exit(2);
+    });
+    let input: Box<dyn BufRead> = if options.input == cli::STDIO {
+        Box::new(io::stdin().lock())
+    } else {
+        let file = File::open(&options.input).unwrap_or_else(|error| {
+            eprintln!("Cannot read data from {}: {}", options.input, error);
+            process::exit(1);
+        });
+        Box::new(BufReader::new(file))
+    };
+    let output: Box<dyn Write> = if options.output == cli::STDIO {
+        Box::new(io::stdout().lock())
+    } else {
+        let file = File::create(&options.output).unwrap_or_else(|error| {
+            eprintln!("Cannot write to {}: {}", options.output, error);
+            process::exit(1);
+        });
+        Box::new(BufWriter::new(file))
     };
-    fs::write("zad1_output.txt", outcome.as_bytes()).expect(format!("Could not write {} to zad1_output.txt", outcome).as_str());
+    match cli::run(&options, input, output, io::stderr().lock()) {
+        Ok(0) => {}
+        Ok(failed) => {
+            eprintln!("Positions that could not be solved: {}", failed);
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Processing failed: {}", error);
+            process::exit(1);
+        }
+    }
 }
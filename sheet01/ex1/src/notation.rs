@@ -0,0 +1,427 @@
+//! Standard chess notations: FEN for positions, SAN and long algebraic notation for moves
+//! and PGN for whole solution lines, so results can be pasted into chess GUIs.
+//!
+//! Castling and en passant never occur in the supported positions, FEN fields describing them are always `-`.
+
+use std::fmt::{Display, Formatter};
+
+use crate::position::{square_to_string, Piece, Position};
+use crate::{Color, Pos, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// Fewer than the piece placement and the side to move.
+    MissingField,
+    InvalidPlacement(String),
+    InvalidTurn(String),
+    /// Castling rights or an en passant tile.
+    Unsupported(String),
+    InvalidCounter(String),
+    /// See [`crate::position::ParseError::IllegalPosition`].
+    IllegalPosition,
+    /// The position is not a KRK position with White having the rook.
+    NotKrk,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingField => write!(f, "piece placement and side to move are required"),
+            FenError::InvalidPlacement(placement) => write!(f, "invalid piece placement: {}", placement),
+            FenError::InvalidTurn(turn) => write!(f, "invalid side to move: {}", turn),
+            FenError::Unsupported(field) => write!(f, "castling and en passant are not supported: {}", field),
+            FenError::InvalidCounter(counter) => write!(f, "invalid move counter: {}", counter),
+            FenError::IllegalPosition => write!(f, "illegal position"),
+            FenError::NotKrk => write!(f, "not a position of white king and rook against black king"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Position {
+    /// Parses a FEN record. Only the piece placement and the side to move are required,
+    /// the move counters are checked but not kept.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields = fen.split_whitespace().collect::<Vec<_>>();
+        let [placement, turn, rest @ ..] = &fields[..] else {
+            return Err(FenError::MissingField);
+        };
+        let invalid_placement = || FenError::InvalidPlacement(placement.to_string());
+        let rows = placement.split('/').collect::<Vec<_>>();
+        if rows.len() != 8 {
+            return Err(invalid_placement());
+        }
+        let mut pieces = Vec::new();
+        // rows are listed from the 8th down to the 1st.
+        for (row, text) in (0..8).rev().zip(rows) {
+            let mut col: Pos = 0;
+            for letter in text.chars() {
+                if let Some(empty) = letter.to_digit(10).filter(|empty| (1..=8).contains(empty)) {
+                    // empty tiles may not run past the end of the row.
+                    if col + empty as Pos > 8 {
+                        return Err(invalid_placement());
+                    }
+                    col += empty as Pos;
+                    continue;
+                }
+                let piece = Piece::from_letter(letter.to_ascii_uppercase()).ok_or_else(invalid_placement)?;
+                let color = if letter.is_ascii_uppercase() { Color::White } else { Color::Black };
+                if col > 7 {
+                    return Err(invalid_placement());
+                }
+                pieces.push((color, piece, State::encode_position(col, row)));
+                col += 1;
+            }
+            if col != 8 {
+                return Err(invalid_placement());
+            }
+        }
+        let turn = match *turn {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidTurn(turn.to_string())),
+        };
+        for (index, field) in rest.iter().enumerate() {
+            match index {
+                0 | 1 if *field != "-" => return Err(FenError::Unsupported(field.to_string())),
+                2 | 3 if field.parse::<u32>().is_err() => return Err(FenError::InvalidCounter(field.to_string())),
+                4.. => return Err(FenError::InvalidCounter(field.to_string())),
+                _ => {}
+            }
+        }
+        let position = Position::new(pieces, turn);
+        if position.is_legal() {
+            Ok(position)
+        } else {
+            Err(FenError::IllegalPosition)
+        }
+    }
+
+    /// FEN record of the position, move counters start a new game.
+    pub fn to_fen(&self) -> String {
+        let mut board = [[None; 8]; 8];
+        for &(color, piece, square) in self.pieces() {
+            let (col, row) = State::decode_position(square);
+            board[row as usize][col as usize] = Some((color, piece));
+        }
+        let rows = board.iter().rev().map(|tiles| {
+            let mut text = String::new();
+            let mut empty = 0;
+            for tile in tiles {
+                match tile {
+                    None => empty += 1,
+                    Some((color, piece)) => {
+                        if empty > 0 {
+                            text.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        text.push(match color {
+                            Color::White => piece.letter(),
+                            Color::Black => piece.letter().to_ascii_lowercase(),
+                        });
+                    }
+                }
+            }
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+            }
+            text
+        }).collect::<Vec<_>>();
+        let turn = match self.turn() {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+        format!("{} {} - - 0 1", rows.join("/"), turn)
+    }
+}
+
+impl TryFrom<&Position> for State {
+    type Error = FenError;
+
+    fn try_from(position: &Position) -> Result<Self, Self::Error> {
+        match position.pieces() {
+            &[(Color::Black, Piece::King, black_king), (Color::White, Piece::King, white_king), (Color::White, Piece::Rook, white_rook)] => {
+                Ok(State::new(white_king, white_rook, black_king, position.turn()))
+            }
+            _ => Err(FenError::NotKrk),
+        }
+    }
+}
+
+impl State {
+    /// Parses a FEN record of a KRK position, see [`Position::from_fen`].
+    /// States are written in FEN through [`Position::from`].
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        State::try_from(&Position::from_fen(fen)?)
+    }
+}
+
+/// Move between two consecutive positions of a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub piece: Piece,
+    pub from: Pos,
+    pub to: Pos,
+    pub capture: bool,
+    pub promotion: Option<Piece>,
+}
+
+impl Move {
+    /// Recovers the move leading from one position to the other, `None` if no single move does.
+    pub fn between(before: &Position, after: &Position) -> Option<Self> {
+        let mover = before.turn();
+        fn squares(position: &Position, color: Color) -> impl Iterator<Item = (Piece, Pos)> + '_ {
+            position.pieces().iter().filter(move |&&(other, _, _)| other == color).map(|&(_, piece, square)| (piece, square))
+        }
+        fn occupied(position: &Position, color: Color, square: Pos) -> bool {
+            squares(position, color).any(|(_, other)| other == square)
+        }
+        let mut left = squares(before, mover).filter(|&(_, square)| !occupied(after, mover, square));
+        let mut entered = squares(after, mover).filter(|&(_, square)| !occupied(before, mover, square));
+        let ((piece, from), (moved, to)) = (left.next()?, entered.next()?);
+        if left.next().is_some() || entered.next().is_some() || after.turn() != mover.flip() {
+            return None;
+        }
+        Some(Self {
+            piece,
+            from,
+            to,
+            capture: occupied(before, mover.flip(), to),
+            promotion: (moved != piece).then_some(moved),
+        })
+    }
+}
+
+/// How moves of a line are written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Notation {
+    /// Standard algebraic notation, like `Rc1` or `exd5`.
+    San,
+    /// Long algebraic notation with both tiles, like `Rc8-c1` or `e4xd5`.
+    Lan,
+}
+
+/// Check or mate sign of the position after a move.
+/// Checks of a side that can not mate anymore are not mates, even though the game is over.
+fn check_suffix(after: &Position) -> &'static str {
+    if after.is_checkmate(after.substates().len()) {
+        "#"
+    } else if after.is_in_check(after.turn()) {
+        "+"
+    } else {
+        ""
+    }
+}
+
+fn promotion_suffix(promotion: Option<Piece>) -> String {
+    promotion.map(|piece| format!("={}", piece.letter())).unwrap_or_default()
+}
+
+/// The move in long algebraic notation, pawns have no letter.
+pub fn to_lan(before: &Position, after: &Position) -> Option<String> {
+    let Move { piece, from, to, capture, promotion } = Move::between(before, after)?;
+    let letter = if piece == Piece::Pawn { String::new() } else { piece.letter().to_string() };
+    Some(format!(
+        "{}{}{}{}{}{}",
+        letter,
+        square_to_string(from),
+        if capture { 'x' } else { '-' },
+        square_to_string(to),
+        promotion_suffix(promotion),
+        check_suffix(after),
+    ))
+}
+
+/// The move in standard algebraic notation, with the origin tile disambiguated when needed.
+pub fn to_san(before: &Position, after: &Position) -> Option<String> {
+    let played = Move::between(before, after)?;
+    let from = square_to_string(played.from);
+    let mut text = String::new();
+    if played.piece == Piece::Pawn {
+        if played.capture {
+            text.push_str(&from[..1]);
+        }
+    } else {
+        text.push(played.piece.letter());
+        // other pieces of the same kind reaching the same tile.
+        let rivals = before.substates().iter()
+            .filter_map(|substate| Move::between(before, substate))
+            .filter(|other| other.piece == played.piece && other.to == played.to && other.from != played.from)
+            .map(|other| square_to_string(other.from))
+            .collect::<Vec<_>>();
+        if !rivals.is_empty() {
+            if rivals.iter().all(|other| other[..1] != from[..1]) {
+                text.push_str(&from[..1]);
+            } else if rivals.iter().all(|other| other[1..] != from[1..]) {
+                text.push_str(&from[1..]);
+            } else {
+                text.push_str(&from);
+            }
+        }
+    }
+    if played.capture {
+        text.push('x');
+    }
+    text.push_str(&square_to_string(played.to));
+    text.push_str(&promotion_suffix(played.promotion));
+    text.push_str(check_suffix(after));
+    Some(text)
+}
+
+/// Numbered moves of the line, like `1. Rc8-c1 Kh3-g2 2. ...`, a line starting with Black begins with `1...`.
+pub fn line_to_string(line: &[Position], notation: Notation) -> String {
+    let write = match notation {
+        Notation::San => to_san,
+        Notation::Lan => to_lan,
+    };
+    let mut number = 1;
+    let mut tokens = Vec::new();
+    for (index, pair) in line.windows(2).enumerate() {
+        let [before, after] = pair else {
+            unreachable!("Windows have two positions.");
+        };
+        match before.turn() {
+            Color::White => tokens.push(format!("{}.", number)),
+            Color::Black if index == 0 => tokens.push(format!("{}...", number)),
+            Color::Black => {}
+        }
+        if before.turn() == Color::Black {
+            number += 1;
+        }
+        tokens.push(write(before, after).unwrap_or_else(|| panic!("No move leads from {} to {}", before, after)));
+    }
+    tokens.join(" ")
+}
+
+/// Result tag of a game ending with the last position of the line.
+fn result(last: &Position) -> &'static str {
+    let substates = last.substates();
+    if last.is_checkmate(substates.len()) {
+        match last.turn() {
+            Color::White => "0-1",
+            Color::Black => "1-0",
+        }
+    } else if substates.is_empty() {
+        "1/2-1/2"
+    } else {
+        "*"
+    }
+}
+
+/// PGN of the line with the start position as the `FEN` tag, moves are written in SAN.
+pub fn to_pgn(line: &[Position]) -> String {
+    let (Some(first), Some(last)) = (line.first(), line.last()) else {
+        return String::new();
+    };
+    let result = result(last);
+    let mut pgn = String::new();
+    for (tag, value) in [
+        ("Event", "Endgame solution"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+        ("Result", result),
+        ("SetUp", "1"),
+        ("FEN", &first.to_fen()),
+    ] {
+        pgn.push_str(&format!("[{} \"{}\"]\n", tag, value));
+    }
+    pgn.push('\n');
+    // movetext lines are kept below 80 characters.
+    let mut width = 0;
+    for token in line_to_string(line, Notation::San).split_whitespace().chain([result]) {
+        if width > 0 && width + token.len() + 1 > 79 {
+            pgn.push('\n');
+            width = 0;
+        } else if width > 0 {
+            pgn.push(' ');
+            width += 1;
+        }
+        pgn.push_str(token);
+        width += token.len();
+    }
+    pgn.push('\n');
+    pgn
+}
+
+#[cfg(test)]
+mod tests_notation {
+    use super::{line_to_string, to_lan, to_pgn, to_san, FenError, Notation};
+    use crate::position::Position;
+    use crate::{State, TraversalResult};
+
+    fn position(text: &str) -> Position {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_fen() {
+        let state = State::from("white c4 c8 h3");
+        assert_eq!(Position::from(&state).to_fen(), "2R5/8/8/8/2K5/7k/8/8 w - - 0 1");
+        assert_eq!(State::from_fen("2R5/8/8/8/2K5/7k/8/8 w - - 0 1"), Ok(state));
+        assert_eq!(State::from_fen("2R5/8/8/8/2K5/7k/8/8 w"), Ok(State::from("white c4 c8 h3")));
+        let pawns = position("black Kh8 Pd7 white Ka1 Pe2");
+        assert_eq!(Position::from_fen(&pawns.to_fen()), Ok(pawns));
+
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8/8"), Err(FenError::MissingField));
+        assert_eq!(Position::from_fen("9/8/8/8/8/8/8/8 w"), Err(FenError::InvalidPlacement("9/8/8/8/8/8/8/8".to_string())));
+        assert_eq!(Position::from_fen("8/8/8/8/8/8/8 w"), Err(FenError::InvalidPlacement("8/8/8/8/8/8/8".to_string())));
+        let placement = ["8888888888888888"; 8].join("/");
+        assert_eq!(Position::from_fen(&format!("{} w", placement)), Err(FenError::InvalidPlacement(placement)));
+        assert!(Position::from_fen("44K/8/8/8/8/7k/8/8 w").is_err());
+        assert_eq!(Position::from_fen("2R5/8/8/8/2K5/7k/8/8 x"), Err(FenError::InvalidTurn("x".to_string())));
+        assert_eq!(Position::from_fen("2R5/8/8/8/2K5/7k/8/8 w KQ - 0 1"), Err(FenError::Unsupported("KQ".to_string())));
+        assert_eq!(Position::from_fen("2R5/8/8/8/2K5/7k/8/8 w - - x 1"), Err(FenError::InvalidCounter("x".to_string())));
+        assert_eq!(Position::from_fen("7R/8/8/8/2K5/7k/8/8 w"), Err(FenError::IllegalPosition));
+        assert_eq!(State::from_fen("Q7/8/8/8/2K5/7k/8/8 w"), Err(FenError::NotKrk));
+    }
+
+    #[test]
+    fn test_moves() {
+        let before = position("white Kc4 Rc8 black Kh3");
+        let after = position("black Kh3 white Kc4 Rc1");
+        assert_eq!(to_lan(&before, &after).as_deref(), Some("Rc8-c1"));
+        assert_eq!(to_san(&before, &after).as_deref(), Some("Rc1"));
+        assert_eq!(to_san(&before, &before), None);
+
+        /* Captures, promotions, checks and disambiguation. */
+        let capture = position("white Ka1 Pe4 black Kh8 Nd5");
+        let after = position("black Kh8 white Ka1 Pd5");
+        assert_eq!(to_san(&capture, &after).as_deref(), Some("exd5"));
+        assert_eq!(to_lan(&capture, &after).as_deref(), Some("e4xd5"));
+        let promotion = position("white Kf6 Pg7 black Kh7");
+        let after = position("black Kh7 white Kf6 Qg8");
+        assert_eq!(to_san(&promotion, &after).as_deref(), Some("g8=Q+"));
+        let rooks = position("white Ka1 Rd1 Rh1 black Kg8");
+        assert_eq!(to_san(&rooks, &position("black Kg8 white Ka1 Rf1 Rh1")).as_deref(), Some("Rdf1"));
+        let rooks = position("white Kc2 Ra1 Ra5 black Kh8");
+        assert_eq!(to_san(&rooks, &position("black Kh8 white Kc2 Ra3 Ra5")).as_deref(), Some("R1a3"));
+        let mate = position("white Kb6 Qh7 black Ka8");
+        assert_eq!(to_san(&mate, &position("black Ka8 white Kb6 Qb7")).as_deref(), Some("Qb7#"));
+        /* A lone knight can not mate, its check ends the game without a mate. */
+        let knight = position("white Kc1 Nd5 black Ka8");
+        assert_eq!(to_san(&knight, &position("black Ka8 white Kc1 Nb6")).as_deref(), Some("Nb6+"));
+    }
+
+    #[test]
+    fn test_line_and_pgn() {
+        let TraversalResult::CheckMate(path) = State::from("black c4 c8 h3").solve() else {
+            panic!("Mate exists.");
+        };
+        let line = path.iter().map(Position::from).collect::<Vec<_>>();
+        let lan = line_to_string(&line, Notation::Lan);
+        assert!(lan.starts_with("1... Kh3-"), "{}", lan);
+        assert!(lan.ends_with('#'), "{}", lan);
+        assert_eq!(lan.split_whitespace().filter(|token| !token.ends_with('.')).count(), line.len() - 1);
+
+        let line = [position("white Kb6 Qh7 black Ka8"), position("black Ka8 white Kb6 Qb7")];
+        assert_eq!(line_to_string(&line, Notation::San), "1. Qb7#");
+        let pgn = to_pgn(&line);
+        assert!(pgn.contains("[FEN \"k7/7Q/1K6/8/8/8/8/8 w - - 0 1\"]\n"), "{}", pgn);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN") && pgn.contains("[Result \"1-0\"]"), "{}", pgn);
+        assert!(pgn.ends_with("\n\n1. Qb7# 1-0\n"), "{}", pgn);
+    }
+}
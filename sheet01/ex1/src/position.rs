@@ -0,0 +1,537 @@
+//! Positions with any small set of pieces, like KQK, KBNK or KRKR.
+//!
+//! Castling and en passant are not supported, endgame puzzles do not need them.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::bitboard::{attacks, bit, squares, Bitboard};
+use crate::{Color, Pos, State, TraversalResult, BOARD_BOUNDS};
+
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+pub enum Piece {
+    King,
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+    Pawn,
+}
+
+pub const KNIGHT_MOVES: [(Pos, Pos); 8] = [
+    (-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)
+];
+
+impl Piece {
+    /// Pieces a pawn may promote to.
+    pub const PROMOTIONS: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+
+    pub fn letter(self) -> char {
+        match self {
+            Piece::King => 'K',
+            Piece::Queen => 'Q',
+            Piece::Rook => 'R',
+            Piece::Bishop => 'B',
+            Piece::Knight => 'N',
+            Piece::Pawn => 'P',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'K' => Some(Piece::King),
+            'Q' => Some(Piece::Queen),
+            'R' => Some(Piece::Rook),
+            'B' => Some(Piece::Bishop),
+            'N' => Some(Piece::Knight),
+            'P' => Some(Piece::Pawn),
+            _ => None,
+        }
+    }
+}
+
+/// Direction the pawns of the color move in.
+const fn pawn_direction(color: Color) -> Pos {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+/// Row the pawns of the color promote on.
+const fn promotion_row(color: Color) -> Pos {
+    match color {
+        Color::White => 7,
+        Color::Black => 0,
+    }
+}
+
+pub fn square_to_string(position: Pos) -> String {
+    State::position_to_string(position)
+}
+
+pub fn square_from_str(square: &str) -> Option<Pos> {
+    match square.as_bytes() {
+        [col, row] if (b'a'..=b'h').contains(col) && (b'1'..=b'8').contains(row) => {
+            Some(State::encode_position((col - b'a') as Pos, (row - b'1') as Pos))
+        }
+        _ => None,
+    }
+}
+
+/// Tile contents of the whole board.
+type Board = [Option<(Color, Piece)>; 64];
+
+/// Game state with any pieces, stored as a list of pieces.
+/// The list is kept sorted so that positions with the same pieces on the same tiles compare equal.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub struct Position {
+    pieces: Vec<(Color, Piece, Pos)>,
+    turn: Color,
+}
+
+impl Position {
+    pub fn new(mut pieces: Vec<(Color, Piece, Pos)>, turn: Color) -> Self {
+        pieces.sort();
+        Self { pieces, turn }
+    }
+
+    pub fn pieces(&self) -> &[(Color, Piece, Pos)] {
+        &self.pieces
+    }
+
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    fn board(&self) -> Board {
+        let mut board = [None; 64];
+        for &(color, piece, square) in &self.pieces {
+            board[square as usize] = Some((color, piece));
+        }
+        board
+    }
+
+    fn king(&self, color: Color) -> Option<Pos> {
+        self.pieces.iter().find(|&&(other, piece, _)| other == color && piece == Piece::King).map(|&(_, _, square)| square)
+    }
+
+    /// Tiles occupied by pieces of the color.
+    fn occupied_by(&self, color: Color) -> Bitboard {
+        self.pieces.iter().filter(|&&(other, _, _)| other == color).fold(0, |occupied, &(_, _, square)| occupied | bit(square))
+    }
+
+    /// Tiles attacked by any piece of the color, sliding pieces are blocked by pieces of both sides.
+    pub fn attacked_by(&self, color: Color) -> Bitboard {
+        let occupied = self.occupied_by(Color::White) | self.occupied_by(Color::Black);
+        self.pieces.iter()
+            .filter(|&&(other, _, _)| other == color)
+            .fold(0, |attacked, &(_, piece, square)| attacked | attacks(piece, color, square, occupied))
+    }
+
+    /// Checks if the king of the color is attacked.
+    pub fn is_in_check(&self, color: Color) -> bool {
+        match self.king(color) {
+            Some(king) => self.attacked_by(color.flip()) & bit(king) != 0,
+            None => false,
+        }
+    }
+
+    /// Checks if the position may occur in a game: each side has one king, pieces occupy different tiles,
+    /// no pawn stands on the first or the last row and the side not to move is not in check.
+    pub fn is_legal(&self) -> bool {
+        let kings = |color| self.pieces.iter().filter(|&&(other, piece, _)| other == color && piece == Piece::King).count();
+        let occupied = self.occupied_by(Color::White) | self.occupied_by(Color::Black);
+        let shared_tile = occupied.count_ones() as usize != self.pieces.len();
+        let pawn_on_edge = self.pieces.iter()
+            .any(|&(_, piece, square)| piece == Piece::Pawn && [0, 7].contains(&State::decode_position(square).1));
+        kings(Color::White) == 1 && kings(Color::Black) == 1 && !shared_tile
+            && !pawn_on_edge && !self.is_in_check(self.turn.flip())
+    }
+
+    /// Checks if neither side can ever mate: only kings are left, possibly with a single bishop or knight.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut others = self.pieces.iter().filter(|&&(_, piece, _)| piece != Piece::King);
+        match (others.next(), others.next()) {
+            (None, _) => true,
+            (Some(&(_, piece, _)), None) => piece == Piece::Bishop || piece == Piece::Knight,
+            _ => false,
+        }
+    }
+
+    /// Checks if current position is a checkmate, `possible_moves` is the number of its substates.
+    pub fn is_checkmate(&self, possible_moves: usize) -> bool {
+        self.is_in_check(self.turn) && possible_moves == 0 && !self.is_insufficient_material()
+    }
+
+    /// Checks if current position is a stalemate, `possible_moves` is the number of its substates.
+    pub fn is_stalemate(&self, possible_moves: usize) -> bool {
+        !self.is_in_check(self.turn) && possible_moves == 0 && !self.is_insufficient_material()
+    }
+
+    /// Position after moving the piece from one tile to another, capturing whatever stood there.
+    fn after_move(&self, from: Pos, to: Pos, promotion: Option<Piece>) -> Position {
+        let pieces = self.pieces.iter().filter(|&&(_, _, square)| square != to).map(|&(color, piece, square)| {
+            if square == from {
+                (color, promotion.unwrap_or(piece), to)
+            } else {
+                (color, piece, square)
+            }
+        }).collect();
+        Position::new(pieces, self.turn.flip())
+    }
+
+    /// Produces all positions reachable with one legal move.
+    /// Once mate is impossible the game is over and there are no moves.
+    pub fn substates(&self) -> Vec<Position> {
+        if self.is_insufficient_material() {
+            return Vec::new();
+        }
+        let board = self.board();
+        let own = self.occupied_by(self.turn);
+        let enemies = self.occupied_by(self.turn.flip());
+        let occupied = own | enemies;
+        let mut substates = Vec::new();
+        for &(color, piece, from) in self.pieces.iter().filter(|&&(color, _, _)| color == self.turn) {
+            let (col, row) = State::decode_position(from);
+            let tile = |col: Pos, row: Pos| {
+                (BOARD_BOUNDS.contains(&col) && BOARD_BOUNDS.contains(&row)).then(|| State::encode_position(col, row))
+            };
+            // tiles the piece may move to, captures of kings are filtered out below.
+            let targets = match piece {
+                Piece::Pawn => {
+                    let direction = pawn_direction(color);
+                    let mut targets = attacks(piece, color, from, occupied) & enemies;
+                    if let Some(to) = tile(col, row + direction).filter(|&to| occupied & bit(to) == 0) {
+                        targets |= bit(to);
+                        let start_row = promotion_row(color.flip()) + direction;
+                        if let Some(to) = tile(col, row + 2 * direction).filter(|&to| row == start_row && occupied & bit(to) == 0) {
+                            targets |= bit(to);
+                        }
+                    }
+                    targets
+                }
+                _ => attacks(piece, color, from, occupied) & !own,
+            };
+            for to in squares(targets) {
+                // kings are never captured in legal play.
+                if matches!(board[to as usize], Some((_, Piece::King))) {
+                    continue;
+                }
+                let promotions = if piece == Piece::Pawn && State::decode_position(to).1 == promotion_row(color) {
+                    Piece::PROMOTIONS.map(Some).to_vec()
+                } else {
+                    vec![None]
+                };
+                for promotion in promotions {
+                    let substate = self.after_move(from, to, promotion);
+                    if !substate.is_in_check(color) {
+                        substates.push(substate);
+                    }
+                }
+            }
+        }
+        substates
+    }
+
+    /// Produces all legal positions from which a move without capture or promotion leads to this position.
+    pub fn unmoves(&self) -> Vec<Position> {
+        let board = self.board();
+        let mover = self.turn.flip();
+        let occupied = self.occupied_by(Color::White) | self.occupied_by(Color::Black);
+        let mut predecessors = Vec::new();
+        for (index, &(color, piece, to)) in self.pieces.iter().enumerate().filter(|(_, &(color, _, _))| color == mover) {
+            let (col, row) = State::decode_position(to);
+            let empty = |col: Pos, row: Pos| {
+                (BOARD_BOUNDS.contains(&col) && BOARD_BOUNDS.contains(&row))
+                    .then(|| State::encode_position(col, row))
+                    .filter(|&square| board[square as usize].is_none())
+            };
+            let mut origins = Vec::new();
+            match piece {
+                Piece::Pawn => {
+                    let direction = pawn_direction(color);
+                    if let Some(from) = empty(col, row - direction) {
+                        origins.push(from);
+                        // a double step starts on the row next to the own first row.
+                        if row - 2 * direction == promotion_row(color.flip()) + direction {
+                            origins.extend(empty(col, row - 2 * direction));
+                        }
+                    }
+                }
+                // moves of other pieces are symmetric, they come from tiles they attack.
+                _ => origins.extend(squares(attacks(piece, color, to, occupied) & !occupied)),
+            }
+            for from in origins {
+                let mut pieces = self.pieces.clone();
+                pieces[index].2 = from;
+                let predecessor = Position::new(pieces, mover);
+                if predecessor.is_legal() {
+                    predecessors.push(predecessor);
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// Board drawn in text, rows from the 8th down with white pieces in capitals and empty tiles as dots.
+    pub fn diagram(&self) -> String {
+        let board = self.board();
+        let mut diagram = String::new();
+        for row in (0..8).rev() {
+            diagram.push_str(&(row + 1).to_string());
+            for col in 0..8 {
+                let letter = match board[State::encode_position(col, row) as usize] {
+                    None => '.',
+                    Some((Color::White, piece)) => piece.letter(),
+                    Some((Color::Black, piece)) => piece.letter().to_ascii_lowercase(),
+                };
+                diagram.push(' ');
+                diagram.push(letter);
+            }
+            diagram.push('\n');
+        }
+        diagram.push_str("  a b c d e f g h\n");
+        diagram
+    }
+
+    /// Finds the shortest sequence of moves of both sides leading to a checkmate of either side.
+    pub fn solve(self) -> TraversalResult<Position> {
+        let mut memo = HashSet::from([self.clone()]);
+        let mut queue = VecDeque::from([(self, Vec::new())]);
+        while let Some((position, mut path)) = queue.pop_front() {
+            path.push(position.clone());
+            let substates = position.substates();
+            if position.is_checkmate(substates.len()) {
+                return TraversalResult::CheckMate(path);
+            } else if !position.is_stalemate(substates.len()) {
+                for substate in substates {
+                    if memo.insert(substate.clone()) {
+                        queue.push_back((substate, path.clone()));
+                    }
+                }
+            }
+        }
+        TraversalResult::Inf
+    }
+}
+
+impl From<&State> for Position {
+    fn from(state: &State) -> Self {
+        let mut pieces = vec![(Color::White, Piece::King, state.white_king_pos), (Color::Black, Piece::King, state.black_king_pos)];
+        if !state.is_rook_captured() {
+            pieces.push((Color::White, Piece::Rook, state.white_rook_pos));
+        }
+        Position::new(pieces, state.turn)
+    }
+}
+
+/// Lists pieces of the side to move first, like `white Kc4 Qd1 black Kh8`.
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for color in [self.turn, self.turn.flip()] {
+            write!(f, "{}{}", if first { "" } else { " " }, color)?;
+            first = false;
+            for &(_, piece, square) in self.pieces.iter().filter(|&&(other, _, _)| other == color) {
+                write!(f, " {}{}", piece.letter(), square_to_string(square))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// Piece listed before any color.
+    MissingColor(String),
+    /// Same color listed twice.
+    RepeatedColor(Color),
+    InvalidPiece(String),
+    /// Side to move of a KRK position is neither `white` nor `black`.
+    InvalidColor(String),
+    /// KRK positions list exactly three tiles.
+    TileCount(usize),
+    /// Pieces overlap, a king is missing or duplicated, a pawn stands on an edge row or the side not to move is in check.
+    IllegalPosition,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingColor(token) => write!(f, "piece {} listed before its color", token),
+            ParseError::RepeatedColor(color) => write!(f, "color {} listed twice", color),
+            ParseError::InvalidPiece(token) => write!(f, "invalid piece: {}", token),
+            ParseError::InvalidColor(token) => write!(f, "invalid color: {}", token),
+            ParseError::TileCount(count) => write!(f, "expected 3 tiles, found {}", count),
+            ParseError::IllegalPosition => write!(f, "illegal position"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses colors followed by their pieces, the color listed first is to move.
+/// Pieces are given by their letter and tile, the letter of pawns may be left out.
+impl FromStr for Position {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut turn = None;
+        let mut color = None;
+        let mut seen = Vec::new();
+        let mut pieces = Vec::new();
+        for token in text.split_whitespace() {
+            let new_color = match token {
+                "white" => Some(Color::White),
+                "black" => Some(Color::Black),
+                _ => None,
+            };
+            if let Some(new_color) = new_color {
+                if seen.contains(&new_color) {
+                    return Err(ParseError::RepeatedColor(new_color));
+                }
+                seen.push(new_color);
+                turn = turn.or(Some(new_color));
+                color = Some(new_color);
+                continue;
+            }
+            let Some(color) = color else {
+                return Err(ParseError::MissingColor(token.to_string()));
+            };
+            let (piece, square) = match token.chars().next().and_then(Piece::from_letter) {
+                Some(piece) => (piece, &token[1..]),
+                None => (Piece::Pawn, token),
+            };
+            let square = square_from_str(square).ok_or_else(|| ParseError::InvalidPiece(token.to_string()))?;
+            pieces.push((color, piece, square));
+        }
+        let position = Position::new(pieces, turn.unwrap_or(Color::White));
+        if position.is_legal() {
+            Ok(position)
+        } else {
+            Err(ParseError::IllegalPosition)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_position {
+    use super::{ParseError, Piece, Position};
+    use crate::{Color, State, TraversalResult};
+
+    fn position(text: &str) -> Position {
+        text.parse().unwrap()
+    }
+
+    /// Random legal positions of the pieces, from a fixed xorshift sequence.
+    fn random_positions(pieces: &[(Color, Piece)], count: usize) -> Vec<Position> {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut positions = Vec::new();
+        while positions.len() < count {
+            let mut next = || {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                seed
+            };
+            let turn = if next() % 2 == 0 { Color::White } else { Color::Black };
+            let position = Position::new(pieces.iter().map(|&(color, piece)| (color, piece, (next() % 64) as i8)).collect(), turn);
+            if position.is_legal() {
+                positions.push(position);
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let parsed = position("white Kc4 Qd1 black Kh8");
+        assert_eq!(parsed.to_string(), "white Kc4 Qd1 black Kh8");
+        assert_eq!(parsed.turn(), Color::White);
+        assert_eq!(position("black Kh8 white Qd1 Kc4").to_string(), "black Kh8 white Kc4 Qd1");
+        assert_eq!(position("white Kc4 e2 black Kh8"), position("white Kc4 Pe2 black Kh8"));
+
+        assert_eq!("Kc4 white".parse::<Position>(), Err(ParseError::MissingColor("Kc4".to_string())));
+        assert_eq!("white Kc4 white Kd6".parse::<Position>(), Err(ParseError::RepeatedColor(Color::White)));
+        assert_eq!("white Kc9 black Kh8".parse::<Position>(), Err(ParseError::InvalidPiece("Kc9".to_string())));
+        assert_eq!("white Xc4 black Kh8".parse::<Position>(), Err(ParseError::InvalidPiece("Xc4".to_string())));
+        /* Missing king, shared tile, pawn on the last row and the side not to move in check. */
+        for illegal in ["white Qd1 black Kh8", "white Kc4 Qc4 black Kh8", "white Kc4 Pa8 black Kh8", "white Kc4 Qh1 black Kh8"] {
+            assert_eq!(illegal.parse::<Position>(), Err(ParseError::IllegalPosition), "{}", illegal);
+        }
+    }
+
+    #[test]
+    fn test_agrees_with_krk_state() {
+        let pieces = [(Color::White, Piece::King), (Color::White, Piece::Rook), (Color::Black, Piece::King)];
+        for position in random_positions(&pieces, 500) {
+            let [(_, _, black_king), (_, _, white_king), (_, _, white_rook)] = position.pieces()[..] else {
+                unreachable!("KRK has three pieces.");
+            };
+            let state = State::new(white_king, white_rook, black_king, position.turn());
+            let mut expected = state.substates().iter().map(Position::from).collect::<Vec<_>>();
+            let mut substates = position.substates();
+            expected.sort_by_key(|position| position.to_string());
+            substates.sort_by_key(|position| position.to_string());
+            assert_eq!(substates, expected, "{}", position);
+        }
+    }
+
+    #[test]
+    fn test_pawn_moves() {
+        let moves = |text| position(text).substates().iter().map(Position::to_string).collect::<Vec<_>>();
+        /* Single and double step, the double step is blocked. */
+        assert_eq!(moves("white Pe2 Ka1 black Kh8").iter().filter(|text| !text.contains(" Pe2")).count(), 2);
+        assert_eq!(moves("white Pe2 Ka1 black Kh8 Ne4").iter().filter(|text| !text.contains(" Pe2")).count(), 1);
+        /* Promotion to four pieces, both straight and with capture. */
+        let promotions = moves("white Pb7 Ka1 black Kh6 Nc8");
+        assert_eq!(promotions.iter().filter(|text| !text.contains(" Pb7")).count(), 8);
+        assert!(promotions.contains(&"black Kh6 white Ka1 Nc8".to_string()));
+        /* Black pawns move down. */
+        assert!(moves("black Pd7 Kh8 white Ka1").contains(&"white Ka1 black Kh8 Pd5".to_string()));
+        /* Pinned knight may not move, only the king does. */
+        assert_eq!(moves("black Ke8 Ne7 white Ka1 Re1").len(), 4);
+    }
+
+    #[test]
+    fn test_unmoves_reverse_moves() {
+        let materials: [&[(Color, Piece)]; 3] = [
+            &[(Color::White, Piece::King), (Color::White, Piece::Queen), (Color::Black, Piece::King)],
+            &[(Color::White, Piece::King), (Color::White, Piece::Bishop), (Color::White, Piece::Bishop), (Color::Black, Piece::King)],
+            &[(Color::White, Piece::King), (Color::White, Piece::Pawn), (Color::Black, Piece::King), (Color::Black, Piece::Knight)],
+        ];
+        for pieces in materials {
+            for position in random_positions(pieces, 200) {
+                for predecessor in position.unmoves() {
+                    assert!(predecessor.substates().contains(&position), "{} from {}", position, predecessor);
+                }
+                let same_material = |other: &Position| other.pieces().iter().map(|&(color, piece, _)| (color, piece)).eq(pieces.iter().copied());
+                for substate in position.substates().into_iter().filter(|substate| same_material(substate)) {
+                    assert!(substate.unmoves().contains(&position), "{} to {}", position, substate);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_and_game_end() {
+        let TraversalResult::CheckMate(path) = position("white Kb6 Qh7 black Ka8").solve() else {
+            panic!("Mate in one exists.");
+        };
+        assert_eq!(path.len(), 2);
+        /* Promotion to a queen mates. */
+        let TraversalResult::CheckMate(path) = position("white Kf6 Pg7 black Kh7").solve() else {
+            panic!("Mate exists.");
+        };
+        assert!(path.iter().any(|position| position.pieces().iter().any(|&(_, piece, _)| piece == Piece::Queen)));
+
+        let stalemate = position("black Ka8 white Kc6 Qb6");
+        assert!(stalemate.is_stalemate(stalemate.substates().len()));
+        /* Lone kings can not mate. */
+        let kings = position("white Ke1 black Ke8");
+        assert!(kings.is_insufficient_material() && kings.substates().is_empty() && !kings.is_stalemate(0));
+        assert!(matches!(kings.solve(), TraversalResult::Inf));
+    }
+}
@@ -0,0 +1,276 @@
+//! Retrograde analysis of the whole KRK endgame.
+//!
+//! Every legal position with either side to move gets its distance to mate with optimal play:
+//! White mates as soon as possible while Black delays the mate as long as possible.
+//! Distances are counted in plies, the same way as the length of paths found by [`State::solve`].
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{Color, Pos, State};
+
+/// Number of squares of the board.
+const SQUARES: usize = 64;
+/// Number of positions the table has room for, including illegal ones.
+const POSITIONS: usize = 2 * SQUARES * SQUARES * SQUARES;
+
+const ILLEGAL: u8 = u8::MAX;
+const DRAW: u8 = u8::MAX - 1;
+
+/// Header of saved tables, guards against loading unrelated files.
+const MAGIC: &[u8] = b"KRK DTM 1\n";
+
+/// Value of a position with optimal play of both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Pieces overlap, kings touch or the side not to move is in check.
+    Illegal,
+    Draw,
+    /// White mates in given number of plies, `0` if Black is already mated.
+    Mate(u8),
+}
+
+/// Distance to mate of all KRK positions, one byte per position.
+pub struct Tablebase {
+    values: Vec<u8>,
+}
+
+/// Index of the position in the table.
+fn index_of(state: &State) -> usize {
+    let turn = match state.turn {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    ((turn * SQUARES + state.white_king_pos as usize) * SQUARES + state.white_rook_pos as usize) * SQUARES + state.black_king_pos as usize
+}
+
+/// Position stored at the index of the table.
+fn state_at(index: usize) -> State {
+    let black_king = (index % SQUARES) as Pos;
+    let white_rook = (index / SQUARES % SQUARES) as Pos;
+    let white_king = (index / SQUARES / SQUARES % SQUARES) as Pos;
+    let turn = if index / SQUARES / SQUARES / SQUARES == 0 { Color::White } else { Color::Black };
+    State::new(white_king, white_rook, black_king, turn)
+}
+
+impl Tablebase {
+    /// Computes the table by retrograde analysis.
+    ///
+    /// Mates are labelled first. A position with White to move is won as soon as one of its moves
+    /// leads to a won position, a position with Black to move only when all of its moves do.
+    /// Working backwards in order of distance gives every position its shortest distance for White
+    /// and the longest one for Black. Positions never reached this way are draws.
+    pub fn generate() -> Self {
+        /* Successors of all legal positions in compressed rows, `successors[offsets[i]..offsets[i + 1]]`. */
+        let mut values = vec![ILLEGAL; POSITIONS];
+        let mut offsets = Vec::with_capacity(POSITIONS + 1);
+        let mut successors = Vec::new();
+        offsets.push(0);
+        for (index, value) in values.iter_mut().enumerate() {
+            let state = state_at(index);
+            if state.is_legal() {
+                *value = DRAW;
+                successors.extend(state.substates().iter().map(|substate| index_of(substate) as u32));
+            }
+            offsets.push(successors.len());
+        }
+
+        /* Transpose to predecessors by counting sort. */
+        let mut predecessor_offsets = vec![0; POSITIONS + 1];
+        for &successor in &successors {
+            predecessor_offsets[successor as usize + 1] += 1;
+        }
+        for index in 0..POSITIONS {
+            predecessor_offsets[index + 1] += predecessor_offsets[index];
+        }
+        let mut predecessors = vec![0u32; successors.len()];
+        let mut filled = predecessor_offsets.clone();
+        for index in 0..POSITIONS {
+            for &successor in &successors[offsets[index]..offsets[index + 1]] {
+                predecessors[filled[successor as usize]] = index as u32;
+                filled[successor as usize] += 1;
+            }
+        }
+
+        /* Positions with Black to move wait until all their successors are won. */
+        let mut unresolved = (0..POSITIONS).map(|index| offsets[index + 1] - offsets[index]).collect::<Vec<_>>();
+        let mut queue = Vec::new();
+        for index in 0..POSITIONS {
+            let state = state_at(index);
+            if values[index] == DRAW && state.turn == Color::Black && unresolved[index] == 0 && state.is_black_in_check() {
+                values[index] = 0;
+                queue.push(index);
+            }
+        }
+        drop(successors);
+
+        /* Breadth-first search backwards, layer by layer of the distance. */
+        let mut distance = 0;
+        while !queue.is_empty() {
+            distance += 1;
+            let mut next = Vec::new();
+            for index in queue {
+                for &predecessor in &predecessors[predecessor_offsets[index]..predecessor_offsets[index + 1]] {
+                    let predecessor = predecessor as usize;
+                    if values[predecessor] != DRAW {
+                        continue;
+                    }
+                    unresolved[predecessor] -= 1;
+                    /* White needs one winning move, Black is lost once no move escapes. */
+                    if predecessor < POSITIONS / 2 || unresolved[predecessor] == 0 {
+                        values[predecessor] = distance;
+                        next.push(predecessor);
+                    }
+                }
+            }
+            queue = next;
+        }
+        Self { values }
+    }
+
+    /// Loads a table saved with [`Tablebase::save`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        match bytes.strip_prefix(MAGIC) {
+            Some(values) if values.len() == POSITIONS => Ok(Self { values: values.to_vec() }),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Not a KRK tablebase.")),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, [MAGIC, &self.values].concat())
+    }
+
+    /// Loads the table from the path, generating and saving it first if the file does not exist.
+    pub fn load_or_generate(path: impl AsRef<Path>) -> io::Result<Self> {
+        match Self::load(&path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let tablebase = Self::generate();
+                tablebase.save(path)?;
+                Ok(tablebase)
+            }
+            result => result,
+        }
+    }
+
+    pub fn probe(&self, state: &State) -> Outcome {
+        match self.values[index_of(state)] {
+            ILLEGAL => Outcome::Illegal,
+            DRAW => Outcome::Draw,
+            plies => Outcome::Mate(plies),
+        }
+    }
+
+    /// Best move of the side to move, `None` if the game is over.
+    /// In lost positions Black picks the move delaying the mate the most, in drawn ones a move keeping the draw.
+    pub fn best_move(&self, state: &State) -> Option<State> {
+        let substates = state.substates();
+        match self.probe(state) {
+            Outcome::Illegal | Outcome::Mate(0) => None,
+            Outcome::Mate(plies) => substates.into_iter().find(|substate| self.probe(substate) == Outcome::Mate(plies - 1)),
+            Outcome::Draw => substates.into_iter().find(|substate| self.probe(substate) == Outcome::Draw),
+        }
+    }
+
+    /// Line of best moves starting with the state and ending with the mate, `None` if the position is not won.
+    pub fn line(&self, state: &State) -> Option<Vec<State>> {
+        let Outcome::Mate(_) = self.probe(state) else {
+            return None;
+        };
+        let mut line = vec![state.clone()];
+        while let Some(next) = self.best_move(line.last().expect("Line is never empty.")) {
+            line.push(next);
+        }
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests_tablebase {
+    use std::sync::OnceLock;
+
+    use super::{index_of, state_at, Outcome, Tablebase, POSITIONS};
+    use crate::{Color, State};
+
+    /// Generating takes a while, all tests share one table.
+    fn tablebase() -> &'static Tablebase {
+        static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+        TABLEBASE.get_or_init(Tablebase::generate)
+    }
+
+    #[test]
+    fn test_index_round_trip() {
+        for index in [0, 1, 4095, 4096, POSITIONS / 2, POSITIONS - 1] {
+            assert_eq!(index_of(&state_at(index)), index);
+        }
+        assert_eq!(index_of(&State::from("white a1 c2 a3")), 17 * 64 + 2);
+    }
+
+    #[test]
+    fn test_mates_and_illegal_positions() {
+        let tablebase = tablebase();
+        assert_eq!(tablebase.probe(&State::from("black b3 h1 a1")), Outcome::Mate(0));
+        assert_eq!(tablebase.probe(&State::from("white b3 h2 a1")), Outcome::Mate(1));
+        assert_eq!(tablebase.best_move(&State::from("white b3 h2 a1")), Some(State::from("black b3 h1 a1")));
+        /* Kings touching, pieces sharing a square and Black in check with White to move, `State::from` rejects them. */
+        for (white_king, white_rook, black_king, turn) in [(9, 15, 0, Color::White), (0, 0, 63, Color::Black), (17, 56, 0, Color::White)] {
+            assert_eq!(tablebase.probe(&State::new(white_king, white_rook, black_king, turn)), Outcome::Illegal);
+        }
+        /* Taking the undefended rook draws, the position after the capture is final. */
+        let capture = State::from("black c4 h2 g1").substates().into_iter().find(State::is_rook_captured).unwrap();
+        assert_eq!(tablebase.probe(&capture), Outcome::Draw);
+        assert_eq!(tablebase.probe(&State::from("black c4 h2 g1")), Outcome::Draw);
+        assert_eq!(tablebase.best_move(&State::from("black c4 h2 g1")), Some(capture));
+    }
+
+    #[test]
+    fn test_distances_are_consistent() {
+        let tablebase = tablebase();
+        /* Longest distance with White to move. */
+        let mut longest = 0;
+        for index in 0..POSITIONS {
+            let state = state_at(index);
+            let Outcome::Mate(plies) = tablebase.probe(&state) else {
+                continue;
+            };
+            let distances = state.substates().iter().map(|substate| match tablebase.probe(substate) {
+                Outcome::Mate(plies) => Some(plies as u32 + 1),
+                _ => None,
+            }).collect::<Vec<_>>();
+            match state.turn {
+                Color::White => {
+                    longest = longest.max(plies);
+                    assert_eq!(distances.iter().flatten().min(), Some(&(plies as u32)), "{}", state);
+                }
+                Color::Black if plies == 0 => assert!(distances.is_empty()),
+                Color::Black => {
+                    assert!(distances.iter().all(Option::is_some), "{}", state);
+                    assert_eq!(distances.iter().flatten().max(), Some(&(plies as u32)), "{}", state);
+                }
+            }
+        }
+        /* The longest KRK mate takes 16 moves. */
+        assert_eq!(longest, 31);
+    }
+
+    #[test]
+    fn test_line_and_saving() {
+        let tablebase = tablebase();
+        let state = State::from("black g8 h1 c4");
+        let Outcome::Mate(plies) = tablebase.probe(&state) else {
+            panic!("Position is won.");
+        };
+        let line = tablebase.line(&state).unwrap();
+        assert_eq!(line.len(), plies as usize + 1);
+        assert_eq!(tablebase.probe(line.last().unwrap()), Outcome::Mate(0));
+
+        let path = std::env::temp_dir().join(format!("krk-tablebase-{}.bin", std::process::id()));
+        tablebase.save(&path).unwrap();
+        let loaded = Tablebase::load(&path).unwrap();
+        assert!(loaded.values == tablebase.values);
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(Tablebase::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
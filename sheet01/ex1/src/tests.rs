@@ -16,7 +16,7 @@ mod tests {
         for substate in state.substates() {
             assert!(substates.contains(&substate));
         }
-        assert_eq!(state.substates().count(), substates.len());
+        assert_eq!(state.substates().len(), substates.len());
     }
 
     #[test]
@@ -30,7 +30,7 @@ mod tests {
         for substate in state.substates() {
             assert!(substates.contains(&substate));
         }
-        assert_eq!(state.substates().count(), substates.len());
+        assert_eq!(state.substates().len(), substates.len());
     }
 
     #[test]
@@ -49,7 +49,7 @@ mod tests {
         for substate in state.substates() {
             assert!(substates.contains(&substate));
         }
-        assert_eq!(state.substates().count(), substates.len());
+        assert_eq!(state.substates().len(), substates.len());
     }
 
     #[test]
@@ -101,4 +101,197 @@ mod tests {
         }
         assert_eq!(state.rook_moves().count(), rook_substates.len());
     }
-}
\ No newline at end of file
+}
+
+use crate::tablebase::Tablebase;
+use crate::{Color, Mode, Pos, State, TraversalResult};
+
+#[test]
+fn black_king_takes_undefended_rook() {
+    let state = State::from("black c4 h2 g1");
+    let substates = state.substates();
+    assert_eq!(substates.len(), 2);
+    assert!(substates.contains(&State::from("white c4 h2 f1")));
+    let capture = substates.into_iter().find(State::is_rook_captured).unwrap();
+    assert!(capture.substates().is_empty());
+    assert!(!capture.is_checkmate(0) && !capture.is_stalemate(0));
+
+    // the white king guards the rook.
+    assert_eq!(State::from("black g3 h2 g1").substates(), [State::from("white g3 h2 f1")]);
+}
+
+#[test]
+fn white_king_blocks_rook_line() {
+    let state = State::from("black d1 a1 g2");
+    let substates = state.substates();
+    assert_eq!(substates.len(), 8);
+    assert!(substates.contains(&State::from("white d1 a1 g1")));
+    // the black king itself does not block the line, it can not step to c1.
+    let state = State::from("black h4 a1 b1");
+    assert!(state.substates().iter().all(State::is_legal));
+    assert_eq!(state.substates().len(), 3);
+}
+
+#[test]
+fn rook_may_move_next_to_black_king() {
+    assert!(State::from("white c6 h7 a8").rook_moves().any(|state| state == State::from("black c6 b7 a8")));
+}
+
+#[test]
+fn checkmate_and_stalemate() {
+    let mate = State::from("black b3 h1 a1");
+    assert!(mate.substates().is_empty() && mate.is_checkmate(0) && !mate.is_stalemate(0));
+    let stalemate = State::from("black c8 h7 a8");
+    assert!(stalemate.substates().is_empty() && stalemate.is_stalemate(0) && !stalemate.is_checkmate(0));
+    assert!(matches!(stalemate.solve(), TraversalResult::Inf));
+}
+
+#[test]
+fn illegal_positions_are_rejected() {
+    // kings touching, pawns sharing a tile and black in check with white to move.
+    for position in ["white b2 h2 a1", "black a1 a1 h8", "black c4 h8 h8", "white b3 h1 a1"] {
+        assert!(std::panic::catch_unwind(|| State::from(position)).is_err(), "{}", position);
+    }
+    // black may be in check when it is to move.
+    assert!(State::from("black b3 h1 a1").is_black_in_check());
+}
+
+fn moves(result: TraversalResult) -> Option<usize> {
+    match result {
+        TraversalResult::Inf => None,
+        TraversalResult::CheckMate(path) => Some(path.len() - 1),
+    }
+}
+
+#[test]
+fn helpmate_and_optimal_modes() {
+    let tablebase = Tablebase::generate();
+    let positions = [("black g8 h1 c4", 10, 28), ("black b4 f3 e8", 6, 26), ("white a1 e3 b7", 9, 19), ("black h7 a2 f2", 6, 28)];
+    for (position, helpmate, optimal) in positions {
+        let state = State::from(position);
+        assert_eq!(moves(state.clone().solve()), Some(helpmate), "{}", position);
+        // black no longer helps, so the mate takes longer.
+        assert_eq!(moves(state.solve_optimal(&tablebase)), Some(optimal), "{}", position);
+    }
+    // mate in one can not be delayed.
+    assert_eq!(moves(State::from("white b3 h2 a1").solve_optimal(&tablebase)), Some(1));
+    assert_eq!("optimal".parse(), Ok(Mode::Optimal));
+    assert_eq!("helpmate".parse(), Ok(Mode::Helpmate));
+    assert!("fastest".parse::<Mode>().is_err());
+}
+
+/// Number of positions reached after exactly `depth` plies, games ending earlier are not counted.
+fn perft(state: &State, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    state.substates().iter().map(|substate| perft(substate, depth - 1)).sum()
+}
+
+/// Moves generated tile by tile straight from the rules, slow but independent of `substates`.
+fn slow_substates(state: &State) -> Vec<State> {
+    let tile = |position: Pos| State::decode_position(position);
+    let king_step = |from: Pos, to: Pos| {
+        let ((from_col, from_row), (to_col, to_row)) = (tile(from), tile(to));
+        from != to && (from_col - to_col).abs() <= 1 && (from_row - to_row).abs() <= 1
+    };
+    // the rook reaches the tile along a line with no piece strictly between.
+    let rook_reaches = |rook: Pos, to: Pos, pieces: &[Pos]| {
+        let ((rook_col, rook_row), (to_col, to_row)) = (tile(rook), tile(to));
+        let between = |piece: Pos| {
+            let (col, row) = tile(piece);
+            if rook_col == to_col {
+                col == to_col && rook_row.min(to_row) < row && row < rook_row.max(to_row)
+            } else {
+                row == to_row && rook_col.min(to_col) < col && col < rook_col.max(to_col)
+            }
+        };
+        rook != to && (rook_col == to_col || rook_row == to_row) && !pieces.iter().any(|&piece| between(piece))
+    };
+    if state.is_rook_captured() {
+        return Vec::new();
+    }
+    let (white_king, rook, black_king) = (state.white_king_pos, state.white_rook_pos, state.black_king_pos);
+    let mut substates = Vec::new();
+    for to in 0..64 {
+        match state.turn {
+            Color::Black => {
+                // the black king does not shield the tiles behind it from the rook.
+                let attacked = to != rook && rook_reaches(rook, to, &[white_king]);
+                if king_step(black_king, to) && !king_step(white_king, to) && to != white_king && !attacked {
+                    substates.push(State::new(white_king, rook, to, Color::White));
+                }
+            }
+            Color::White => {
+                if king_step(white_king, to) && !king_step(black_king, to) && to != rook && to != black_king {
+                    substates.push(State::new(to, rook, black_king, Color::Black));
+                }
+                if rook_reaches(rook, to, &[white_king, black_king]) && to != white_king && to != black_king {
+                    substates.push(State::new(white_king, to, black_king, Color::Black));
+                }
+            }
+        }
+    }
+    substates
+}
+
+fn slow_perft(state: &State, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    slow_substates(state).iter().map(|substate| slow_perft(substate, depth - 1)).sum()
+}
+
+/// Random legal positions, from a fixed xorshift sequence.
+fn random_states(count: usize) -> Vec<State> {
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    let mut states = Vec::new();
+    while states.len() < count {
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 64) as Pos
+        };
+        let turn = if next() % 2 == 0 { Color::White } else { Color::Black };
+        let state = State::new(next(), next(), next(), turn);
+        if state.is_legal() && !state.is_rook_captured() {
+            states.push(state);
+        }
+    }
+    states
+}
+
+#[test]
+fn perft_reference_counts() {
+    // leaf counts for depths 1 to 4, in the last position black may take the undefended rook.
+    let references = [
+        ("black c4 c8 h3", [5, 90, 573, 11872]),
+        ("black g8 h1 c4", [8, 152, 1087, 20044]),
+        ("white a1 e3 b7", [17, 120, 2099, 10456]),
+        ("black a2 e4 a4", [2, 38, 176, 3183]),
+        ("white c4 h2 g1", [22, 52, 882, 3439]),
+    ];
+    for (position, counts) in references {
+        let state = State::from(position);
+        for (depth, &count) in (1..).zip(&counts) {
+            assert_eq!(perft(&state, depth), count, "{} at depth {}", position, depth);
+        }
+    }
+}
+
+#[test]
+fn substates_match_slow_generator() {
+    for state in random_states(20_000) {
+        let mut substates = state.substates();
+        let mut expected = slow_substates(&state);
+        substates.sort_by_key(State::to_string);
+        expected.sort_by_key(State::to_string);
+        assert_eq!(substates, expected, "{}", state);
+        assert!(substates.iter().all(State::is_legal), "{}", state);
+    }
+    // perft compares whole trees, including captures and positions after them.
+    for state in random_states(50) {
+        assert_eq!(perft(&state, 3), slow_perft(&state, 3), "{}", state);
+    }
+}
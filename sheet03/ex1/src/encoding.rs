@@ -0,0 +1,206 @@
+use sat::cardinality::{at_most, exactly_one};
+use sat::cnf::Cnf;
+use sat::lit::{Lit, Var};
+use sat::model::Model;
+use sat::solver::{SolveResult, Solver};
+
+use crate::grid::Grid;
+use crate::nonogram::{BlockSpec, Color, Field, Nonogram};
+
+/// Nonogram encoded as a CNF formula.
+///
+/// Every field has a variable per colour, true if the field has that colour, at most one of them is true.
+/// Every block of a line has a variable per position it can start at, exactly one of them is true.
+/// Blocks keep their order and gaps, and a field has a colour if and only if a block of that colour covers it.
+/// Fields already known in the grid of the puzzle are fixed by unit clauses.
+pub struct NonogramEncoding {
+    cnf: Cnf,
+    height: usize,
+    width: usize,
+    colors: Vec<Color>,
+    fields: Vec<Var>,
+}
+
+impl NonogramEncoding {
+    pub fn new(nonogram: &Nonogram) -> Self {
+        let grid = nonogram.grid();
+        let (height, width) = (grid.height(), grid.width());
+        let mut cnf = Cnf::new();
+        let fields = cnf.new_vars(height * width * grid.colors().len());
+        let mut encoding = Self { cnf, height, width, colors: grid.colors().to_vec(), fields };
+
+        for row in 0..height {
+            for col in 0..width {
+                let lits = encoding.field_lits(row, col).collect::<Vec<_>>();
+                at_most(&mut encoding.cnf, &lits, 1);
+                for field in grid.values().filter(|&field| !grid.can_be(row, col, field)) {
+                    match field {
+                        Field::Empty => encoding.cnf.add_clause(lits.iter().copied()),
+                        Field::Filled(color) => encoding.cnf.add_clause([!encoding.field_lit(row, col, color)]),
+                    }
+                }
+            }
+        }
+        for (row, spec) in nonogram.row_spec().0.iter().enumerate() {
+            encoding.encode_line(spec, &(0..width).map(|col| (row, col)).collect::<Vec<_>>());
+        }
+        for (col, spec) in nonogram.col_spec().0.iter().enumerate() {
+            encoding.encode_line(spec, &(0..height).map(|row| (row, col)).collect::<Vec<_>>());
+        }
+        encoding
+    }
+
+    pub fn cnf(&self) -> &Cnf {
+        &self.cnf
+    }
+
+    /// Literal true if the field has given colour.
+    pub fn field_lit(&self, row: usize, col: usize, color: Color) -> Lit {
+        let index = self.colors.iter().position(|&other| other == color).expect("Colour belongs to the puzzle.");
+        self.fields[(row * self.width + col) * self.colors.len() + index].positive()
+    }
+
+    fn field_lits(&self, row: usize, col: usize) -> impl Iterator<Item=Lit> + '_ {
+        self.colors.iter().map(move |&color| self.field_lit(row, col, color))
+    }
+
+    /// Encodes blocks of a line made of given fields with block-start variables.
+    fn encode_line(&mut self, spec: &BlockSpec, fields: &[(usize, usize)]) {
+        let blocks = &spec.0;
+        let needs_gap = |j: usize| usize::from(j + 1 < blocks.len() && blocks[j].color == blocks[j + 1].color);
+        let mut earliest = Vec::with_capacity(blocks.len());
+        let mut start = 0;
+        for (j, block) in blocks.iter().enumerate() {
+            earliest.push(start);
+            start += block.size + needs_gap(j);
+        }
+        let mut latest = vec![0; blocks.len()];
+        let mut end = fields.len() as isize;
+        for (j, block) in blocks.iter().enumerate().rev() {
+            end -= (block.size + needs_gap(j)) as isize;
+            latest[j] = end;
+        }
+        if blocks.iter().enumerate().any(|(j, _)| latest[j] < earliest[j] as isize) {
+            self.cnf.add_clause([]);
+            return;
+        }
+
+        /* starts[j][p] - block j starts at position earliest[j] + p. */
+        let starts = (0..blocks.len()).map(|j| self.cnf.new_vars(latest[j] as usize - earliest[j] + 1)).collect::<Vec<_>>();
+        for block_starts in &starts {
+            exactly_one(&mut self.cnf, &block_starts.iter().map(|var| var.positive()).collect::<Vec<_>>());
+        }
+        for j in 1..blocks.len() {
+            let distance = blocks[j - 1].size + needs_gap(j - 1);
+            for (p, previous) in starts[j - 1].iter().enumerate() {
+                for (q, next) in starts[j].iter().enumerate() {
+                    if earliest[j] + q < earliest[j - 1] + p + distance {
+                        self.cnf.add_clause([previous.negative(), next.negative()]);
+                    }
+                }
+            }
+        }
+
+        let colors = self.colors.clone();
+        for (position, &(row, col)) in fields.iter().enumerate() {
+            for &color in &colors {
+                let field = self.field_lit(row, col, color);
+                let mut covering = Vec::new();
+                for (j, block) in blocks.iter().enumerate().filter(|(_, block)| block.color == color) {
+                    for (p, &start) in starts[j].iter().enumerate() {
+                        let first = earliest[j] + p;
+                        if (first..first + block.size).contains(&position) {
+                            self.cnf.add_clause([start.negative(), field]);
+                            covering.push(start.positive());
+                        }
+                    }
+                }
+                self.cnf.add_clause(std::iter::once(!field).chain(covering));
+            }
+        }
+    }
+
+    /// Grid of the solution described by the model.
+    pub fn decode(&self, model: &Model) -> Grid {
+        let mut grid = Grid::with_colors(self.height, self.width, &self.colors);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let color = self.colors.iter().copied().find(|&color| model.satisfies(self.field_lit(row, col, color)));
+                grid.set(row, col, color.map_or(Field::Empty, Field::Filled));
+            }
+        }
+        grid
+    }
+
+    /// Clause excluding the solution described by the model.
+    fn blocking_clause(&self, model: &Model) -> Vec<Lit> {
+        self.fields.iter().map(|&var| if model.value(var) { var.negative() } else { var.positive() }).collect()
+    }
+
+    /// Finds any solution with the SAT solver.
+    pub fn solve(&self) -> Option<Grid> {
+        Solver::from_cnf(&self.cnf).solve().model().map(|model| self.decode(model))
+    }
+
+    /// Counts solutions with the SAT solver, stops counting after reaching `cap`.
+    pub fn count_solutions(&self, cap: usize) -> usize {
+        let mut solver = Solver::from_cnf(&self.cnf);
+        let mut count = 0;
+        while count < cap {
+            let SolveResult::Sat(model) = solver.solve() else { break };
+            count += 1;
+            solver.add_clause(self.blocking_clause(&model));
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests_encoding {
+    use super::NonogramEncoding;
+    use crate::generator::Generator;
+    use crate::nonogram::Nonogram;
+
+    #[test]
+    fn test_solves_like_propagation() {
+        let mut generator = Generator::new(7);
+        for (height, width) in [(5, 5), (8, 6), (10, 10)] {
+            for _ in 0..5 {
+                let image = generator.random_image(height, width, 0.55);
+                let nonogram = Nonogram::from_image(&image);
+                let encoding = NonogramEncoding::new(&nonogram);
+                assert_eq!(encoding.count_solutions(3), nonogram.count_solutions(3));
+                let solution = encoding.solve().unwrap();
+                assert_eq!(Nonogram::from_image(&solution).puzzle_text(), nonogram.puzzle_text());
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_puzzles() {
+        let mut generator = Generator::new(3);
+        for _ in 0..3 {
+            let puzzle = generator.random_puzzle(12, 12, 0.5).unwrap();
+            let encoding = NonogramEncoding::new(&puzzle.nonogram);
+            assert_eq!(encoding.solve().as_ref(), Some(&puzzle.solution));
+            assert_eq!(encoding.count_solutions(2), 1);
+        }
+    }
+
+    #[test]
+    fn test_colors_and_givens() {
+        let nonogram = "2 3\n1r 1b\n2r\n2r\n1b 1r\n0\n".parse::<Nonogram>().unwrap();
+        let encoding = NonogramEncoding::new(&nonogram);
+        assert_eq!(encoding.count_solutions(5), 1);
+        assert_eq!(encoding.solve().unwrap().to_string(), "rb.\nrr.\n");
+
+        /* Two diagonals fit the clues, the given field picks one of them. */
+        let nonogram = "2 2\n1\n1\n1\n1\n".parse::<Nonogram>().unwrap();
+        assert_eq!(NonogramEncoding::new(&nonogram).count_solutions(5), 2);
+        let nonogram = "2 2\n1\n1\n1\n1\n#?\n??\n".parse::<Nonogram>().unwrap();
+        assert_eq!(NonogramEncoding::new(&nonogram).solve().unwrap().to_string(), "#.\n.#\n");
+        assert_eq!(NonogramEncoding::new(&nonogram).count_solutions(5), 1);
+
+        assert!(NonogramEncoding::new(&"1 2\n1\n0\n0\n".parse().unwrap()).solve().is_none());
+    }
+}
@@ -0,0 +1,331 @@
+//! Simple JSON description of the puzzle:
+//!
+//! ```json
+//! {
+//!   "rows": [[1, 1], [1]],
+//!   "columns": [[1], [1], [1]],
+//!   "solution": ["#.#", ".#."]
+//! }
+//! ```
+//!
+//! Blocks of colour puzzles are written as strings with the colour letter after the size, like `"3r"`.
+//! `solution` is optional and uses the same characters as the text grid. Unknown keys are ignored.
+
+use crate::formats::text::parse_field;
+use crate::formats::{Cursor, PuzzleFile};
+use crate::grid::Grid;
+use crate::nonogram::{Block, BlockSpec, Color, Nonogram, RowSpec};
+use crate::parse::{check_fits, parse_block, ParseError, ParseErrorKind};
+
+enum Value {
+    Null,
+    Bool,
+    Number(String),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+/// JSON value with the position where it starts.
+struct Node {
+    line: usize,
+    column: usize,
+    value: Value,
+}
+
+impl Node {
+    fn error(&self, expected: &str) -> ParseError {
+        ParseError::new(self.line, self.column, ParseErrorKind::InvalidSyntax(format!("expected {}", expected)))
+    }
+
+    fn as_array(&self) -> Result<&[Node], ParseError> {
+        match &self.value {
+            Value::Array(items) => Ok(items),
+            _ => Err(self.error("an array")),
+        }
+    }
+}
+
+fn syntax_error(cursor: &Cursor, message: &str) -> ParseError {
+    let (line, column) = cursor.position();
+    ParseError::new(line, column, ParseErrorKind::InvalidSyntax(message.to_string()))
+}
+
+/// Consumes `expected` characters, failing on the first difference.
+fn expect(cursor: &mut Cursor, expected: &str) -> Result<(), ParseError> {
+    for character in expected.chars() {
+        match cursor.peek() {
+            Some(next) if next == character => cursor.bump(),
+            Some(_) => return Err(syntax_error(cursor, &format!("expected {:?}", expected))),
+            None => return Err(cursor.error(ParseErrorKind::UnexpectedEnd)),
+        };
+    }
+    Ok(())
+}
+
+fn parse_string(cursor: &mut Cursor) -> Result<String, ParseError> {
+    expect(cursor, "\"")?;
+    let mut string = String::new();
+    loop {
+        let escape_position = cursor.position();
+        match cursor.bump().ok_or(cursor.error(ParseErrorKind::UnexpectedEnd))? {
+            '"' => return Ok(string),
+            '\\' => {
+                let escaped = match cursor.bump().ok_or(cursor.error(ParseErrorKind::UnexpectedEnd))? {
+                    '"' => '"',
+                    '\\' => '\\',
+                    '/' => '/',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let mut code = 0;
+                        for _ in 0..4 {
+                            let digit = cursor.peek().and_then(|digit| digit.to_digit(16));
+                            code = code * 16 + digit.ok_or(syntax_error(cursor, "expected a hexadecimal digit"))?;
+                            cursor.bump();
+                        }
+                        /* Surrogate pairs are not needed for clues, they are replaced. */
+                        char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                    }
+                    _ => {
+                        let (line, column) = escape_position;
+                        return Err(ParseError::new(line, column, ParseErrorKind::InvalidSyntax(String::from("invalid escape"))));
+                    }
+                };
+                string.push(escaped);
+            }
+            character => string.push(character),
+        }
+    }
+}
+
+/// Consumes `,` between items or the closing bracket. Returns whether more items follow.
+fn separator(cursor: &mut Cursor, close: char) -> Result<bool, ParseError> {
+    cursor.skip_whitespace();
+    let more = match cursor.peek() {
+        Some(',') => true,
+        Some(next) if next == close => false,
+        Some(_) => return Err(syntax_error(cursor, &format!("expected ',' or '{}'", close))),
+        None => return Err(cursor.error(ParseErrorKind::UnexpectedEnd)),
+    };
+    cursor.bump();
+    Ok(more)
+}
+
+fn parse_value(cursor: &mut Cursor) -> Result<Node, ParseError> {
+    cursor.skip_whitespace();
+    let (line, column) = cursor.position();
+    let value = match cursor.peek().ok_or(cursor.error(ParseErrorKind::UnexpectedEnd))? {
+        '{' => {
+            cursor.bump();
+            let mut members = Vec::new();
+            cursor.skip_whitespace();
+            if cursor.peek() == Some('}') {
+                cursor.bump();
+            } else {
+                loop {
+                    cursor.skip_whitespace();
+                    let key = parse_string(cursor)?;
+                    cursor.skip_whitespace();
+                    expect(cursor, ":")?;
+                    members.push((key, parse_value(cursor)?));
+                    if !separator(cursor, '}')? {
+                        break;
+                    }
+                }
+            }
+            Value::Object(members)
+        }
+        '[' => {
+            cursor.bump();
+            let mut items = Vec::new();
+            cursor.skip_whitespace();
+            if cursor.peek() == Some(']') {
+                cursor.bump();
+            } else {
+                loop {
+                    items.push(parse_value(cursor)?);
+                    if !separator(cursor, ']')? {
+                        break;
+                    }
+                }
+            }
+            Value::Array(items)
+        }
+        '"' => Value::String(parse_string(cursor)?),
+        't' => expect(cursor, "true").map(|_| Value::Bool)?,
+        'f' => expect(cursor, "false").map(|_| Value::Bool)?,
+        'n' => expect(cursor, "null").map(|_| Value::Null)?,
+        '-' | '0'..='9' => {
+            let mut number = String::new();
+            while let Some(next) = cursor.peek().filter(|next| matches!(next, '-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+                number.push(next);
+                cursor.bump();
+            }
+            Value::Number(number)
+        }
+        _ => return Err(syntax_error(cursor, "expected a value")),
+    };
+    Ok(Node { line, column, value })
+}
+
+/// Reads array of arrays of blocks, one inner array per line.
+fn parse_specs(node: &Node, length: Option<usize>) -> Result<RowSpec, ParseError> {
+    let mut specs = Vec::new();
+    for line in node.as_array()? {
+        let mut blocks = Vec::new();
+        for block in line.as_array()? {
+            let block = match &block.value {
+                Value::Number(number) => match number.parse::<usize>() {
+                    Ok(size) => Block { size, color: Color::BLACK },
+                    Err(_) => return Err(ParseError::new(block.line, block.column, ParseErrorKind::InvalidNumber(number.clone()))),
+                },
+                /* Column after the opening quote. */
+                Value::String(token) => parse_block(block.line, block.column + 1, token)?,
+                _ => return Err(block.error("a block")),
+            };
+            if block.size != 0 {
+                blocks.push(block);
+            }
+        }
+        let spec = BlockSpec(blocks);
+        if let Some(length) = length {
+            check_fits(&spec, length, line.line).map_err(|error| ParseError { column: line.column, ..error })?;
+        }
+        specs.push(spec);
+    }
+    Ok(RowSpec(specs))
+}
+
+/// Reads solution of a puzzle whose empty grid is given.
+fn parse_solution(node: &Node, empty: &Grid) -> Result<Grid, ParseError> {
+    let (height, width) = (empty.height(), empty.width());
+    let rows = node.as_array()?;
+    if rows.len() != height {
+        return Err(node.error(&format!("{} solution rows", height)));
+    }
+    let mut solution = empty.clone();
+    for (row, line) in rows.iter().enumerate() {
+        let Value::String(fields) = &line.value else { return Err(line.error("a string")) };
+        if fields.chars().count() != width {
+            let kind = ParseErrorKind::InvalidLineLength { expected: width, found: fields.chars().count() };
+            return Err(ParseError::new(line.line, line.column, kind));
+        }
+        for (col, character) in fields.chars().enumerate() {
+            let invalid = || ParseError::new(line.line, line.column + col + 1, ParseErrorKind::InvalidField(character));
+            match parse_field(character).map_err(|_| invalid())? {
+                Some(field) if solution.plane(field).is_none() => return Err(invalid()),
+                Some(field) => solution.set(row, col, field),
+                None => {}
+            }
+        }
+    }
+    Ok(solution)
+}
+
+/// Reads puzzle from JSON together with its solution, if it is given.
+pub fn read_json(text: &str) -> Result<PuzzleFile, ParseError> {
+    let mut cursor = Cursor::new(text);
+    let root = parse_value(&mut cursor)?;
+    cursor.skip_whitespace();
+    if cursor.peek().is_some() {
+        return Err(syntax_error(&cursor, "unexpected data after the document"));
+    }
+    let Value::Object(members) = &root.value else { return Err(root.error("an object")) };
+    let member = |key: &'static str| members.iter().rev().find(|(name, _)| name == key).map(|(_, node)| node);
+    let missing = |key| ParseError::new(root.line, root.column, ParseErrorKind::MissingKey(key));
+
+    let rows = member("rows").ok_or_else(|| missing("rows"))?;
+    let columns = member("columns").ok_or_else(|| missing("columns"))?;
+    let (height, width) = (rows.as_array()?.len(), columns.as_array()?.len());
+    let row_spec = parse_specs(rows, Some(width))?;
+    let col_spec = parse_specs(columns, Some(height))?;
+    let nonogram = Nonogram::from_specs(row_spec, col_spec);
+    let solution = match member("solution") {
+        None | Some(Node { value: Value::Null, .. }) => None,
+        Some(solution) => Some(parse_solution(solution, nonogram.grid())?),
+    };
+    Ok(PuzzleFile { nonogram, solution })
+}
+
+/// Writes the puzzle as JSON, adding the solution if given.
+pub fn write_json(nonogram: &Nonogram, solution: Option<&Grid>) -> String {
+    let specs = |spec: &RowSpec| spec.0.iter().map(|spec| {
+        let blocks = spec.0.iter().map(|block| match block.color {
+            Color::BLACK => block.to_string(),
+            _ => format!("\"{}\"", block),
+        });
+        format!("[{}]", blocks.collect::<Vec<_>>().join(", "))
+    }).collect::<Vec<_>>().join(",\n    ");
+    let mut json = format!(
+        "{{\n  \"rows\": [\n    {}\n  ],\n  \"columns\": [\n    {}\n  ]",
+        specs(nonogram.row_spec()),
+        specs(nonogram.col_spec())
+    );
+    if let Some(solution) = solution {
+        let rows = solution.to_string().lines().map(|row| format!("\"{}\"", row)).collect::<Vec<_>>().join(",\n    ");
+        json.push_str(&format!(",\n  \"solution\": [\n    {}\n  ]", rows));
+    }
+    json.push_str("\n}\n");
+    json
+}
+
+#[cfg(test)]
+mod tests_json {
+    use super::{read_json, write_json};
+    use crate::nonogram::Nonogram;
+    use crate::parse::{ParseError, ParseErrorKind};
+
+    #[test]
+    fn test_read() {
+        let text = r##"{"title": "a\"b\u0041", "rows": [[1, 1], [0]], "columns": [[1], [], [1]], "solution": ["#.#", "..."], "x": null}"##;
+        let file = read_json(text).unwrap();
+        assert_eq!(file.nonogram.puzzle_text(), "2 3\n1 1\n0\n1\n0\n1\n");
+        assert_eq!(file.solution.unwrap().to_string(), "#.#\n...\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut nonogram = "2 3\n1 1\n1\n1\n1\n1\n".parse::<Nonogram>().unwrap();
+        let json = write_json(&nonogram, None);
+        assert_eq!(json, "{\n  \"rows\": [\n    [1, 1],\n    [1]\n  ],\n  \"columns\": [\n    [1],\n    [1],\n    [1]\n  ]\n}\n");
+        assert!(read_json(&json).unwrap().solution.is_none());
+
+        nonogram.solve().unwrap();
+        let file = read_json(&write_json(&nonogram, Some(nonogram.grid()))).unwrap();
+        assert_eq!(file.nonogram.puzzle_text(), nonogram.puzzle_text());
+        assert_eq!(file.solution.as_ref(), Some(nonogram.grid()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(read_json("{\"rows\": []}").unwrap_err(), ParseError::new(1, 1, ParseErrorKind::MissingKey("columns")));
+        assert_eq!(read_json("{\"rows\": [[1,]]}").unwrap_err().column, 14);
+        assert_eq!(read_json("{\"rows\": [[1]], \"columns\": [[1]]").unwrap_err().kind, ParseErrorKind::UnexpectedEnd);
+        let kind = ParseErrorKind::InvalidNumber(String::from("1.5"));
+        assert_eq!(read_json("{\"rows\": [[1]],\n\"columns\": [[1.5]]}").unwrap_err(), ParseError::new(2, 14, kind));
+        let kind = ParseErrorKind::BlocksTooLong { required: 2, length: 1 };
+        assert_eq!(read_json("{\"rows\": [[2]], \"columns\": [[1]]}").unwrap_err(), ParseError::new(1, 11, kind));
+        let kind = ParseErrorKind::InvalidField('x');
+        let text = "{\"rows\": [[1]], \"columns\": [[1]], \"solution\": [\"x\"]}";
+        assert_eq!(read_json(text).unwrap_err(), ParseError::new(1, 49, kind));
+    }
+
+    #[test]
+    fn test_colors() {
+        let text = r#"{"rows": [["1r", "1b"]], "columns": [["1r"], ["1b"]], "solution": ["rb"]}"#;
+        let file = read_json(text).unwrap();
+        assert_eq!(file.nonogram.puzzle_text(), "1 2\n1r 1b\n1r\n1b\n");
+        let json = write_json(&file.nonogram, file.solution.as_ref());
+        assert!(json.contains(r#"["1r", "1b"]"#));
+        assert_eq!(read_json(&json).unwrap().solution, file.solution);
+
+        let text = r#"{"rows": [["1r"]], "columns": [["1r"]], "solution": ["g"]}"#;
+        assert_eq!(read_json(text).unwrap_err(), ParseError::new(1, 55, ParseErrorKind::InvalidField('g')));
+        let text = r#"{"rows": [["1r!"]], "columns": [["1r"]]}"#;
+        assert_eq!(read_json(text).unwrap_err(), ParseError::new(1, 14, ParseErrorKind::InvalidColor(String::from("r!"))));
+    }
+}
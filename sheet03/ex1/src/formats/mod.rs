@@ -0,0 +1,65 @@
+//! Import and export of nonograms in formats other than the sheet03 input.
+
+pub mod json;
+pub mod non;
+pub mod pbm;
+pub mod svg;
+pub mod text;
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::grid::Grid;
+use crate::nonogram::Nonogram;
+use crate::parse::{ParseError, ParseErrorKind};
+
+/// Puzzle read from a file together with the solution stored in it, if there is one.
+#[derive(Debug, Clone)]
+pub struct PuzzleFile {
+    pub nonogram: Nonogram,
+    pub solution: Option<Grid>,
+}
+
+/// Character reader keeping track of the line and column of the next character.
+pub(crate) struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self { chars: text.chars().peekable(), line: 1, column: 1 }
+    }
+
+    pub(crate) fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    pub(crate) fn bump(&mut self) -> Option<char> {
+        let next = self.chars.next()?;
+        if next == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(next)
+    }
+
+    /// Line and column of the next character.
+    pub(crate) fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    /// Error located at the next character.
+    pub(crate) fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError::new(self.line, self.column, kind)
+    }
+
+    pub(crate) fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! The `.non` format used by many nonogram collections.
+//!
+//! ```text
+//! title "Example"
+//! width 3
+//! height 2
+//!
+//! rows
+//! 1,1
+//! 1
+//!
+//! columns
+//! 1
+//! 1
+//! 1
+//! goal "101010"
+//! ```
+//!
+//! Keywords other than `width`, `height`, `rows`, `columns` and `goal` are ignored.
+//! Blocks of colour puzzles carry their colour letter after the size, like `1r,2b`, and the goal
+//! uses colour letters for fields of those colours.
+
+use crate::formats::PuzzleFile;
+use crate::grid::Grid;
+use crate::nonogram::{Block, BlockSpec, Color, Field, Nonogram, RowSpec};
+use crate::parse::{check_fits, parse_block_spec, parse_number, tokens, ParseError, ParseErrorKind};
+
+/// Parses comma (or whitespace) separated block sizes.
+fn parse_clue_line(line: &str, line_number: usize) -> Result<BlockSpec, ParseError> {
+    /* Replacing one character by one character keeps the columns of tokens. */
+    parse_block_spec(&line.replace(',', " "), line_number)
+}
+
+/// Checks if the goal may contain given character.
+fn is_goal_pixel(pixel: char) -> bool {
+    pixel == '0' || pixel == '1' || pixel.is_ascii_alphabetic()
+}
+
+/// Reads puzzle in the `.non` format together with its goal, if it is given.
+pub fn read_non(text: &str) -> Result<PuzzleFile, ParseError> {
+    let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line));
+    let (mut width, mut height) = (None, None);
+    let (mut rows, mut columns, mut goal) = (None, None, None);
+
+    while let Some((line_number, line)) = lines.next() {
+        let mut words = tokens(line);
+        let Some((_, keyword)) = words.next() else { continue };
+        let argument = words.next();
+        let dimension = |name| {
+            let (column, token) = argument.ok_or(ParseError::new(line_number, line.chars().count() + 1, ParseErrorKind::MissingSize(name)))?;
+            parse_number::<usize>(line_number, column, token)
+        };
+        match keyword {
+            "width" => width = Some(dimension("width")?),
+            "height" => height = Some(dimension("height")?),
+            "rows" | "columns" => {
+                let (count, length) = match keyword {
+                    "rows" => (height.ok_or(ParseError::new(line_number, 1, ParseErrorKind::MissingKey("height")))?, width),
+                    _ => (width.ok_or(ParseError::new(line_number, 1, ParseErrorKind::MissingKey("width")))?, height),
+                };
+                let mut specs = Vec::with_capacity(count);
+                for (spec_line_number, spec_line) in lines.by_ref().take(count) {
+                    let spec = parse_clue_line(spec_line, spec_line_number)?;
+                    if let Some(length) = length {
+                        check_fits(&spec, length, spec_line_number)?;
+                    }
+                    specs.push(spec);
+                }
+                if specs.len() < count {
+                    let kind = ParseErrorKind::MissingSpecLines { expected: count, found: specs.len() };
+                    return Err(ParseError::new(line_number + specs.len() + 1, 1, kind));
+                }
+                match keyword {
+                    "rows" => rows = Some(RowSpec(specs)),
+                    _ => columns = Some(RowSpec(specs)),
+                }
+            }
+            "goal" => {
+                let (column, token) = argument.ok_or(ParseError::new(line_number, line.chars().count() + 1, ParseErrorKind::MissingKey("goal")))?;
+                goal = Some((line_number, column, token.trim_matches('"').to_string(), token.starts_with('"')));
+            }
+            _ => {}
+        }
+    }
+
+    let last_line = text.lines().count().max(1);
+    let missing = |key| ParseError::new(last_line, 1, ParseErrorKind::MissingKey(key));
+    let width = width.ok_or_else(|| missing("width"))?;
+    let height = height.ok_or_else(|| missing("height"))?;
+    let rows = rows.ok_or_else(|| missing("rows"))?;
+    let columns = columns.ok_or_else(|| missing("columns"))?;
+    let nonogram = Nonogram::from_specs(rows, columns);
+
+    let solution = match goal {
+        None => None,
+        Some((line_number, column, pixels, quoted)) => {
+            let first_pixel = column + usize::from(quoted);
+            if pixels.chars().count() != width * height {
+                let kind = ParseErrorKind::InvalidLineLength { expected: width * height, found: pixels.chars().count() };
+                return Err(ParseError::new(line_number, first_pixel, kind));
+            }
+            let mut solution = nonogram.grid().clone();
+            for (index, pixel) in pixels.chars().enumerate() {
+                let field = match pixel {
+                    '1' => Field::FILLED,
+                    '0' => Field::Empty,
+                    name => Field::Filled(Color(name)),
+                };
+                if !is_goal_pixel(pixel) || solution.plane(field).is_none() {
+                    return Err(ParseError::new(line_number, first_pixel + index, ParseErrorKind::InvalidField(pixel)));
+                }
+                solution.set(index / width.max(1), index % width.max(1), field);
+            }
+            Some(solution)
+        }
+    };
+    Ok(PuzzleFile { nonogram, solution })
+}
+
+/// Writes the puzzle in the `.non` format. Solution, if given, is written as the goal.
+pub fn write_non(nonogram: &Nonogram, solution: Option<&Grid>) -> String {
+    let grid = nonogram.grid();
+    let clues = |spec: &RowSpec| spec.0.iter().map(|spec| match spec.0.is_empty() {
+        true => String::from("0\n"),
+        false => spec.0.iter().map(Block::to_string).collect::<Vec<_>>().join(",") + "\n",
+    }).collect::<String>();
+    let mut non = format!(
+        "width {}\nheight {}\n\nrows\n{}\ncolumns\n{}",
+        grid.width(),
+        grid.height(),
+        clues(nonogram.row_spec()),
+        clues(nonogram.col_spec())
+    );
+    if let Some(solution) = solution {
+        let pixels = solution.rows().flat_map(|row| row.iter()).map(|field| match field {
+            Some(Field::Filled(Color::BLACK)) => '1',
+            Some(Field::Filled(color)) => color.0,
+            _ => '0',
+        }).collect::<String>();
+        non.push_str(&format!("goal \"{}\"\n", pixels));
+    }
+    non
+}
+
+#[cfg(test)]
+mod tests_non {
+    use super::{read_non, write_non};
+    use crate::nonogram::Nonogram;
+    use crate::parse::{ParseError, ParseErrorKind};
+
+    const EXAMPLE: &str = "catalogue \"example\"\ntitle \"Example\"\nwidth 3\nheight 2\n\nrows\n1,1\n1\n\ncolumns\n1\n1\n1\ngoal \"101010\"\n";
+
+    #[test]
+    fn test_read() {
+        let file = read_non(EXAMPLE).unwrap();
+        assert_eq!(file.nonogram.puzzle_text(), "2 3\n1 1\n1\n1\n1\n1\n");
+        assert_eq!(file.solution.unwrap().to_string(), "#.#\n.#.\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut nonogram = "2 3\n3\n0\n1\n1\n1\n".parse::<Nonogram>().unwrap();
+        nonogram.solve().unwrap();
+        let non = write_non(&nonogram, Some(nonogram.grid()));
+        assert_eq!(non, "width 3\nheight 2\n\nrows\n3\n0\n\ncolumns\n1\n1\n1\ngoal \"111000\"\n");
+        let file = read_non(&non).unwrap();
+        assert_eq!(file.nonogram.puzzle_text(), nonogram.puzzle_text());
+        assert_eq!(file.solution.as_ref(), Some(nonogram.grid()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(read_non("rows\n1\n").unwrap_err().kind, ParseErrorKind::MissingKey("height"));
+        assert_eq!(read_non("width 1\nheight 1\nrows\n1\n").unwrap_err().kind, ParseErrorKind::MissingKey("columns"));
+        let kind = ParseErrorKind::BlocksTooLong { required: 3, length: 2 };
+        assert_eq!(read_non("width 2\nheight 1\nrows\n1,1\n").unwrap_err(), ParseError::new(4, 1, kind));
+        let kind = ParseErrorKind::InvalidField('2');
+        let text = "width 2\nheight 1\nrows\n1\ncolumns\n1\n0\ngoal \"12\"\n";
+        assert_eq!(read_non(text).unwrap_err(), ParseError::new(8, 8, kind));
+        let kind = ParseErrorKind::MissingSize("width");
+        assert_eq!(read_non("width\u{a0}\n").unwrap_err(), ParseError::new(1, 7, kind));
+    }
+
+    #[test]
+    fn test_colors() {
+        let text = "width 3\nheight 1\nrows\n1r,1b\ncolumns\n1r\n1b\n0\ngoal \"rb0\"\n";
+        let file = read_non(text).unwrap();
+        assert_eq!(file.nonogram.puzzle_text(), "1 3\n1r 1b\n1r\n1b\n0\n");
+        assert_eq!(file.solution.as_ref().unwrap().to_string(), "rb.\n");
+        assert_eq!(write_non(&file.nonogram, file.solution.as_ref()), "width 3\nheight 1\n\nrows\n1r,1b\n\ncolumns\n1r\n1b\n0\ngoal \"rb0\"\n");
+        let kind = ParseErrorKind::InvalidField('1');
+        assert_eq!(read_non(&text.replace("rb0", "r10")).unwrap_err(), ParseError::new(9, 8, kind));
+    }
+}
@@ -0,0 +1,230 @@
+//! Plain netpbm images: bitmaps (`P1`), where black pixels are filled fields,
+//! greymaps (`P2`), where pixels darker than half of the maximal value are filled,
+//! and pixmaps (`P3`) for solutions of colour puzzles.
+
+use crate::formats::Cursor;
+use crate::grid::Grid;
+use crate::nonogram::{Field, Nonogram};
+use crate::parse::{parse_number, ParseError, ParseErrorKind};
+
+/// Maximal line length recommended by the netpbm specification.
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Skips whitespace and comments running from `#` to the end of line.
+fn skip_separators(cursor: &mut Cursor) {
+    loop {
+        cursor.skip_whitespace();
+        if cursor.peek() != Some('#') {
+            return;
+        }
+        while cursor.peek().is_some_and(|next| next != '\n') {
+            cursor.bump();
+        }
+    }
+}
+
+/// Reads the next whitespace separated token with its position.
+fn token(cursor: &mut Cursor) -> Result<(usize, usize, String), ParseError> {
+    skip_separators(cursor);
+    let (line, column) = cursor.position();
+    let mut token = String::new();
+    while let Some(next) = cursor.peek().filter(|next| !next.is_whitespace() && *next != '#') {
+        token.push(next);
+        cursor.bump();
+    }
+    match token.is_empty() {
+        true => Err(cursor.error(ParseErrorKind::UnexpectedEnd)),
+        false => Ok((line, column, token)),
+    }
+}
+
+/// Reads the format identifier, which has to be the expected one, followed by the width and height of the image.
+fn read_header(cursor: &mut Cursor, expected: &str) -> Result<(usize, usize), ParseError> {
+    let (line, column, magic) = token(cursor)?;
+    if magic != expected {
+        return Err(ParseError::new(line, column, ParseErrorKind::UnsupportedFormat(magic)));
+    }
+    let (line, column, width) = token(cursor)?;
+    let width = parse_number(line, column, &width)?;
+    let (line, column, height) = token(cursor)?;
+    let height = parse_number(line, column, &height)?;
+    Ok((width, height))
+}
+
+/// Checks that only whitespace and comments follow the image.
+fn read_end(cursor: &mut Cursor, grid: Grid) -> Result<Grid, ParseError> {
+    skip_separators(cursor);
+    match cursor.peek() {
+        Some(_) => Err(cursor.error(ParseErrorKind::InvalidSyntax(String::from("unexpected data after the image")))),
+        None => Ok(grid),
+    }
+}
+
+/// Reads a plain PBM image. Pixels may be separated by whitespace, but do not have to be.
+pub fn read_pbm(text: &str) -> Result<Grid, ParseError> {
+    let mut cursor = Cursor::new(text);
+    let (width, height) = read_header(&mut cursor, "P1")?;
+
+    let mut grid = Grid::new(height, width);
+    for row in 0..height {
+        for col in 0..width {
+            skip_separators(&mut cursor);
+            let field = match cursor.peek() {
+                Some('1') => Field::FILLED,
+                Some('0') => Field::Empty,
+                Some(pixel) => return Err(cursor.error(ParseErrorKind::InvalidField(pixel))),
+                None => return Err(cursor.error(ParseErrorKind::UnexpectedEnd)),
+            };
+            cursor.bump();
+            grid.set(row, col, field);
+        }
+    }
+    read_end(&mut cursor, grid)
+}
+
+/// Reads a plain PGM image. Grey values run from black at 0 to white at the maximal value given in the header,
+/// pixels darker than half of it are filled fields.
+pub fn read_pgm(text: &str) -> Result<Grid, ParseError> {
+    let mut cursor = Cursor::new(text);
+    let (width, height) = read_header(&mut cursor, "P2")?;
+    let (line, column, max_value) = token(&mut cursor)?;
+    let max_value: u16 = parse_number(line, column, &max_value)?;
+    if max_value == 0 {
+        return Err(ParseError::new(line, column, ParseErrorKind::InvalidSyntax(String::from("maximal grey value must be positive"))));
+    }
+
+    let mut grid = Grid::new(height, width);
+    for row in 0..height {
+        for col in 0..width {
+            let (line, column, pixel) = token(&mut cursor)?;
+            let value: u16 = parse_number(line, column, &pixel)?;
+            if value > max_value {
+                let kind = ParseErrorKind::InvalidSyntax(format!("grey value {} above the maximum {}", value, max_value));
+                return Err(ParseError::new(line, column, kind));
+            }
+            grid.set(row, col, if 2 * u32::from(value) < u32::from(max_value) { Field::FILLED } else { Field::Empty });
+        }
+    }
+    read_end(&mut cursor, grid)
+}
+
+/// Creates puzzle whose specifications are derived from a plain PBM or PGM image.
+pub fn import_pbm(text: &str) -> Result<Nonogram, ParseError> {
+    let image = match text.trim_start().starts_with("P2") {
+        true => read_pgm(text)?,
+        false => read_pbm(text)?,
+    };
+    Ok(Nonogram::from_image(&image))
+}
+
+/// Appends rows of pixels separated with spaces, wrapping lines that would be too long.
+fn push_pixels(image: &mut String, grid: &Grid, pixel: impl Fn(Option<Field>) -> String) {
+    for row in grid.rows() {
+        let pixels = row.iter().map(&pixel).collect::<Vec<_>>();
+        let mut line_length = 0;
+        for pixel in pixels {
+            if line_length > 0 && line_length + 1 + pixel.len() > MAX_LINE_LENGTH {
+                image.push('\n');
+                line_length = 0;
+            }
+            if line_length > 0 {
+                image.push(' ');
+                line_length += 1;
+            }
+            image.push_str(&pixel);
+            line_length += pixel.len();
+        }
+        image.push('\n');
+    }
+}
+
+/// Writes grid as a plain PBM image. Fields of any colour are black, unknown fields are white.
+pub fn write_pbm(grid: &Grid) -> String {
+    let mut pbm = format!("P1\n{} {}\n", grid.width(), grid.height());
+    push_pixels(&mut pbm, grid, |field| String::from(if matches!(field, Some(Field::Filled(_))) { "1" } else { "0" }));
+    pbm
+}
+
+/// Writes grid as a plain PPM image with fields in their colours. Empty and unknown fields are white.
+pub fn write_ppm(grid: &Grid) -> String {
+    let mut ppm = format!("P3\n{} {}\n255\n", grid.width(), grid.height());
+    push_pixels(&mut ppm, grid, |field| {
+        let [red, green, blue] = match field {
+            Some(Field::Filled(color)) => color.rgb(),
+            _ => [255, 255, 255],
+        };
+        format!("{} {} {}", red, green, blue)
+    });
+    ppm
+}
+
+#[cfg(test)]
+mod tests_pbm {
+    use super::{import_pbm, read_pbm, read_pgm, write_pbm, write_ppm};
+    use crate::formats::text::read_text_grid;
+    use crate::parse::{ParseError, ParseErrorKind};
+
+    #[test]
+    fn test_read_with_comments_and_packed_pixels() {
+        let grid = read_pbm("P1\n# comment\n3 2 # size\n101\n0 1\n0\n").unwrap();
+        assert_eq!(grid.to_string(), "#.#\n.#.\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let grid = read_text_grid("#..#\n.##.\n").unwrap();
+        assert_eq!(write_pbm(&grid), "P1\n4 2\n1 0 0 1\n0 1 1 0\n");
+        assert_eq!(read_pbm(&write_pbm(&grid)).unwrap(), grid);
+    }
+
+    #[test]
+    fn test_long_rows_are_wrapped() {
+        let grid = read_text_grid(&"#".repeat(40)).unwrap();
+        let pbm = write_pbm(&grid);
+        assert!(pbm.lines().all(|line| line.len() <= 70));
+        assert_eq!(read_pbm(&pbm).unwrap(), grid);
+    }
+
+    #[test]
+    fn test_ppm_colors() {
+        let grid = read_text_grid("#r.\n").unwrap();
+        assert_eq!(write_ppm(&grid), "P3\n3 1\n255\n0 0 0 220 40 40 255 255 255\n");
+        let wide = write_ppm(&read_text_grid(&"r".repeat(30)).unwrap());
+        assert!(wide.lines().all(|line| line.len() <= 70));
+        assert_eq!(wide.split_whitespace().count(), 4 + 30 * 3);
+    }
+
+    #[test]
+    fn test_import_derives_specifications() {
+        let nonogram = import_pbm("P1 3 2 110 011").unwrap();
+        assert_eq!(nonogram.puzzle_text(), "2 3\n2\n2\n1\n2\n1\n");
+    }
+
+    #[test]
+    fn test_import_long_runs() {
+        let nonogram = import_pbm(&format!("P1 300 1 {}", "1".repeat(300))).unwrap();
+        assert!(nonogram.puzzle_text().starts_with("1 300\n300\n"));
+    }
+
+    #[test]
+    fn test_read_pgm() {
+        let grid = read_pgm("P2\n# grey\n3 2\n255\n0 127 128\n255 10 200\n").unwrap();
+        assert_eq!(grid.to_string(), "##.\n.#.\n");
+        assert_eq!(import_pbm("P2 2 1 1 0 1").unwrap().puzzle_text(), "1 2\n1\n1\n0\n");
+        let kind = ParseErrorKind::InvalidSyntax(String::from("grey value 16 above the maximum 15"));
+        assert_eq!(read_pgm("P2 1 1 15\n16\n"), Err(ParseError::new(2, 1, kind)));
+        assert_eq!(read_pgm("P2 2 1 15\n1"), Err(ParseError::new(2, 2, ParseErrorKind::UnexpectedEnd)));
+        let kind = ParseErrorKind::UnsupportedFormat(String::from("P1"));
+        assert_eq!(read_pgm("P1 1 1 1"), Err(ParseError::new(1, 1, kind)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let kind = ParseErrorKind::UnsupportedFormat(String::from("P4"));
+        assert_eq!(read_pbm("P4\n1 1\n"), Err(ParseError::new(1, 1, kind)));
+        assert_eq!(read_pbm("P1\n2 1\n1"), Err(ParseError::new(3, 2, ParseErrorKind::UnexpectedEnd)));
+        assert_eq!(read_pbm("P1\n2 1\n12"), Err(ParseError::new(3, 2, ParseErrorKind::InvalidField('2'))));
+        let kind = ParseErrorKind::InvalidNumber(String::from("x"));
+        assert_eq!(read_pbm("P1 x 1"), Err(ParseError::new(1, 4, kind)));
+    }
+}
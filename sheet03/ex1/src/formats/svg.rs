@@ -0,0 +1,131 @@
+//! Printable SVG image of the puzzle with clues next to the grid.
+//! Clues of coloured blocks are drawn white on the background of their colour.
+
+use std::fmt::Write;
+
+use crate::grid::Grid;
+use crate::nonogram::{Block, Color, Field, Nonogram};
+
+/// Side of a single field in pixels.
+const CELL: usize = 20;
+/// Every that many lines the grid line is drawn thicker.
+const GUIDE_EVERY: usize = 5;
+
+fn hex(color: Color) -> String {
+    let [red, green, blue] = color.rgb();
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+/// Draws a single clue in the field with given top left corner.
+fn push_clue(svg: &mut String, x: usize, y: usize, block: &Block) {
+    let (center, baseline) = (x + CELL / 2, y + CELL * 7 / 10);
+    if block.color == Color::BLACK {
+        let _ = writeln!(svg, r#"<text x="{}" y="{}">{}</text>"#, center, baseline, block.size);
+    } else {
+        let _ = writeln!(svg, r#"<rect class="clue" x="{}" y="{}" width="{CELL}" height="{CELL}" fill="{}"/>"#, x, y, hex(block.color));
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" fill="white">{}</text>"#, center, baseline, block.size);
+    }
+}
+
+/// Draws the puzzle with row clues on the left and column clues on top.
+/// When `solution` is given, its filled fields are drawn in the grid in their colours.
+pub fn write_svg(nonogram: &Nonogram, solution: Option<&Grid>) -> String {
+    let (height, width) = (nonogram.grid().height(), nonogram.grid().width());
+    let left = nonogram.row_spec().0.iter().map(|spec| spec.0.len()).max().unwrap_or(0).max(1) * CELL;
+    let top = nonogram.col_spec().0.iter().map(|spec| spec.0.len()).max().unwrap_or(0).max(1) * CELL;
+    let (total_width, total_height) = (left + width * CELL + 1, top + height * CELL + 1);
+
+    let mut svg = String::new();
+    /* Writing to a String never fails. */
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        total_width, total_height
+    );
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    if let Some(solution) = solution {
+        for row in 0..height {
+            for (col, field) in solution.row(row).iter().enumerate() {
+                if let Some(Field::Filled(color)) = field {
+                    let _ = writeln!(
+                        svg,
+                        r#"<rect class="field" x="{}" y="{}" width="{CELL}" height="{CELL}" fill="{}"/>"#,
+                        left + col * CELL,
+                        top + row * CELL,
+                        hex(color)
+                    );
+                }
+            }
+        }
+    }
+
+    let _ = writeln!(svg, r#"<g font-family="sans-serif" font-size="{}" text-anchor="middle">"#, CELL * 3 / 5);
+    for (row, spec) in nonogram.row_spec().0.iter().enumerate() {
+        let start = left - spec.0.len() * CELL;
+        for (index, block) in spec.0.iter().enumerate() {
+            push_clue(&mut svg, start + index * CELL, top + row * CELL, block);
+        }
+    }
+    for (col, spec) in nonogram.col_spec().0.iter().enumerate() {
+        let start = top - spec.0.len() * CELL;
+        for (index, block) in spec.0.iter().enumerate() {
+            push_clue(&mut svg, left + col * CELL, start + index * CELL, block);
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+
+    /* Lines start half a pixel off so that one pixel wide strokes stay sharp. */
+    let stroke = |index: usize, count: usize| match index.is_multiple_of(GUIDE_EVERY) || index == count {
+        true => r#"stroke="black" stroke-width="2""#,
+        false => r#"stroke="gray" stroke-width="1""#,
+    };
+    for row in 0..=height {
+        let y = top + row * CELL;
+        let _ = writeln!(svg, r#"<line x1="0" y1="{y}.5" x2="{}" y2="{y}.5" {}/>"#, total_width, stroke(row, height));
+    }
+    for col in 0..=width {
+        let x = left + col * CELL;
+        let _ = writeln!(svg, r#"<line x1="{x}.5" y1="0" x2="{x}.5" y2="{}" {}/>"#, total_height, stroke(col, width));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests_svg {
+    use super::write_svg;
+    use crate::nonogram::Nonogram;
+
+    fn puzzle() -> Nonogram {
+        "2 3\n1 1\n1\n1\n1\n1\n".parse::<Nonogram>().unwrap()
+    }
+
+    #[test]
+    fn test_clues_are_drawn() {
+        let svg = write_svg(&puzzle(), None);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<text ").count(), 6);
+        assert_eq!(svg.matches(r#"class="field""#).count(), 0);
+    }
+
+    #[test]
+    fn test_solution_is_drawn() {
+        let mut nonogram = puzzle();
+        nonogram.solve().unwrap();
+        let svg = write_svg(&nonogram, Some(nonogram.grid()));
+        assert_eq!(svg.matches(r#"class="field""#).count(), 3);
+        assert_eq!(svg.matches(r##"fill="#000000""##).count(), 3);
+    }
+
+    #[test]
+    fn test_colors_are_drawn() {
+        let mut nonogram = "1 2\n1r 1b\n1r\n1b\n".parse::<Nonogram>().unwrap();
+        nonogram.solve().unwrap();
+        let svg = write_svg(&nonogram, Some(nonogram.grid()));
+        assert_eq!(svg.matches(r#"class="clue""#).count(), 4);
+        assert_eq!(svg.matches(r##"fill="#dc2828""##).count(), 3);
+        assert_eq!(svg.matches(r##"fill="#2850d2""##).count(), 3);
+    }
+}
@@ -0,0 +1,93 @@
+//! Grid written as rows of `#` (filled), `.` (empty) and `?` (unknown) fields.
+//! Fields of colour puzzles are written as names of their colours, like `r` or `b`.
+
+use crate::grid::Grid;
+use crate::nonogram::{Color, Field};
+use crate::parse::{ParseError, ParseErrorKind};
+
+/// Value of a field written as given character, `None` for an unknown field.
+pub(crate) fn parse_field(character: char) -> Result<Option<Field>, ParseErrorKind> {
+    match character {
+        '#' => Ok(Some(Field::FILLED)),
+        '.' => Ok(Some(Field::Empty)),
+        '?' => Ok(None),
+        name if name.is_ascii_alphabetic() => Ok(Some(Field::Filled(Color(name)))),
+        _ => Err(ParseErrorKind::InvalidField(character)),
+    }
+}
+
+/// Reads a grid of `#`, `.`, `?` and colour letters. Blank lines and trailing whitespace are ignored.
+/// Grid has exactly the colours that appear in the text, or just black if there are none.
+pub fn read_text_grid(text: &str) -> Result<Grid, ParseError> {
+    let mut rows = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = Vec::with_capacity(line.len());
+        for (column, character) in line.chars().enumerate() {
+            fields.push(parse_field(character).map_err(|kind| ParseError::new(index + 1, column + 1, kind))?);
+        }
+        if let Some(expected) = rows.first().map(Vec::len) {
+            if fields.len() != expected {
+                let kind = ParseErrorKind::InvalidLineLength { expected, found: fields.len() };
+                return Err(ParseError::new(index + 1, 1, kind));
+            }
+        }
+        rows.push(fields);
+    }
+
+    let colors = Color::palette(rows.iter().flatten().filter_map(|field| match field {
+        Some(Field::Filled(color)) => Some(*color),
+        _ => None,
+    }));
+    let mut grid = Grid::with_colors(rows.len(), rows.first().map_or(0, Vec::len), &colors);
+    for (row, fields) in rows.iter().enumerate() {
+        for (col, field) in fields.iter().enumerate() {
+            if let Some(field) = *field {
+                grid.set(row, col, field);
+            }
+        }
+    }
+    Ok(grid)
+}
+
+/// Writes grid one row per line, the same way as the sheet03 output.
+pub fn write_text_grid(grid: &Grid) -> String {
+    grid.to_string()
+}
+
+#[cfg(test)]
+mod tests_text {
+    use super::{read_text_grid, write_text_grid};
+    use crate::nonogram::{Color, Field};
+    use crate::parse::{ParseError, ParseErrorKind};
+
+    #[test]
+    fn test_round_trip() {
+        let text = "#.?\n..#\n";
+        assert_eq!(write_text_grid(&read_text_grid(text).unwrap()), text);
+    }
+
+    #[test]
+    fn test_blank_lines_are_skipped() {
+        let grid = read_text_grid("\n#.\n\n.#  \n\n").unwrap();
+        assert_eq!(grid.to_string(), "#.\n.#\n");
+    }
+
+    #[test]
+    fn test_colors() {
+        let grid = read_text_grid("rb?\n.#r\n").unwrap();
+        assert_eq!(grid.colors(), [Color::BLACK, Color('b'), Color('r')]);
+        assert_eq!(grid.get(0, 1), Some(Field::Filled(Color('b'))));
+        assert_eq!(write_text_grid(&grid), "rb?\n.#r\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(read_text_grid("#.\n#!\n"), Err(ParseError::new(2, 2, ParseErrorKind::InvalidField('!'))));
+        let kind = ParseErrorKind::InvalidLineLength { expected: 2, found: 3 };
+        assert_eq!(read_text_grid("#.\n#..\n"), Err(ParseError::new(2, 1, kind)));
+    }
+}
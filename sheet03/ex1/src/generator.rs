@@ -0,0 +1,173 @@
+use algorithms::rng::XorShift64;
+
+use crate::grid::Grid;
+use crate::nonogram::{Field, Nonogram};
+use crate::solver::{Solver, Uniqueness};
+
+/// Weakest technique that solves a puzzle, ordered from the easiest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    /// Solved by running line solver over rows and columns alone.
+    LineSolving,
+    /// Solved once probing of single fields is added.
+    Probing,
+    /// Requires guessing values and backtracking.
+    Search,
+}
+
+/// Rates the puzzle by the weakest technique that solves it, `None` if it has no solution.
+pub fn grade(nonogram: &Nonogram) -> Option<Difficulty> {
+    let solver = Solver::new(nonogram.row_spec(), nonogram.col_spec());
+    let mut grid = nonogram.grid().clone();
+    solver.propagate_all(&mut grid).ok()?;
+    if grid.is_complete() {
+        return Some(Difficulty::LineSolving);
+    }
+    solver.probe(&mut grid).ok()?;
+    if grid.is_complete() {
+        return Some(Difficulty::Probing);
+    }
+    solver.solve(grid).ok().map(|_| Difficulty::Search)
+}
+
+/// Puzzle with exactly one solution.
+#[derive(Debug, Clone)]
+pub struct GeneratedPuzzle {
+    pub nonogram: Nonogram,
+    pub solution: Grid,
+    pub difficulty: Difficulty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationError {
+    /// Image was still ambiguous after the maximal number of perturbations.
+    NotUnique { perturbations: usize },
+}
+
+/// Generates puzzles with unique solutions from target or random images.
+///
+/// Clues are derived from the image, while they describe more than one image
+/// a random field where two solutions differ gets the other solution's value in the image.
+pub struct Generator {
+    rng: XorShift64,
+    max_perturbations: usize,
+}
+
+impl Generator {
+    pub const DEFAULT_MAX_PERTURBATIONS: usize = 256;
+
+    pub fn new(seed: u64) -> Self {
+        Self { rng: XorShift64::new(seed), max_perturbations: Self::DEFAULT_MAX_PERTURBATIONS }
+    }
+
+    pub fn max_perturbations(mut self, max_perturbations: usize) -> Self {
+        self.max_perturbations = max_perturbations;
+        self
+    }
+
+    /// Random image where each field is filled with given probability.
+    pub fn random_image(&mut self, height: usize, width: usize, density: f64) -> Grid {
+        let mut image = Grid::new(height, width);
+        for row in 0..height {
+            for col in 0..width {
+                let field = if self.rng.chance(density) { Field::FILLED } else { Field::Empty };
+                image.set(row, col, field);
+            }
+        }
+        image
+    }
+
+    /// Generates puzzle from a random image with given density.
+    pub fn random_puzzle(&mut self, height: usize, width: usize, density: f64) -> Result<GeneratedPuzzle, GenerationError> {
+        let image = self.random_image(height, width, density);
+        self.puzzle_from_image(image)
+    }
+
+    /// Generates puzzle from a complete target image, perturbing it until the solution is unique.
+    pub fn puzzle_from_image(&mut self, mut image: Grid) -> Result<GeneratedPuzzle, GenerationError> {
+        assert!(image.is_complete(), "Target image must be complete.");
+        for _ in 0..=self.max_perturbations {
+            let nonogram = Nonogram::from_image(&image);
+            match nonogram.uniqueness() {
+                Uniqueness::Unique(_) => {
+                    let difficulty = grade(&nonogram).expect("Unique puzzle has a solution.");
+                    return Ok(GeneratedPuzzle { nonogram, solution: image, difficulty });
+                }
+                Uniqueness::Ambiguous { first, second, differences } => {
+                    let (row, col) = differences[self.rng.below(differences.len())];
+                    let field = image.get(row, col).expect("Target image is complete.");
+                    let other = [first.get(row, col), second.get(row, col)].into_iter().flatten().find(|&other| other != field);
+                    image.set(row, col, other.expect("Solutions differ at this field."));
+                }
+                Uniqueness::Unsolvable => unreachable!("Image solves the puzzle derived from it."),
+            }
+        }
+        Err(GenerationError::NotUnique { perturbations: self.max_perturbations })
+    }
+}
+
+#[cfg(test)]
+mod tests_generator {
+    use super::{grade, Difficulty, Generator};
+    use crate::formats::text::read_text_grid;
+    use crate::nonogram::Nonogram;
+    use crate::solver::Uniqueness;
+
+    #[test]
+    fn test_unique_image_is_kept() {
+        let mut nonogram = "5 5\n5\n1 1 1\n3\n2 2\n5\n2 2\n1 3\n3 1\n1 3\n2 2\n".parse::<Nonogram>().unwrap();
+        nonogram.solve().unwrap();
+        let puzzle = Generator::new(7).puzzle_from_image(nonogram.grid().clone()).unwrap();
+        assert_eq!(puzzle.solution, *nonogram.grid());
+        assert_eq!(puzzle.nonogram.row_spec(), nonogram.row_spec());
+        assert_eq!(puzzle.nonogram.col_spec(), nonogram.col_spec());
+        assert_eq!(puzzle.difficulty, Difficulty::LineSolving);
+    }
+
+    #[test]
+    fn test_random_puzzles_are_unique() {
+        let mut generator = Generator::new(2022);
+        for _ in 0..5 {
+            let puzzle = generator.random_puzzle(10, 12, 0.5).unwrap();
+            assert_eq!(puzzle.nonogram.uniqueness(), Uniqueness::Unique(puzzle.solution.clone()));
+            assert_eq!(grade(&puzzle.nonogram), Some(puzzle.difficulty));
+        }
+    }
+
+    #[test]
+    fn test_generation_is_reproducible() {
+        let first = Generator::new(5).random_puzzle(8, 8, 0.4).unwrap();
+        let second = Generator::new(5).random_puzzle(8, 8, 0.4).unwrap();
+        assert_eq!(first.nonogram.puzzle_text(), second.nonogram.puzzle_text());
+    }
+
+    #[test]
+    fn test_puzzle_text_round_trip() {
+        let puzzle = Generator::new(11).random_puzzle(6, 9, 0.5).unwrap();
+        let mut parsed = puzzle.nonogram.puzzle_text().parse::<Nonogram>().unwrap();
+        parsed.solve().unwrap();
+        assert_eq!(*parsed.grid(), puzzle.solution);
+    }
+
+    #[test]
+    fn test_empty_lines_and_long_runs_round_trip() {
+        let image = read_text_grid(&format!("{}\n{}\n", "#".repeat(300), ".".repeat(300))).unwrap();
+        let nonogram = Nonogram::from_image(&image);
+        assert!(nonogram.puzzle_text().starts_with("2 300\n300\n0\n"));
+        let parsed = nonogram.puzzle_text().parse::<Nonogram>().unwrap();
+        assert_eq!(parsed.row_spec(), nonogram.row_spec());
+        assert_eq!(parsed.col_spec(), nonogram.col_spec());
+    }
+
+    #[test]
+    fn test_grade_ambiguous_requires_search() {
+        let nonogram = "2 2\n1\n1\n1\n1\n".parse::<Nonogram>().unwrap();
+        assert_eq!(grade(&nonogram), Some(Difficulty::Search));
+    }
+
+    #[test]
+    fn test_grade_unsolvable() {
+        let nonogram = "2 2\n2\n0\n1\n0\n".parse::<Nonogram>().unwrap();
+        assert_eq!(grade(&nonogram), None);
+    }
+}
@@ -0,0 +1,583 @@
+use std::fmt::{Display, Formatter};
+
+use crate::nonogram::{Color, Field};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Number of words needed to store given number of bits.
+const fn word_count(len: usize) -> usize {
+    len.div_ceil(WORD_BITS)
+}
+
+/// Mask of bits `[from, to)` of a single word, `to` may be equal to `WORD_BITS`.
+const fn word_mask(from: usize, to: usize) -> u64 {
+    let upper = if to == WORD_BITS { u64::MAX } else { (1 << to) - 1 };
+    upper & !((1 << from) - 1)
+}
+
+// region Bits
+
+/// Borrowed sequence of bits packed into words, least significant bit first.
+#[derive(Debug, Clone, Copy)]
+pub struct BitSlice<'a> {
+    len: usize,
+    words: &'a [u64],
+}
+
+impl<'a> BitSlice<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn words(&self) -> &'a [u64] {
+        self.words
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1
+    }
+
+    /// Checks if all bits in range `[from, to)` are set.
+    pub fn all_set(&self, from: usize, to: usize) -> bool {
+        if from >= to {
+            return true;
+        }
+        let (first, last) = (from / WORD_BITS, (to - 1) / WORD_BITS);
+        (first..=last).all(|word| {
+            let lower = if word == first { from % WORD_BITS } else { 0 };
+            let upper = if word == last { (to - 1) % WORD_BITS + 1 } else { WORD_BITS };
+            let mask = word_mask(lower, upper);
+            self.words[word] & mask == mask
+        })
+    }
+
+    /// Index of the first bit with given value at or after `from`, `len` if there is none.
+    fn find(&self, from: usize, value: bool) -> usize {
+        let mut word = from / WORD_BITS;
+        let mut bits = if from < self.len { word_mask(from % WORD_BITS, WORD_BITS) } else { 0 };
+        while word < self.words.len() {
+            let candidates = if value { self.words[word] } else { !self.words[word] } & bits;
+            if candidates != 0 {
+                return (word * WORD_BITS + candidates.trailing_zeros() as usize).min(self.len);
+            }
+            word += 1;
+            bits = u64::MAX;
+        }
+        self.len
+    }
+
+    /// Iterates over indices of set bits.
+    pub fn ones(self) -> impl Iterator<Item=usize> + 'a {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    index * WORD_BITS + bit
+                })
+            })
+        })
+    }
+
+    /// Iterates over maximal runs of set bits as `(start, length)` pairs.
+    pub fn runs(self) -> impl Iterator<Item=(usize, usize)> + 'a {
+        let mut position = 0;
+        std::iter::from_fn(move || {
+            let start = self.find(position, true);
+            (start < self.len).then(|| {
+                position = self.find(start, false);
+                (start, position - start)
+            })
+        })
+    }
+
+    pub fn to_owned(self) -> BitLine {
+        BitLine { len: self.len, words: self.words.to_vec() }
+    }
+}
+
+/// Owned sequence of bits packed into words.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitLine {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitLine {
+    /// Creates line of `len` unset bits.
+    pub fn zeros(len: usize) -> Self {
+        Self { len, words: vec![0; word_count(len)] }
+    }
+
+    /// Creates line of `len` set bits.
+    pub fn ones(len: usize) -> Self {
+        let mut line = Self::zeros(len);
+        line.set_range(0, len);
+        line
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_slice(&self) -> BitSlice<'_> {
+        BitSlice { len: self.len, words: &self.words }
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.as_slice().get(index)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        let bit = 1 << (index % WORD_BITS);
+        if value {
+            self.words[index / WORD_BITS] |= bit;
+        } else {
+            self.words[index / WORD_BITS] &= !bit;
+        }
+    }
+
+    /// Sets all bits in range `[from, to)`.
+    pub fn set_range(&mut self, from: usize, to: usize) {
+        for index in from..to {
+            self.set(index, true);
+        }
+    }
+
+    /// Bits set in self or in `other`.
+    pub fn union(&self, other: BitSlice) -> BitLine {
+        let words = self.words.iter().zip(other.words).map(|(&word, &other)| word | other).collect();
+        BitLine { len: self.len, words }
+    }
+
+    /// Bits set in self but not in `other`.
+    pub fn difference(&self, other: BitSlice) -> BitLine {
+        let words = self.words.iter().zip(other.words).map(|(&word, &other)| word & !other).collect();
+        BitLine { len: self.len, words }
+    }
+}
+
+impl FromIterator<bool> for BitLine {
+    fn from_iter<T: IntoIterator<Item=bool>>(iter: T) -> Self {
+        let bits = iter.into_iter().collect::<Vec<_>>();
+        let mut line = BitLine::zeros(bits.len());
+        for (index, bit) in bits.into_iter().enumerate() {
+            line.set(index, bit);
+        }
+        line
+    }
+}
+
+/// Matrix of bits stored line by line, every line padded to whole words.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BitMatrix {
+    line_len: usize,
+    words_per_line: usize,
+    words: Vec<u64>,
+}
+
+impl BitMatrix {
+    fn ones(line_count: usize, line_len: usize) -> Self {
+        let line = BitLine::ones(line_len);
+        let words = (0..line_count).flat_map(|_| line.words.iter().copied()).collect();
+        Self { line_len, words_per_line: word_count(line_len), words }
+    }
+
+    fn line(&self, index: usize) -> BitSlice<'_> {
+        let start = index * self.words_per_line;
+        BitSlice { len: self.line_len, words: &self.words[start..start + self.words_per_line] }
+    }
+
+    fn put(&mut self, line: usize, index: usize, value: bool) {
+        let word = &mut self.words[line * self.words_per_line + index / WORD_BITS];
+        let bit = 1 << (index % WORD_BITS);
+        if value { *word |= bit } else { *word &= !bit }
+    }
+}
+
+// endregion
+
+
+// region Grid
+
+/// Single row or column of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Row(usize),
+    Col(usize),
+}
+
+impl Line {
+    /// Line crossing self at given index.
+    pub fn crossing(self, index: usize) -> Line {
+        match self {
+            Line::Row(_) => Line::Col(index),
+            Line::Col(_) => Line::Row(index),
+        }
+    }
+
+    /// Coordinates `(row, col)` of the field at given index of the line.
+    pub fn field(self, index: usize) -> (usize, usize) {
+        match self {
+            Line::Row(row) => (row, index),
+            Line::Col(col) => (index, col),
+        }
+    }
+}
+
+/// Values each field of a line can still take, one bit line per plane of the grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineDomain {
+    pub planes: Vec<BitLine>,
+}
+
+/// Plane of fields that can be empty, planes of colours follow it.
+pub const EMPTY_PLANE: usize = 0;
+
+/// Partially known nonogram image.
+///
+/// Every field keeps the set of values it can still take as one bit in each plane: the first plane
+/// marks fields that can be empty, the next ones fields that can be filled with each colour of the grid.
+/// Field is known once exactly one of its bits is set; black-and-white grids have just two planes.
+/// Planes are stored both row by row and column by column, so that views of rows and columns
+/// borrow words directly. All updates go through the grid which keeps both layouts in sync.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid {
+    height: usize,
+    width: usize,
+    /// Colours of filled fields, colour `colors[i]` is kept in plane `i + 1`.
+    colors: Vec<Color>,
+    /// Planes stored row by row.
+    by_rows: Vec<BitMatrix>,
+    /// Planes stored column by column.
+    by_cols: Vec<BitMatrix>,
+}
+
+impl Grid {
+    /// Creates black-and-white grid of given size with all fields unknown.
+    pub fn new(height: usize, width: usize) -> Self {
+        Self::with_colors(height, width, &[Color::BLACK])
+    }
+
+    /// Creates grid of given size with all fields unknown, filled fields can take any of given colours.
+    pub fn with_colors(height: usize, width: usize, colors: &[Color]) -> Self {
+        let planes = colors.len() + 1;
+        let by_rows = vec![BitMatrix::ones(height, width); planes];
+        let by_cols = vec![BitMatrix::ones(width, height); planes];
+        Self { height, width, colors: colors.to_vec(), by_rows, by_cols }
+    }
+
+    /// Creates fully known grid from rows of fields, with colours that appear in the image.
+    pub fn from_image(image: &[Vec<Field>]) -> Self {
+        let colors = Color::palette(image.iter().flatten().filter_map(|field| match field {
+            Field::Filled(color) => Some(*color),
+            Field::Empty => None,
+        }));
+        let mut grid = Self::with_colors(image.len(), image.first().map_or(0, Vec::len), &colors);
+        for (row, fields) in image.iter().enumerate() {
+            for (col, &field) in fields.iter().enumerate() {
+                grid.set(row, col, field);
+            }
+        }
+        grid
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    /// All values a field can take: empty followed by filled with each colour.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item=Field> + '_ {
+        std::iter::once(Field::Empty).chain(self.colors.iter().map(|&color| Field::Filled(color)))
+    }
+
+    /// Plane keeping given value, `None` for colours the grid does not have.
+    pub fn plane(&self, field: Field) -> Option<usize> {
+        match field {
+            Field::Empty => Some(EMPTY_PLANE),
+            Field::Filled(color) => self.colors.iter().position(|&other| other == color).map(|index| index + 1),
+        }
+    }
+
+    fn value(&self, plane: usize) -> Field {
+        match plane {
+            EMPTY_PLANE => Field::Empty,
+            _ => Field::Filled(self.colors[plane - 1]),
+        }
+    }
+
+    /// Checks if field can still take given value.
+    pub fn can_be(&self, row: usize, col: usize, field: Field) -> bool {
+        self.plane(field).is_some_and(|plane| self.by_rows[plane].line(row).get(col))
+    }
+
+    /// Value of the field, `None` if it is not known yet.
+    pub fn get(&self, row: usize, col: usize) -> Option<Field> {
+        let mut planes = (0..self.by_rows.len()).filter(|&plane| self.by_rows[plane].line(row).get(col));
+        match (planes.next(), planes.next()) {
+            (Some(plane), None) => Some(self.value(plane)),
+            _ => None,
+        }
+    }
+
+    /// Removes given value from the possible values of the field.
+    pub fn exclude(&mut self, row: usize, col: usize, field: Field) {
+        if let Some(plane) = self.plane(field) {
+            self.put(row, col, plane, false);
+        }
+    }
+
+    fn put(&mut self, row: usize, col: usize, plane: usize, possible: bool) {
+        self.by_rows[plane].put(row, col, possible);
+        self.by_cols[plane].put(col, row, possible);
+    }
+
+    /// Fixes value of the field, overriding whatever was known about it.
+    /// Panics if the field is filled with a colour the grid does not have.
+    pub fn set(&mut self, row: usize, col: usize, field: Field) {
+        let plane = self.plane(field).unwrap_or_else(|| panic!("grid has no value {:?}", field));
+        for other in 0..self.by_rows.len() {
+            self.put(row, col, other, other == plane);
+        }
+    }
+
+    pub fn line(&self, line: Line) -> LineView<'_> {
+        LineView { grid: self, line }
+    }
+
+    pub fn line_mut(&mut self, line: Line) -> LineViewMut<'_> {
+        LineViewMut { grid: self, line }
+    }
+
+    pub fn row(&self, row: usize) -> LineView<'_> {
+        self.line(Line::Row(row))
+    }
+
+    pub fn col(&self, col: usize) -> LineView<'_> {
+        self.line(Line::Col(col))
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> LineViewMut<'_> {
+        self.line_mut(Line::Row(row))
+    }
+
+    pub fn col_mut(&mut self, col: usize) -> LineViewMut<'_> {
+        self.line_mut(Line::Col(col))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item=LineView<'_>> {
+        (0..self.height).map(|row| self.row(row))
+    }
+
+    pub fn cols(&self) -> impl Iterator<Item=LineView<'_>> {
+        (0..self.width).map(|col| self.col(col))
+    }
+
+    /// Coordinates of the first field that is not known yet.
+    pub fn first_unknown(&self) -> Option<(usize, usize)> {
+        self.rows().enumerate().find_map(|(row, line)| line.unknown().map(|col| (row, col)))
+    }
+
+    /// Checks if value of every field is known.
+    pub fn is_complete(&self) -> bool {
+        self.first_unknown().is_none()
+    }
+}
+
+/// Writes filled fields as names of their colours (`#` for black), empty as `.` and unknown as `?`.
+impl Display for Grid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.rows() {
+            let line = row.iter().map(|field| match field {
+                Some(Field::Filled(color)) => color.0,
+                Some(Field::Empty) => '.',
+                None => '?',
+            }).collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Zero-copy view of a single row or column of the grid.
+#[derive(Clone, Copy)]
+pub struct LineView<'a> {
+    grid: &'a Grid,
+    line: Line,
+}
+
+impl<'a> LineView<'a> {
+    pub fn len(&self) -> usize {
+        match self.line {
+            Line::Row(_) => self.grid.width,
+            Line::Col(_) => self.grid.height,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn plane(&self, plane: usize) -> BitSlice<'a> {
+        match self.line {
+            Line::Row(row) => self.grid.by_rows[plane].line(row),
+            Line::Col(col) => self.grid.by_cols[plane].line(col),
+        }
+    }
+
+    /// Bits of fields that can still take given value. Panics if the grid has no such colour.
+    pub fn possible(&self, field: Field) -> BitSlice<'a> {
+        self.plane(self.grid.plane(field).unwrap_or_else(|| panic!("grid has no value {:?}", field)))
+    }
+
+    /// Bits of all planes of the line, in the order of grid planes.
+    pub fn planes(&self) -> Vec<BitSlice<'a>> {
+        (0..self.grid.by_rows.len()).map(|plane| self.plane(plane)).collect()
+    }
+
+    pub fn domain(&self) -> LineDomain {
+        LineDomain { planes: self.planes().into_iter().map(BitSlice::to_owned).collect() }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Field> {
+        let (row, col) = self.line.field(index);
+        self.grid.get(row, col)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=Option<Field>> + 'a {
+        let view = *self;
+        (0..self.len()).map(move |index| view.get(index))
+    }
+
+    /// Index of the first field that is not known yet.
+    pub fn unknown(&self) -> Option<usize> {
+        let planes = self.planes();
+        (0..word_count(self.len())).find_map(|index| {
+            /* Bits set in at least two planes. */
+            let (mut seen, mut several) = (0, 0);
+            for plane in &planes {
+                let word = plane.words()[index];
+                several |= seen & word;
+                seen |= word;
+            }
+            (several != 0).then(|| index * WORD_BITS + several.trailing_zeros() as usize)
+        })
+    }
+}
+
+/// Mutable view of a single row or column of the grid.
+pub struct LineViewMut<'a> {
+    grid: &'a mut Grid,
+    line: Line,
+}
+
+impl LineViewMut<'_> {
+    pub fn get(&self, index: usize) -> Option<Field> {
+        self.grid.line(self.line).get(index)
+    }
+
+    pub fn set(&mut self, index: usize, field: Field) {
+        let (row, col) = self.line.field(index);
+        self.grid.set(row, col, field);
+    }
+
+    /// Narrows possible values of the line's fields down to given domain.
+    /// Returns bits of fields that have lost any value.
+    pub fn restrict(&mut self, domain: &LineDomain) -> BitLine {
+        let view = self.grid.line(self.line);
+        let mut changed = BitLine::zeros(view.len());
+        let lost = view.planes().into_iter().zip(&domain.planes)
+            .map(|(current, allowed)| current.to_owned().difference(allowed.as_slice()))
+            .collect::<Vec<_>>();
+        for (plane, lost) in lost.iter().enumerate() {
+            for index in lost.as_slice().ones() {
+                let (row, col) = self.line.field(index);
+                self.grid.put(row, col, plane, false);
+            }
+            changed = changed.union(lost.as_slice());
+        }
+        changed
+    }
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests_grid {
+    use super::{BitLine, Grid};
+    use crate::nonogram::{Color, Field};
+
+    #[test]
+    fn test_all_set_across_words() {
+        let mut line = BitLine::zeros(150);
+        line.set_range(60, 140);
+        assert!(line.as_slice().all_set(60, 140));
+        assert!(line.as_slice().all_set(64, 128));
+        assert!(!line.as_slice().all_set(59, 70));
+        assert!(!line.as_slice().all_set(130, 141));
+    }
+
+    #[test]
+    fn test_runs() {
+        let line = [0, 1, 1, 0, 1, 0, 0, 1, 1, 1].iter().map(|&bit| bit == 1).collect::<BitLine>();
+        assert_eq!(line.as_slice().runs().collect::<Vec<_>>(), [(1, 2), (4, 1), (7, 3)]);
+    }
+
+    #[test]
+    fn test_row_and_col_views_agree() {
+        let mut grid = Grid::new(3, 70);
+        grid.col_mut(65).set(2, Field::FILLED);
+        grid.row_mut(1).set(65, Field::Empty);
+        assert_eq!(grid.row(2).get(65), Some(Field::FILLED));
+        assert_eq!(grid.col(65).get(1), Some(Field::Empty));
+        assert_eq!(grid.col(65).get(0), None);
+        assert_eq!(grid.row(2).possible(Field::FILLED).ones().count(), 70);
+        assert_eq!(grid.col(65).possible(Field::FILLED).ones().collect::<Vec<_>>(), [0, 2]);
+    }
+
+    #[test]
+    fn test_color_planes() {
+        let red = Field::Filled(Color('r'));
+        let mut grid = Grid::with_colors(2, 3, &[Color::BLACK, Color('r')]);
+        assert_eq!(grid.values().collect::<Vec<_>>(), [Field::Empty, Field::FILLED, red]);
+        grid.exclude(0, 1, Field::FILLED);
+        assert_eq!(grid.get(0, 1), None);
+        assert_eq!(grid.row(0).unknown(), Some(0));
+        grid.exclude(0, 1, Field::Empty);
+        assert_eq!(grid.get(0, 1), Some(red));
+        grid.set(0, 0, red);
+        grid.set(0, 2, Field::Empty);
+        assert_eq!(grid.row(0).unknown(), None);
+        assert_eq!(grid.to_string(), "rr.\n???\n");
+        assert!(!grid.can_be(1, 1, Field::Filled(Color('b'))));
+    }
+
+    #[test]
+    fn test_set_overrides_fixed_value() {
+        let mut grid = Grid::new(2, 2);
+        grid.set(0, 0, Field::FILLED);
+        grid.set(0, 0, Field::Empty);
+        assert_eq!(grid.get(0, 0), Some(Field::Empty));
+        assert!(!grid.can_be(0, 0, Field::FILLED));
+        grid.set(0, 1, Field::Empty);
+        assert_eq!(grid.row(0).unknown(), None);
+        assert_eq!(grid.first_unknown(), Some((1, 0)));
+    }
+}
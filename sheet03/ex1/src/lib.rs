@@ -0,0 +1,9 @@
+pub mod constraint;
+pub mod encoding;
+pub mod formats;
+pub mod generator;
+pub mod grid;
+pub mod line_solver;
+pub mod nonogram;
+pub mod parse;
+pub mod solver;
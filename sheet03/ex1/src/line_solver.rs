@@ -0,0 +1,210 @@
+use crate::grid::{BitLine, BitSlice, LineDomain, EMPTY_PLANE};
+use crate::nonogram::{BlockSpec, Color};
+
+/// Returned when no placement of the blocks agrees with the already known fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contradiction;
+
+/// Deduces every field that has the same value in all placements of blocks consistent with the line.
+///
+/// Line is given as bits of fields that can still take each value, one slice per plane of the grid
+/// with given colours: the first slice for empty fields, then one slice for each colour.
+/// Runs two dynamic programs over block positions, `O(length * block count)` each:
+/// - `prefix[j][i]` - first `j` blocks can be placed in fields `[0, i)`,
+/// - `suffix[j][i]` - blocks from `j` onwards can be placed in fields `[i, length)`.
+///
+/// Consecutive blocks of the same colour need an empty field between them, blocks of different colours
+/// may touch. A field can be empty if some `j` splits the blocks around it, and it can have a colour
+/// if some placement of a block of that colour covering it is supported by both tables.
+/// Checking whether a block or a gap fits in a range is a masked comparison of whole words.
+pub fn solve_line(spec: &BlockSpec, colors: &[Color], planes: &[BitSlice]) -> Result<LineDomain, Contradiction> {
+    /* Blocks as (size, plane), a block of colour the line cannot have fits nowhere. */
+    let blocks = spec.0.iter()
+        .map(|block| colors.iter().position(|&color| color == block.color).map(|index| (block.size, index + 1)))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(Contradiction)?;
+    let can_empty = planes[EMPTY_PLANE];
+    let length = can_empty.len();
+    let block_count = blocks.len();
+
+    let no_filled = |from: usize, to: usize| can_empty.all_set(from, to);
+    let fits = |plane: usize, from: usize, to: usize| planes[plane].all_set(from, to);
+    let may_be_empty = |index: usize| can_empty.get(index);
+    /* Whether blocks `j - 1` and `j` must be separated by an empty field. */
+    let needs_gap = |j: usize| j > 0 && j < block_count && blocks[j - 1].1 == blocks[j].1;
+
+    let mut prefix = vec![vec![false; length + 1]; block_count + 1];
+    for (i, fits) in prefix[0].iter_mut().enumerate() {
+        *fits = no_filled(0, i);
+    }
+    for j in 1..=block_count {
+        let (size, plane) = blocks[j - 1];
+        for i in 1..=length {
+            let gap = prefix[j][i - 1] && may_be_empty(i - 1);
+            let block = i >= size && fits(plane, i - size, i) && match (i - size, needs_gap(j - 1)) {
+                (start, false) => prefix[j - 1][start],
+                (0, true) => false,
+                (start, true) => may_be_empty(start - 1) && prefix[j - 1][start - 1],
+            };
+            prefix[j][i] = gap || block;
+        }
+    }
+    if !prefix[block_count][length] {
+        return Err(Contradiction);
+    }
+
+    let mut suffix = vec![vec![false; length + 1]; block_count + 1];
+    for (i, fits) in suffix[block_count].iter_mut().enumerate() {
+        *fits = no_filled(i, length);
+    }
+    for j in (0..block_count).rev() {
+        let (size, plane) = blocks[j];
+        for i in (0..length).rev() {
+            let gap = suffix[j][i + 1] && may_be_empty(i);
+            let end = i + size;
+            let block = end <= length && fits(plane, i, end) && match needs_gap(j + 1) {
+                false => suffix[j + 1][end],
+                true => end < length && may_be_empty(end) && suffix[j + 1][end + 1],
+            };
+            suffix[j][i] = gap || block;
+        }
+    }
+
+    /* Difference arrays of the number of valid block placements covering each field, one per plane. */
+    let mut coverage = vec![vec![0isize; length + 1]; planes.len()];
+    for (j, &(size, plane)) in blocks.iter().enumerate().filter(|(_, &(size, _))| size <= length) {
+        for start in 0..=length - size {
+            let end = start + size;
+            let fits_left = match needs_gap(j) {
+                false => prefix[j][start],
+                true => start > 0 && may_be_empty(start - 1) && prefix[j][start - 1],
+            };
+            let fits_right = match needs_gap(j + 1) {
+                false => suffix[j + 1][end],
+                true => end < length && may_be_empty(end) && suffix[j + 1][end + 1],
+            };
+            if fits(plane, start, end) && fits_left && fits_right {
+                coverage[plane][start] += 1;
+                coverage[plane][end] -= 1;
+            }
+        }
+    }
+
+    let mut solved = LineDomain { planes: vec![BitLine::zeros(length); planes.len()] };
+    let mut covering_placements = vec![0; planes.len()];
+    for index in 0..length {
+        let can_be_empty = may_be_empty(index) && (0..=block_count).any(|j| prefix[j][index] && suffix[j][index + 1]);
+        solved.planes[EMPTY_PLANE].set(index, can_be_empty);
+        let mut possible = can_be_empty;
+        for plane in EMPTY_PLANE + 1..planes.len() {
+            covering_placements[plane] += coverage[plane][index];
+            let can_be_filled = covering_placements[plane] > 0;
+            solved.planes[plane].set(index, can_be_filled);
+            possible |= can_be_filled;
+        }
+        if !possible {
+            return Err(Contradiction);
+        }
+    }
+    Ok(solved)
+}
+
+#[cfg(test)]
+mod tests_line_solver {
+    use super::{solve_line, Contradiction};
+    use crate::grid::{BitLine, LineDomain};
+    use crate::nonogram::{BlockSpec, Color};
+
+    /// Values of planes: empty first, then the colours.
+    const VALUES: [char; 4] = ['.', '#', 'r', 'b'];
+
+    /// Writes line as colour names (`#` for black), `.` (empty) and `?` (unknown).
+    fn render(domain: LineDomain) -> String {
+        (0..domain.planes[0].len()).map(|index| {
+            let mut possible = VALUES.iter().zip(&domain.planes).filter(|(_, plane)| plane.get(index));
+            match (possible.next(), possible.next()) {
+                (Some((&value, _)), None) => value,
+                _ => '?',
+            }
+        }).collect()
+    }
+
+    /// Runs line solver on a line of black, red and blue blocks written as
+    /// colour names (`#` for black), `.` (empty) and `?` (unknown).
+    fn solve(spec: &BlockSpec, fields: &str) -> Result<String, Contradiction> {
+        let planes = VALUES.iter()
+            .map(|&value| fields.chars().map(|field| field == value || field == '?').collect::<BitLine>())
+            .collect::<Vec<_>>();
+        let colors = [Color::BLACK, Color('r'), Color('b')];
+        solve_line(spec, &colors, &planes.iter().map(BitLine::as_slice).collect::<Vec<_>>()).map(render)
+    }
+
+    #[test]
+    fn test_overlapping_block() {
+        let spec = BlockSpec::from(vec![8]);
+        assert_eq!(solve(&spec, "??????????"), Ok(String::from("??######??")));
+    }
+
+    #[test]
+    fn test_exact_fit() {
+        let spec = BlockSpec::from(vec![3, 1, 2]);
+        assert_eq!(solve(&spec, "????????"), Ok(String::from("###.#.##")));
+    }
+
+    #[test]
+    fn test_empty_spec() {
+        let spec = BlockSpec::from(vec![]);
+        assert_eq!(solve(&spec, "?????"), Ok(String::from(".....")));
+    }
+
+    #[test]
+    fn test_deduction_from_known_fields() {
+        let spec = BlockSpec::from(vec![3]);
+        assert_eq!(solve(&spec, "???#????"), Ok(String::from(".??#??..")));
+    }
+
+    #[test]
+    fn test_blocks_separated_by_known_empty() {
+        let spec = BlockSpec::from(vec![2, 2]);
+        assert_eq!(solve(&spec, "???.???"), Ok(String::from("?#?.?#?")));
+    }
+
+    #[test]
+    fn test_contradiction_too_short() {
+        let spec = BlockSpec::from(vec![3, 3]);
+        assert_eq!(solve(&spec, "??????"), Err(Contradiction));
+    }
+
+    #[test]
+    fn test_contradiction_with_known_fields() {
+        let spec = BlockSpec::from(vec![2]);
+        assert_eq!(solve(&spec, "#?#"), Err(Contradiction));
+    }
+
+    #[test]
+    fn test_different_colors_may_touch() {
+        let spec = "2r 2b".parse::<BlockSpec>().unwrap();
+        assert_eq!(solve(&spec, "????"), Ok(String::from("rrbb")));
+        assert_eq!(solve(&spec, "?????"), Ok(String::from("?r?b?")));
+    }
+
+    #[test]
+    fn test_same_colors_need_gap() {
+        let spec = "2r 1r".parse::<BlockSpec>().unwrap();
+        assert_eq!(solve(&spec, "????"), Ok(String::from("rr.r")));
+        assert_eq!(solve(&spec, "???"), Err(Contradiction));
+    }
+
+    #[test]
+    fn test_color_of_known_field() {
+        let spec = "1r 1b".parse::<BlockSpec>().unwrap();
+        assert_eq!(solve(&spec, "??b?"), Ok(String::from("??b.")));
+        assert_eq!(solve(&spec, "?#??"), Err(Contradiction));
+    }
+
+    #[test]
+    fn test_unknown_color() {
+        let spec = "1g".parse::<BlockSpec>().unwrap();
+        assert_eq!(solve(&spec, "??"), Err(Contradiction));
+    }
+}
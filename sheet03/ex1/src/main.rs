@@ -1,8 +1,17 @@
-extern crate core;
+use std::fs;
+use std::path::Path;
+use std::process;
 
-mod constraint;
-mod nonogram;
+use ex1::nonogram::{E0119CircumventionWrapper, Nonogram};
 
 fn main() {
-    println!("Hello world!");
+    let mut nonogram = match Nonogram::try_from(E0119CircumventionWrapper(Path::new("zad_input.txt"))) {
+        Ok(nonogram) => nonogram,
+        Err(error) => {
+            eprintln!("Could not read zad_input.txt: {}", error);
+            process::exit(1);
+        }
+    };
+    nonogram.solve().expect("Nonogram has no solution.");
+    fs::write("zad_output.txt", nonogram.to_string()).expect("Could not write zad_output.txt");
 }
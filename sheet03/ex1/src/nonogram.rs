@@ -1,78 +1,154 @@
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::path::Path;
-use crate::nonogram::Field::Filled;
+use std::str::FromStr;
 
+use crate::grid::{Grid, Line, LineView};
+use crate::line_solver::{solve_line, Contradiction};
+use crate::parse::{parse_block_spec, parse_nonogram, ParseError};
+use crate::solver::{Solutions, Solver, Uniqueness};
 
-#[derive(Clone, Copy)]
+
+/// Colour of filled fields, named by the character used for it in clues and text output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Color(pub char);
+
+impl Color {
+    /// Colour of black-and-white puzzles, blocks written without colour have it.
+    pub const BLACK: Color = Color('#');
+
+    /// Sorted distinct colours, just black if there are none.
+    pub fn palette(colors: impl IntoIterator<Item=Color>) -> Vec<Color> {
+        let mut palette = colors.into_iter().collect::<Vec<_>>();
+        palette.sort_unstable();
+        palette.dedup();
+        if palette.is_empty() {
+            palette.push(Color::BLACK);
+        }
+        palette
+    }
+
+    /// Colour used by image exporters. Common colour initials get their colour, other names a fixed mix.
+    pub fn rgb(self) -> [u8; 3] {
+        match self.0.to_ascii_lowercase() {
+            '#' | 'k' => [0, 0, 0],
+            'r' => [220, 40, 40],
+            'g' => [40, 160, 60],
+            'b' => [40, 80, 210],
+            'y' => [240, 200, 30],
+            'o' => [245, 130, 30],
+            'p' => [140, 60, 180],
+            'c' => [30, 180, 200],
+            'm' => [210, 50, 160],
+            'n' => [130, 80, 40],
+            'e' => [128, 128, 128],
+            'w' => [255, 255, 255],
+            name => {
+                let mix = (name as u32).wrapping_mul(2_654_435_761);
+                [(mix >> 24) as u8, (mix >> 16) as u8, (mix >> 8) as u8]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Field {
-    Filled,
+    Filled(Color),
     Empty,
 }
 
+impl Field {
+    /// Filled field of a black-and-white puzzle.
+    pub const FILLED: Field = Field::Filled(Color::BLACK);
+
+    /// Opposite value in a black-and-white puzzle, colour of filled fields is lost.
+    pub const fn flipped(self) -> Self {
+        match self {
+            Field::Filled(_) => Field::Empty,
+            Field::Empty => Field::FILLED,
+        }
+    }
+}
+
+impl Default for Field {
+    fn default() -> Self {
+        Field::FILLED
+    }
+}
+
 impl<T> From<T> for Field where T: Into<u8> {
     fn from(val: T) -> Self {
         match val.into() {
             0 => Self::Empty,
-            1 => Self::Filled,
+            1 => Self::FILLED,
             other => panic!("unexpected field value. expected 0 or 1, got: {}", other),
         }
     }
 }
 
-impl Default for Field {
-    fn default() -> Self {
-        Filled
+/// Single block of consecutive fields filled with the same colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Block {
+    pub size: usize,
+    pub color: Color,
+}
+
+/// Writes size of the block followed by its colour, black blocks are written as the size alone.
+impl Display for Block {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.color {
+            Color::BLACK => write!(f, "{}", self.size),
+            Color(name) => write!(f, "{}{}", self.size, name),
+        }
     }
 }
 
 impl BlockSpec {
 
-    /// Checks if block with given size is valid block for given index.
-    fn is_block_size_valid(&self, block_size: u8, index: usize) -> bool {
-        if let Some(&expected_block_size) = self.0.get(index) {
-            expected_block_size == block_size
-        } else {
-            false
+    /// Specification of blocks formed by filled fields of the line.
+    /// Neighbouring fields of different colours belong to different blocks.
+    pub fn from_fields(fields: impl IntoIterator<Item=Field>) -> Self {
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut previous = Field::Empty;
+        for field in fields {
+            match (field, blocks.last_mut()) {
+                (Field::Filled(_), Some(block)) if previous == field => block.size += 1,
+                (Field::Filled(color), _) => blocks.push(Block { size: 1, color }),
+                (Field::Empty, _) => {}
+            }
+            previous = field;
         }
+        BlockSpec(blocks)
     }
 
-    /// Checks node consistency of the current constraint.
+    pub fn sizes(&self) -> impl Iterator<Item=usize> + '_ {
+        self.0.iter().map(|block| block.size)
+    }
+
+    /// Colours used by the blocks, in order of blocks.
+    pub fn colors(&self) -> impl Iterator<Item=Color> + '_ {
+        self.0.iter().map(|block| block.color)
+    }
+
+    /// Minimal length of a line the blocks fit in. Only blocks of the same colour need a gap between them.
+    pub fn min_length(&self) -> usize {
+        let gaps = self.0.windows(2).filter(|pair| pair[0].color == pair[1].color).count();
+        self.sizes().sum::<usize>() + gaps
+    }
+
+    /// Checks node consistency of the current constraint:
+    /// blocks of the row must be exactly the expected blocks, both in size and colour.
     pub fn is_node_consistent(&self, row: &[Field]) -> bool {
-        let mut block_count = 0;
-        let mut temp_block_size = 0;
-        let mut rows = row.iter();
-        while let Some(&field) = rows.next() {
-            match field {
-                Field::Filled => {
-                    if temp_block_size == 0 {
-                        block_count += 1;
-                        if block_count > self.0.len() {  /* Found more blocks than expected - false. */
-                            return false;
-                        }
-                    }
-                    temp_block_size += 1
-                }
-                Field::Empty => {
-                    if temp_block_size != 0 {
-                        if !self.is_block_size_valid(temp_block_size, block_count - 1) { /* We found a block that does not match expected size - false. */
-                            return false;
-                        }
-                        temp_block_size = 0
-                    }
-                }
-            }
-        }
-        if temp_block_size != 0 && !self.is_block_size_valid(temp_block_size, block_count - 1) { return false; }
-        if block_count < self.0.len() { return false; }  /* We checked all fields in row and found too few blocks - false. */
-        true  /* If non of the above triggered than the row is valid - true. */
+        BlockSpec::from_fields(row.iter().copied()) == *self
     }
 }
 
-pub struct BlockSpec(pub Vec<u8>);
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockSpec(pub Vec<Block>);
 
 #[cfg(test)]
 mod tests_row_consistency {
-    use crate::nonogram::Field;
+    use crate::nonogram::{Block, Color, Field};
     use super::BlockSpec;
 
     fn block_from_vec(array: Vec<u8>) -> Vec<Field> {
@@ -81,174 +157,363 @@ mod tests_row_consistency {
 
     #[test]
     fn test_invalid_block_count_too_big() {
-        let spec = BlockSpec(vec![3, 4]);
+        let spec = BlockSpec::from(vec![3, 4]);
         let row = block_from_vec(vec![0, 1, 1, 1, 0, 1, 1, 1, 1, 0, 1]);
         assert!(!spec.is_node_consistent(row.as_slice()));
     }
 
     #[test]
     fn test_invalid_block_count_too_small() {
-        let spec = BlockSpec(vec![3, 4, 1]);
+        let spec = BlockSpec::from(vec![3, 4, 1]);
         let row = block_from_vec(vec![0, 1, 1, 1, 0, 1, 1, 1, 1, 0]);
         assert!(!spec.is_node_consistent(row.as_slice()));
     }
 
     #[test]
     fn test_invalid_block_size_1() {
-        let spec = BlockSpec(vec![3, 4]);
+        let spec = BlockSpec::from(vec![3, 4]);
         let row = block_from_vec(vec![0, 1, 1, 1, 1, 0, 1, 1, 1, 0]);
         assert!(!spec.is_node_consistent(row.as_slice()));
     }
 
     #[test]
     fn test_invalid_block_size_2() {
-        let spec = BlockSpec(vec![1, 1, 1, 1, 1]);
+        let spec = BlockSpec::from(vec![1, 1, 1, 1, 1]);
         let row = block_from_vec(vec![1, 0, 1, 0, 1, 0, 1, 0, 1, 1]);
         assert!(!spec.is_node_consistent(row.as_slice()));
     }
 
     #[test]
     fn test_invalid_block_size_3() {
-        let spec = BlockSpec(vec![1]);
+        let spec = BlockSpec::from(vec![1]);
         let row = block_from_vec(vec![0, 0, 0, 0, 0, 1, 1, 0]);
         assert!(!spec.is_node_consistent(row.as_slice()));
     }
 
     #[test]
     fn test_valid_1() {
-        let spec = BlockSpec(vec![2, 4]);
+        let spec = BlockSpec::from(vec![2, 4]);
         let row = block_from_vec(vec![0, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0]);
         assert!(spec.is_node_consistent(row.as_slice()));
     }
 
     #[test]
     fn test_valid_2() {
-        let spec = BlockSpec(vec![5]);
+        let spec = BlockSpec::from(vec![5]);
         let row = block_from_vec(vec![1, 1, 1, 1, 1]);
         assert!(spec.is_node_consistent(row.as_slice()));
     }
-}
 
-impl From<&str> for BlockSpec {
-    fn from(spec_line: &str) -> Self {
-        BlockSpec(spec_line
-            .split_whitespace()
-            .map(|number| number.parse().expect("Could not parse row specification."))
-            .collect()
-        )
+    fn colored(fields: &str) -> Vec<Field> {
+        fields.chars().map(|name| if name == '.' { Field::Empty } else { Field::Filled(Color(name)) }).collect()
     }
-}
 
+    #[test]
+    fn test_valid_adjacent_colors() {
+        let spec = "2r 1b 1r".parse::<BlockSpec>().unwrap();
+        assert!(spec.is_node_consistent(&colored(".rrb.r")));
+        assert!(spec.is_node_consistent(&colored("rrbr..")));
+    }
 
+    #[test]
+    fn test_invalid_color() {
+        let spec = "2r 1b".parse::<BlockSpec>().unwrap();
+        assert!(!spec.is_node_consistent(&colored("rrr.")));
+        assert!(!spec.is_node_consistent(&colored("bbr.")));
+    }
 
-pub struct RowSpecConfig<'a, 'b: 'a, I> where I: Iterator<Item=&'b str> {
-    block_count: usize,
-    lines: &'a mut I,
+    #[test]
+    fn test_same_colors_merge() {
+        let spec = BlockSpec(vec![Block { size: 1, color: Color('r') }, Block { size: 1, color: Color('r') }]);
+        assert!(!spec.is_node_consistent(&colored("rr")));
+        assert!(spec.is_node_consistent(&colored("r.r")));
+        assert_eq!(spec.min_length(), 3);
+    }
 }
 
-impl<'a, 'b: 'a, I> RowSpecConfig<'a, 'b, I> where I: Iterator<Item=&'b str> {
-    pub fn new(block_count: usize, lines: &'a mut I) -> Self { Self { block_count, lines } }
+/// Specification of black blocks with given sizes.
+impl From<Vec<u8>> for BlockSpec {
+    fn from(sizes: Vec<u8>) -> Self {
+        BlockSpec(sizes.into_iter().map(|size| Block { size: size.into(), color: Color::BLACK }).collect())
+    }
 }
 
-pub struct RowSpec(Vec<BlockSpec>);
+impl FromStr for BlockSpec {
+    type Err = ParseError;
 
-impl<'a, 'b: 'a, I> From<RowSpecConfig<'a, 'b, I>> for RowSpec where I: Iterator<Item=&'b str> {
-    fn from(spec_config: RowSpecConfig<'a, 'b, I>) -> Self {
-        let mut row_spec = Vec::<BlockSpec>::with_capacity(spec_config.block_count);
-        for line in spec_config.lines {
-            row_spec.push(BlockSpec::from(line))
-        }
-        if row_spec.len() > spec_config.block_count {
-            panic!("incorrect number of lines passed, expected: {}, got: {}", spec_config.block_count, row_spec.len());
+    fn from_str(spec_line: &str) -> Result<Self, Self::Err> {
+        parse_block_spec(spec_line, 1)
+    }
+}
+
+/// Writes blocks separated with spaces, specification without blocks is written as `0`.
+impl Display for BlockSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "0");
         }
-        Self(row_spec)
+        let blocks = self.0.iter().map(Block::to_string).collect::<Vec<_>>();
+        write!(f, "{}", blocks.join(" "))
     }
 }
 
 
 
-struct NonogramConfiguration(usize, usize);
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RowSpec(pub Vec<BlockSpec>);
 
-type Image = Vec<Vec<Field>>;
+impl RowSpec {
+    /// Specifications of given lines of a complete image.
+    pub fn from_lines<'a>(lines: impl Iterator<Item=LineView<'a>>) -> Self {
+        RowSpec(lines.map(|line| BlockSpec::from_fields(line.iter().map(|field| field.unwrap_or(Field::Empty)))).collect())
+    }
 
-pub struct Iter<'a> {
-    index: usize,
-    image_ref: &'a Image,
+    /// Colours of all blocks of all lines.
+    pub fn colors(&self) -> impl Iterator<Item=Color> + '_ {
+        self.0.iter().flat_map(BlockSpec::colors)
+    }
 }
 
-impl<'a> Iter<'a> {
-    pub fn new(image: &'a Image) -> Self {
-        Self { index: 0, image_ref: image }
+/// Writes specification of each line in separate line.
+impl Display for RowSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for block_spec in &self.0 {
+            writeln!(f, "{}", block_spec)?;
+        }
+        Ok(())
     }
 }
 
-impl<'a> Iterator for Iter<'a> {
-    type Item = &'a [Field];
+/// Reason why fields given before solving cannot be fixed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GivenError {
+    /// Grid of givens has different size than the puzzle, sizes are `(height, width)`.
+    SizeMismatch { expected: (usize, usize), found: (usize, usize) },
+    /// Given field has a colour that no block of the puzzle has.
+    UnknownColor { row: usize, col: usize, color: Color },
+    /// Givens of the line cannot be completed to match its specification.
+    LineContradiction(Line),
+    /// Every line agrees with its givens, but together they leave no solution.
+    Contradiction,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.image_ref.len() {
-            let ret = self.image_ref[self.index].as_slice();
-            self.index += 1;
-            Some(ret)
-        } else {
-            None
+impl Display for GivenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GivenError::SizeMismatch { expected, found } => {
+                write!(f, "expected givens of size {}x{}, found: {}x{}", expected.0, expected.1, found.0, found.1)
+            }
+            GivenError::UnknownColor { row, col, color } => {
+                write!(f, "field in row {}, column {} has colour {:?} that no block has", row + 1, col + 1, color.0)
+            }
+            GivenError::LineContradiction(Line::Row(row)) => write!(f, "givens of row {} contradict its clue", row + 1),
+            GivenError::LineContradiction(Line::Col(col)) => write!(f, "givens of column {} contradict its clue", col + 1),
+            GivenError::Contradiction => write!(f, "givens contradict the clues"),
         }
     }
 }
 
+impl std::error::Error for GivenError {}
+
+#[derive(Debug, Clone)]
 pub struct Nonogram {
-    image: Vec<Vec<Field>>,
-    image_transposed: Vec<Vec<Field>>,
+    grid: Grid,
     row_block_spec: RowSpec,
     col_block_spec: RowSpec,
+    /// Fields fixed before solving, if there are any.
+    givens: Option<Grid>,
 }
 
 impl Nonogram {
-    pub fn new(image: Vec<Vec<Field>>, row_block_spec: RowSpec, col_block_spec: RowSpec) -> Self {
-        let image_transposed = image.clone();
-        Self { image, image_transposed, row_block_spec, col_block_spec }
+    pub fn new(grid: Grid, row_block_spec: RowSpec, col_block_spec: RowSpec) -> Self {
+        Self { grid, row_block_spec, col_block_spec, givens: None }
     }
 
-    pub fn rows(&self) -> Iter<'_> {
-        Iter::new(&self.image)
+    /// Creates puzzle with unknown grid whose fields can take every colour used by the specifications.
+    /// Puzzle without any blocks is treated as black-and-white.
+    pub fn from_specs(row_block_spec: RowSpec, col_block_spec: RowSpec) -> Self {
+        let colors = Color::palette(row_block_spec.colors().chain(col_block_spec.colors()));
+        let grid = Grid::with_colors(row_block_spec.0.len(), col_block_spec.0.len(), &colors);
+        Self::new(grid, row_block_spec, col_block_spec)
     }
 
-    pub fn cols(&self) -> Iter<'_> {
-        Iter::new(&self.image_transposed)
+    /// Creates puzzle with specifications derived from a complete image.
+    pub fn from_image(image: &Grid) -> Self {
+        Self::from_specs(RowSpec::from_lines(image.rows()), RowSpec::from_lines(image.cols()))
     }
 
-    fn parse_config_line(config_line: &str) -> NonogramConfiguration {
-        let mut nonogram_size_iter = config_line
-            .split_whitespace()
-            .map(|word| word.parse::<usize>().expect("Could not parse Nonogram configuration line"));
-        let width = nonogram_size_iter.next().expect("No width information found in Nonogram configuration.");
-        let height = nonogram_size_iter.next().expect("No height information found in Nonogram configuration.");
-        NonogramConfiguration(width, height)
+    /// Puzzle written in the sheet03 input format: size line followed by row and column specifications
+    /// and the grid of givens, if there are any.
+    pub fn puzzle_text(&self) -> String {
+        let givens = self.givens.as_ref().map_or(String::new(), Grid::to_string);
+        format!("{} {}\n{}{}{}", self.grid.height(), self.grid.width(), self.row_block_spec, self.col_block_spec, givens)
+    }
+
+    /// Fixes fields known before solving, unknown fields of `givens` stay unknown.
+    /// Replaces any givens set before and restarts solving from them.
+    ///
+    /// Givens are checked against the clues line by line first, so that the error can name the line
+    /// they contradict, and then propagated over the whole grid.
+    pub fn set_givens(&mut self, givens: &Grid) -> Result<(), GivenError> {
+        let (height, width) = (self.grid.height(), self.grid.width());
+        if (givens.height(), givens.width()) != (height, width) {
+            return Err(GivenError::SizeMismatch { expected: (height, width), found: (givens.height(), givens.width()) });
+        }
+        let mut grid = Grid::with_colors(height, width, self.grid.colors());
+        for row in 0..height {
+            for col in 0..width {
+                match givens.get(row, col) {
+                    Some(Field::Filled(color)) if grid.plane(Field::Filled(color)).is_none() => {
+                        return Err(GivenError::UnknownColor { row, col, color });
+                    }
+                    Some(field) => grid.set(row, col, field),
+                    None => {}
+                }
+            }
+        }
+
+        let lines = (0..height).map(Line::Row).chain((0..width).map(Line::Col));
+        for line in lines {
+            let spec = match line {
+                Line::Row(row) => &self.row_block_spec.0[row],
+                Line::Col(col) => &self.col_block_spec.0[col],
+            };
+            solve_line(spec, grid.colors(), &grid.line(line).planes()).map_err(|_| GivenError::LineContradiction(line))?;
+        }
+        self.solver().propagate_all(&mut grid.clone()).map_err(|_| GivenError::Contradiction)?;
+
+        self.givens = Some(grid.clone());
+        self.grid = grid;
+        Ok(())
+    }
+
+    /// Fields fixed before solving, if there are any.
+    pub fn givens(&self) -> Option<&Grid> {
+        self.givens.as_ref()
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item=LineView<'_>> {
+        self.grid.rows()
+    }
+
+    pub fn cols(&self) -> impl Iterator<Item=LineView<'_>> {
+        self.grid.cols()
+    }
+
+    pub fn row_spec(&self) -> &RowSpec {
+        &self.row_block_spec
+    }
+
+    pub fn col_spec(&self) -> &RowSpec {
+        &self.col_block_spec
+    }
+
+    /// Solves the nonogram completing the grid with found solution.
+    pub fn solve(&mut self) -> Result<(), Contradiction> {
+        self.grid = self.solver().solve(self.grid.clone())?;
+        Ok(())
+    }
+
+    fn solver(&self) -> Solver<'_> {
+        Solver::new(&self.row_block_spec, &self.col_block_spec)
+    }
+
+    /// Iterates over all solutions consistent with the current grid.
+    pub fn solutions(&self) -> Solutions<'_> {
+        self.solver().solutions(self.grid.clone())
+    }
+
+    /// Counts solutions consistent with the current grid, stops counting after reaching `cap`.
+    pub fn count_solutions(&self, cap: usize) -> usize {
+        self.solver().count_solutions(self.grid.clone(), cap)
+    }
+
+    /// Checks if the specification describes exactly one image.
+    pub fn uniqueness(&self) -> Uniqueness {
+        self.solver().uniqueness(self.grid.clone())
+    }
+
+    /// Checks if the grid is complete and every row and column matches its specification.
+    pub fn is_solved(&self) -> bool {
+        let lines_consistent = |lines: &mut dyn Iterator<Item=LineView<'_>>, spec: &RowSpec| {
+            lines.zip(spec.0.iter()).all(|(line, block_spec)| {
+                line.iter().collect::<Option<Vec<_>>>().is_some_and(|row| block_spec.is_node_consistent(&row))
+            })
+        };
+        self.grid.is_complete()
+            && lines_consistent(&mut self.rows(), &self.row_block_spec)
+            && lines_consistent(&mut self.cols(), &self.col_block_spec)
     }
 }
 
-impl<'a, S> From<S> for Nonogram where S: Into<&'a str> {
-    fn from(image: S) -> Self {
-        let mut lines = image.into().lines();
-        let config_line = lines.next().expect("Nonogram configuration line not found.");
-        let NonogramConfiguration(width, height) = Nonogram::parse_config_line(config_line);
+impl Display for Nonogram {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.grid)
+    }
+}
 
-        let row_block_spec = RowSpec::from(RowSpecConfig::new(width, &mut lines));
-        let col_block_spec = RowSpec::from(RowSpecConfig::new(height, &mut lines));
-        let image = vec![vec![Default::default(); width]; height];
+impl FromStr for Nonogram {
+    type Err = ParseError;
 
-        Self::new(image, row_block_spec, col_block_spec)
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_nonogram(text)
     }
 }
 
 /* ehhhh: https://github.com/rust-lang/rust/issues/50133 */
-struct E0119CircumventionWrapper<T>(T);
+pub struct E0119CircumventionWrapper<T>(pub T);
 
 impl<'a, T> TryFrom<E0119CircumventionWrapper<T>> for Nonogram where T: Into<&'a Path> {
     type Error = std::io::Error;
 
     fn try_from(path: E0119CircumventionWrapper<T>) -> Result<Self, Self::Error> {
         let data = fs::read_to_string(path.0.into())?;
-        Ok(Nonogram::from(data.as_str()))
+        data.parse().map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+}
+
+#[cfg(test)]
+mod tests_givens {
+    use super::{Color, Field, GivenError, Nonogram};
+    use crate::grid::{Grid, Line};
+
+    fn diagonal() -> Nonogram {
+        "2 2\n1\n1\n1\n1\n".parse::<Nonogram>().unwrap()
+    }
+
+    #[test]
+    fn test_givens_pick_solution() {
+        let mut nonogram = diagonal();
+        assert_eq!(nonogram.count_solutions(10), 2);
+        let mut givens = Grid::new(2, 2);
+        givens.set(0, 1, Field::FILLED);
+        nonogram.set_givens(&givens).unwrap();
+        assert_eq!(nonogram.count_solutions(10), 1);
+        nonogram.solve().unwrap();
+        assert_eq!(nonogram.to_string(), ".#\n#.\n");
+        assert_eq!(nonogram.givens(), Some(&givens));
+    }
+
+    #[test]
+    fn test_invalid_givens() {
+        let mut nonogram = diagonal();
+        let expected = GivenError::SizeMismatch { expected: (2, 2), found: (2, 3) };
+        assert_eq!(nonogram.set_givens(&Grid::new(2, 3)), Err(expected));
+
+        let red = Color('r');
+        let mut givens = Grid::with_colors(2, 2, &[Color::BLACK, red]);
+        givens.set(1, 0, Field::Filled(red));
+        assert_eq!(nonogram.set_givens(&givens), Err(GivenError::UnknownColor { row: 1, col: 0, color: red }));
+
+        let mut givens = Grid::new(2, 2);
+        givens.set(0, 0, Field::FILLED);
+        givens.set(0, 1, Field::FILLED);
+        assert_eq!(nonogram.set_givens(&givens), Err(GivenError::LineContradiction(Line::Row(0))));
+        assert_eq!(nonogram.givens(), None);
+        assert_eq!(GivenError::LineContradiction(Line::Row(0)).to_string(), "givens of row 1 contradict its clue");
     }
 }
@@ -0,0 +1,360 @@
+use std::fmt::{Display, Formatter};
+
+use crate::formats::text::parse_field;
+use crate::grid::{Grid, Line};
+use crate::nonogram::{Block, BlockSpec, Color, GivenError, Nonogram, RowSpec};
+
+/// Reason why the nonogram description could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Input does not contain the line with nonogram size.
+    MissingSizeLine,
+    /// Size line ended before given dimension.
+    MissingSize(&'static str),
+    /// Token is not a valid number.
+    InvalidNumber(String),
+    /// Block size is followed by something else than a single letter naming its colour.
+    InvalidColor(String),
+    /// Line contains more tokens than expected.
+    UnexpectedToken(String),
+    /// Input ended before specifications of all rows and columns were read.
+    MissingSpecLines { expected: usize, found: usize },
+    /// Input contains more lines than rows and columns.
+    UnexpectedLine,
+    /// Input ended before givens of all rows were read.
+    MissingGivenLines { expected: usize, found: usize },
+    /// Givens cannot be fixed, usually because they contradict the clues.
+    InvalidGivens(GivenError),
+    /// Blocks together with gaps between them do not fit in the line.
+    BlocksTooLong { required: usize, length: usize },
+    /// File does not start with a supported format identifier.
+    UnsupportedFormat(String),
+    /// Input ended in the middle of a value.
+    UnexpectedEnd,
+    /// Character does not describe any field value.
+    InvalidField(char),
+    /// Line of an image has different length than the first line.
+    InvalidLineLength { expected: usize, found: usize },
+    /// Required key or section is not present in the file.
+    MissingKey(&'static str),
+    /// Input does not follow the syntax of the format.
+    InvalidSyntax(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::MissingSizeLine => write!(f, "missing nonogram size line"),
+            ParseErrorKind::MissingSize(dimension) => write!(f, "missing {}", dimension),
+            ParseErrorKind::InvalidNumber(token) => write!(f, "expected a number, got: {:?}", token),
+            ParseErrorKind::InvalidColor(name) => write!(f, "expected a colour letter, got: {:?}", name),
+            ParseErrorKind::UnexpectedToken(token) => write!(f, "unexpected token: {:?}", token),
+            ParseErrorKind::MissingSpecLines { expected, found } => {
+                write!(f, "expected {} specification lines, found: {}", expected, found)
+            }
+            ParseErrorKind::UnexpectedLine => write!(f, "unexpected line after all specifications"),
+            ParseErrorKind::MissingGivenLines { expected, found } => {
+                write!(f, "expected {} lines of givens, found: {}", expected, found)
+            }
+            ParseErrorKind::InvalidGivens(error) => write!(f, "{}", error),
+            ParseErrorKind::BlocksTooLong { required, length } => {
+                write!(f, "blocks need at least {} fields, but the line has {}", required, length)
+            }
+            ParseErrorKind::UnsupportedFormat(magic) => write!(f, "unsupported format: {:?}", magic),
+            ParseErrorKind::UnexpectedEnd => write!(f, "unexpected end of input"),
+            ParseErrorKind::InvalidField(field) => write!(f, "invalid field: {:?}", field),
+            ParseErrorKind::InvalidLineLength { expected, found } => {
+                write!(f, "expected line of length {}, found: {}", expected, found)
+            }
+            ParseErrorKind::MissingKey(key) => write!(f, "missing {:?}", key),
+            ParseErrorKind::InvalidSyntax(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Parse error with position of its cause. Lines and columns are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, kind: ParseErrorKind) -> Self {
+        Self { line, column, kind }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Whitespace separated tokens of the line together with their columns.
+pub(crate) fn tokens(line: &str) -> impl Iterator<Item=(usize, &str)> {
+    line.split_whitespace().map(move |token| {
+        let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+        (line[..offset].chars().count() + 1, token)
+    })
+}
+
+pub(crate) fn parse_number<T: std::str::FromStr>(line_number: usize, column: usize, token: &str) -> Result<T, ParseError> {
+    token.parse().map_err(|_| ParseError::new(line_number, column, ParseErrorKind::InvalidNumber(token.to_string())))
+}
+
+/// Parses a single block: its size optionally followed by a letter naming its colour, like `3` or `3r`.
+pub(crate) fn parse_block(line_number: usize, column: usize, token: &str) -> Result<Block, ParseError> {
+    let digits = token.find(|character: char| !character.is_ascii_digit()).unwrap_or(token.len());
+    let (size, name) = token.split_at(digits);
+    let size = parse_number(line_number, column, size)
+        .map_err(|_| ParseError::new(line_number, column, ParseErrorKind::InvalidNumber(token.to_string())))?;
+    let mut letters = name.chars();
+    let color = match (letters.next(), letters.next()) {
+        (None, _) => Color::BLACK,
+        (Some(letter), None) if letter.is_ascii_alphabetic() => Color(letter),
+        _ => return Err(ParseError::new(line_number, column + digits, ParseErrorKind::InvalidColor(name.to_string()))),
+    };
+    Ok(Block { size, color })
+}
+
+/// Parses blocks of a single line. Blocks of size 0 are skipped, so `0` describes an empty line.
+pub fn parse_block_spec(line: &str, line_number: usize) -> Result<BlockSpec, ParseError> {
+    let mut blocks = Vec::new();
+    for (column, token) in tokens(line) {
+        let block = parse_block(line_number, column, token)?;
+        if block.size != 0 {
+            blocks.push(block);
+        }
+    }
+    Ok(BlockSpec(blocks))
+}
+
+/// Checks that blocks together with gaps between them fit in the line.
+pub(crate) fn check_fits(spec: &BlockSpec, line_length: usize, line_number: usize) -> Result<(), ParseError> {
+    let required = spec.min_length();
+    if required > line_length {
+        let kind = ParseErrorKind::BlocksTooLong { required, length: line_length };
+        return Err(ParseError::new(line_number, 1, kind));
+    }
+    Ok(())
+}
+
+/// Parses the size line: row count followed by column count.
+fn parse_size_line(line: &str, line_number: usize) -> Result<(usize, usize), ParseError> {
+    let mut tokens = tokens(line);
+    let end_column = line.chars().count() + 1;
+    let mut dimension = |name| match tokens.next() {
+        Some((column, token)) => parse_number(line_number, column, token),
+        None => Err(ParseError::new(line_number, end_column, ParseErrorKind::MissingSize(name))),
+    };
+    let row_count = dimension("row count")?;
+    let col_count = dimension("column count")?;
+    match tokens.next() {
+        Some((column, token)) => Err(ParseError::new(line_number, column, ParseErrorKind::UnexpectedToken(token.to_string()))),
+        None => Ok((row_count, col_count)),
+    }
+}
+
+/// Parses grid of givens, one non-blank line per row, and fixes them in the nonogram.
+fn parse_givens(nonogram: &mut Nonogram, lines: &[(usize, &str)]) -> Result<(), ParseError> {
+    let (height, width) = (nonogram.grid().height(), nonogram.grid().width());
+    if let Some(&(line_number, line)) = lines.get(height) {
+        let column = tokens(line).next().map_or(1, |(column, _)| column);
+        return Err(ParseError::new(line_number, column, ParseErrorKind::UnexpectedLine));
+    }
+    if lines.len() < height {
+        let line_number = lines.last().map_or(1, |&(line_number, _)| line_number + 1);
+        let kind = ParseErrorKind::MissingGivenLines { expected: height, found: lines.len() };
+        return Err(ParseError::new(line_number, 1, kind));
+    }
+
+    let mut givens = Grid::with_colors(height, width, nonogram.grid().colors());
+    for (row, &(line_number, line)) in lines.iter().enumerate() {
+        let line = line.trim_end();
+        if line.chars().count() != width {
+            let kind = ParseErrorKind::InvalidLineLength { expected: width, found: line.chars().count() };
+            return Err(ParseError::new(line_number, 1, kind));
+        }
+        for (col, character) in line.chars().enumerate() {
+            let invalid = ParseError::new(line_number, col + 1, ParseErrorKind::InvalidField(character));
+            match parse_field(character).map_err(|_| invalid.clone())? {
+                Some(field) if givens.plane(field).is_none() => return Err(invalid),
+                Some(field) => givens.set(row, col, field),
+                None => {}
+            }
+        }
+    }
+
+    nonogram.set_givens(&givens).map_err(|error| {
+        let (line_number, column) = match error {
+            GivenError::LineContradiction(Line::Row(row)) => (lines[row].0, 1),
+            GivenError::LineContradiction(Line::Col(col)) => (lines[0].0, col + 1),
+            _ => (lines[0].0, 1),
+        };
+        ParseError::new(line_number, column, ParseErrorKind::InvalidGivens(error))
+    })
+}
+
+/// Parses nonogram in the sheet03 format.
+///
+/// First line contains number of rows and number of columns, next come specifications of
+/// all rows followed by specifications of all columns, one line each. Trailing blank lines are ignored.
+/// Blocks of colour puzzles carry a colour letter after their size, like `3r 2b`.
+///
+/// Specifications may be followed by a grid of fields fixed before solving, one line per row written
+/// the same way as the output: `#` or a colour letter for filled fields, `.` for empty and `?` for unknown.
+pub fn parse_nonogram(text: &str) -> Result<Nonogram, ParseError> {
+    let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line));
+    let (size_line_number, size_line) = lines
+        .by_ref()
+        .find(|(_, line)| !line.trim().is_empty())
+        .ok_or(ParseError::new(1, 1, ParseErrorKind::MissingSizeLine))?;
+    let (row_count, col_count) = parse_size_line(size_line, size_line_number)?;
+
+    let expected = row_count + col_count;
+    let mut specs = Vec::with_capacity(expected);
+    for (line_number, line) in lines.by_ref().take(expected) {
+        let line_length = if specs.len() < row_count { col_count } else { row_count };
+        let spec = parse_block_spec(line, line_number)?;
+        check_fits(&spec, line_length, line_number)?;
+        specs.push(spec);
+    }
+    if specs.len() < expected {
+        let line_number = size_line_number + specs.len() + 1;
+        let kind = ParseErrorKind::MissingSpecLines { expected, found: specs.len() };
+        return Err(ParseError::new(line_number, 1, kind));
+    }
+
+    let col_block_spec = RowSpec(specs.split_off(row_count));
+    let mut nonogram = Nonogram::from_specs(RowSpec(specs), col_block_spec);
+    let given_lines = lines.filter(|(_, line)| !line.trim().is_empty()).collect::<Vec<_>>();
+    if !given_lines.is_empty() {
+        parse_givens(&mut nonogram, &given_lines)?;
+    }
+    Ok(nonogram)
+}
+
+#[cfg(test)]
+mod tests_parse {
+    use super::{parse_block_spec, parse_nonogram, ParseError, ParseErrorKind};
+    use crate::grid::Line;
+    use crate::nonogram::{BlockSpec, Color, GivenError};
+
+    fn error(text: &str) -> ParseError {
+        parse_nonogram(text).expect_err("expected parse error")
+    }
+
+    #[test]
+    fn test_rows_and_columns_are_split() {
+        let nonogram = parse_nonogram("2 3\n3\n1\n1\n2\n1\n").unwrap();
+        assert_eq!(nonogram.grid().height(), 2);
+        assert_eq!(nonogram.grid().width(), 3);
+        assert_eq!(nonogram.row_spec().0, [BlockSpec::from(vec![3]), BlockSpec::from(vec![1])]);
+        assert_eq!(nonogram.col_spec().0, [BlockSpec::from(vec![1]), BlockSpec::from(vec![2]), BlockSpec::from(vec![1])]);
+    }
+
+    #[test]
+    fn test_zero_is_empty_line() {
+        assert_eq!(parse_block_spec("0", 1), Ok(BlockSpec::from(vec![])));
+        assert_eq!(parse_block_spec("", 1), Ok(BlockSpec::from(vec![])));
+    }
+
+    #[test]
+    fn test_missing_size_line() {
+        assert_eq!(error(""), ParseError::new(1, 1, ParseErrorKind::MissingSizeLine));
+        assert_eq!(error("\n  \n"), ParseError::new(1, 1, ParseErrorKind::MissingSizeLine));
+    }
+
+    #[test]
+    fn test_missing_column_count() {
+        assert_eq!(error("2\n1\n1\n"), ParseError::new(1, 2, ParseErrorKind::MissingSize("column count")));
+    }
+
+    #[test]
+    fn test_extra_size_token() {
+        assert_eq!(error("2 2 2\n"), ParseError::new(1, 5, ParseErrorKind::UnexpectedToken(String::from("2"))));
+    }
+
+    #[test]
+    fn test_non_numeric_token() {
+        let kind = ParseErrorKind::InvalidNumber(String::from("x"));
+        assert_eq!(error("2 2\n1\n1  x\n1\n1\n"), ParseError::new(3, 4, kind));
+    }
+
+    #[test]
+    fn test_too_few_spec_lines() {
+        let kind = ParseErrorKind::MissingSpecLines { expected: 4, found: 3 };
+        assert_eq!(error("2 2\n1\n1\n1\n"), ParseError::new(5, 1, kind));
+    }
+
+    #[test]
+    fn test_too_many_lines() {
+        assert_eq!(error("2 2\n1\n1\n1\n1\n#.\n\n.#\n\n ?\n"), ParseError::new(10, 2, ParseErrorKind::UnexpectedLine));
+    }
+
+    #[test]
+    fn test_givens() {
+        let nonogram = parse_nonogram("2 3\n2\n1\n1\n1\n1\n\n?#?\n??.\n").unwrap();
+        assert_eq!(nonogram.grid().to_string(), "?#?\n??.\n");
+        assert_eq!(nonogram.puzzle_text(), "2 3\n2\n1\n1\n1\n1\n?#?\n??.\n");
+    }
+
+    #[test]
+    fn test_invalid_givens() {
+        let kind = ParseErrorKind::MissingGivenLines { expected: 2, found: 1 };
+        assert_eq!(error("2 2\n1\n1\n1\n1\n#.\n"), ParseError::new(7, 1, kind));
+        let kind = ParseErrorKind::InvalidLineLength { expected: 2, found: 3 };
+        assert_eq!(error("2 2\n1\n1\n1\n1\n#.\n#..\n"), ParseError::new(7, 1, kind));
+        assert_eq!(error("2 2\n1\n1\n1\n1\n#.\n.r\n"), ParseError::new(7, 2, ParseErrorKind::InvalidField('r')));
+    }
+
+    #[test]
+    fn test_givens_contradicting_clues() {
+        let kind = ParseErrorKind::InvalidGivens(GivenError::LineContradiction(Line::Row(1)));
+        assert_eq!(error("2 2\n1\n1\n1\n1\n??\n##\n"), ParseError::new(7, 1, kind));
+        let kind = ParseErrorKind::InvalidGivens(GivenError::LineContradiction(Line::Col(1)));
+        assert_eq!(error("2 2\n1\n1\n2\n0\n?#\n??\n"), ParseError::new(6, 2, kind));
+        let kind = ParseErrorKind::InvalidGivens(GivenError::Contradiction);
+        assert_eq!(error("1 2\n1\n0\n0\n??\n"), ParseError::new(5, 1, kind));
+    }
+
+    #[test]
+    fn test_blocks_too_long() {
+        let kind = ParseErrorKind::BlocksTooLong { required: 4, length: 3 };
+        assert_eq!(error("2 3\n3\n2 1\n1\n2\n1\n"), ParseError::new(3, 1, kind));
+    }
+
+    #[test]
+    fn test_column_blocks_checked_against_height() {
+        let kind = ParseErrorKind::BlocksTooLong { required: 3, length: 2 };
+        assert_eq!(error("2 3\n3\n1\n1\n3\n1\n"), ParseError::new(5, 1, kind));
+    }
+
+    #[test]
+    fn test_colored_blocks() {
+        let spec = parse_block_spec("3r 0b 2 1b", 1).unwrap();
+        assert_eq!(spec.to_string(), "3r 2 1b");
+        assert_eq!(spec.colors().collect::<Vec<_>>(), [Color('r'), Color::BLACK, Color('b')]);
+        let nonogram = parse_nonogram("1 3\n1r 2b\n1r\n1b\n1b\n").unwrap();
+        assert_eq!(nonogram.grid().colors(), [Color('b'), Color('r')]);
+    }
+
+    #[test]
+    fn test_invalid_color() {
+        let kind = ParseErrorKind::InvalidColor(String::from("rb"));
+        assert_eq!(parse_block_spec("1 12rb", 4), Err(ParseError::new(4, 5, kind)));
+        let kind = ParseErrorKind::InvalidNumber(String::from("r"));
+        assert_eq!(parse_block_spec("r", 1), Err(ParseError::new(1, 1, kind)));
+    }
+
+    #[test]
+    fn test_different_colors_need_no_gap() {
+        assert!(parse_nonogram("1 2\n1r 1b\n1r\n1b\n").is_ok());
+        let kind = ParseErrorKind::BlocksTooLong { required: 3, length: 2 };
+        assert_eq!(error("1 2\n1r 1r\n1r\n1r\n"), ParseError::new(2, 1, kind));
+    }
+}
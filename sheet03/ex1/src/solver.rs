@@ -0,0 +1,350 @@
+use std::collections::VecDeque;
+
+use crate::grid::{Grid, Line};
+use crate::line_solver::{solve_line, Contradiction};
+use crate::nonogram::{Field, RowSpec};
+
+/// Nonogram solver combining line solving, probing and backtracking.
+///
+/// Search proceeds in three stages, each one used only if the previous one got stuck:
+/// 1. line propagation - line solver is run over rows and columns until nothing changes,
+/// 2. probing - every unknown field is assumed filled and empty in turn and propagated,
+///    an assumption that leads to contradiction fixes the field to the other value,
+/// 3. backtracking - value of some unknown field is guessed and search recurses.
+#[derive(Clone, Copy)]
+pub struct Solver<'a> {
+    row_spec: &'a RowSpec,
+    col_spec: &'a RowSpec,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(row_spec: &'a RowSpec, col_spec: &'a RowSpec) -> Self {
+        Self { row_spec, col_spec }
+    }
+
+    fn height(&self) -> usize {
+        self.row_spec.0.len()
+    }
+
+    fn width(&self) -> usize {
+        self.col_spec.0.len()
+    }
+
+    /// Finds any completion of the grid that satisfies row and column specifications.
+    pub fn solve(&self, mut grid: Grid) -> Result<Grid, Contradiction> {
+        self.propagate_all(&mut grid)?;
+        self.search(grid)
+    }
+
+    /// Runs line solver over all rows and columns until no new field gets deduced.
+    pub fn propagate_all(&self, grid: &mut Grid) -> Result<(), Contradiction> {
+        let lines = (0..self.height()).map(Line::Row).chain((0..self.width()).map(Line::Col));
+        self.propagate(grid, lines.collect())
+    }
+
+    /// Runs line solver over queued lines until no new field gets deduced.
+    fn propagate(&self, grid: &mut Grid, mut queue: VecDeque<Line>) -> Result<(), Contradiction> {
+        let mut queued_rows = vec![false; self.height()];
+        let mut queued_cols = vec![false; self.width()];
+        let mut is_queued = |line: Line| match line {
+            Line::Row(row) => std::mem::replace(&mut queued_rows[row], true),
+            Line::Col(col) => std::mem::replace(&mut queued_cols[col], true),
+        };
+        queue.retain(|&line| !is_queued(line));
+
+        while let Some(line) = queue.pop_front() {
+            let spec = match line {
+                Line::Row(row) => { queued_rows[row] = false; &self.row_spec.0[row] }
+                Line::Col(col) => { queued_cols[col] = false; &self.col_spec.0[col] }
+            };
+            let view = grid.line(line);
+            let solved = solve_line(spec, grid.colors(), &view.planes())?;
+            let changed = grid.line_mut(line).restrict(&solved);
+            for index in changed.as_slice().ones() {
+                let crossing = line.crossing(index);
+                let queued = match crossing {
+                    Line::Row(row) => &mut queued_rows[row],
+                    Line::Col(col) => &mut queued_cols[col],
+                };
+                if !std::mem::replace(queued, true) {
+                    queue.push_back(crossing);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns propagated copy of the grid with given field set to given value.
+    fn assume(&self, grid: &Grid, row: usize, col: usize, field: Field) -> Result<Grid, Contradiction> {
+        let mut grid = grid.clone();
+        grid.set(row, col, field);
+        self.propagate(&mut grid, VecDeque::from([Line::Row(row), Line::Col(col)]))?;
+        Ok(grid)
+    }
+
+    /// Probes every unknown field until no probe gives new information.
+    ///
+    /// Every value the field can still take is assumed in turn. Values leading to contradiction are excluded,
+    /// fields that end up with the same value under all remaining assumptions are fixed as well.
+    pub fn probe(&self, grid: &mut Grid) -> Result<(), Contradiction> {
+        loop {
+            let mut progress = false;
+            for row in 0..self.height() {
+                for col in 0..self.width() {
+                    if grid.get(row, col).is_some() {
+                        continue;
+                    }
+                    let mut consistent = Vec::new();
+                    let mut failed = Vec::new();
+                    for field in grid.values().filter(|&field| grid.can_be(row, col, field)) {
+                        match self.assume(grid, row, col, field) {
+                            Ok(assumed) => consistent.push(assumed),
+                            Err(Contradiction) => failed.push(field),
+                        }
+                    }
+                    match consistent.as_slice() {
+                        [] => return Err(Contradiction),
+                        [forced] => {
+                            *grid = forced.clone();
+                            progress = true;
+                        }
+                        [first, rest @ ..] => {
+                            let mut queue = VecDeque::new();
+                            for field in failed {
+                                grid.exclude(row, col, field);
+                                queue.extend([Line::Row(row), Line::Col(col)]);
+                            }
+                            for r in 0..self.height() {
+                                for c in 0..self.width() {
+                                    match (grid.get(r, c), first.get(r, c)) {
+                                        (None, Some(agreed)) if rest.iter().all(|other| other.get(r, c) == Some(agreed)) => {
+                                            grid.set(r, c, agreed);
+                                            queue.extend([Line::Row(r), Line::Col(c)]);
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
+                            if !queue.is_empty() {
+                                self.propagate(grid, queue)?;
+                                progress = true;
+                            }
+                        }
+                    }
+                }
+            }
+            if !progress {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Iterates over all completions of the grid that satisfy row and column specifications.
+    pub fn solutions(&self, mut grid: Grid) -> Solutions<'a> {
+        let stack = match self.propagate_all(&mut grid) {
+            Ok(()) => vec![grid],
+            Err(Contradiction) => vec![],
+        };
+        Solutions { solver: *self, stack }
+    }
+
+    /// Counts solutions of the grid, stops counting after reaching `cap`.
+    pub fn count_solutions(&self, grid: Grid, cap: usize) -> usize {
+        self.solutions(grid).take(cap).count()
+    }
+
+    /// Checks if the grid has exactly one solution.
+    pub fn uniqueness(&self, grid: Grid) -> Uniqueness {
+        let mut solutions = self.solutions(grid);
+        match (solutions.next(), solutions.next()) {
+            (None, _) => Uniqueness::Unsolvable,
+            (Some(solution), None) => Uniqueness::Unique(solution),
+            (Some(first), Some(second)) => {
+                let differences = (0..self.height())
+                    .flat_map(|row| (0..self.width()).map(move |col| (row, col)))
+                    .filter(|&(row, col)| first.get(row, col) != second.get(row, col))
+                    .collect();
+                Uniqueness::Ambiguous { first, second, differences }
+            }
+        }
+    }
+
+    /// Backtracking search over values of fields that could not be deduced.
+    fn search(&self, mut grid: Grid) -> Result<Grid, Contradiction> {
+        self.probe(&mut grid)?;
+        match grid.first_unknown() {
+            None => Ok(grid),
+            Some((row, col)) => grid.values()
+                .rev()
+                .filter(|&field| grid.can_be(row, col, field))
+                .filter_map(|field| self.assume(&grid, row, col, field).ok())
+                .find_map(|guess| self.search(guess).ok())
+                .ok_or(Contradiction),
+        }
+    }
+}
+
+/// Result of checking whether a nonogram has exactly one solution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Uniqueness {
+    Unsolvable,
+    Unique(Grid),
+    /// Two distinct solutions together with coordinates `(row, col)` of fields where they differ.
+    Ambiguous { first: Grid, second: Grid, differences: Vec<(usize, usize)> },
+}
+
+/// Iterator over all solutions, explores search tree depth first keeping pending branches on a stack.
+pub struct Solutions<'a> {
+    solver: Solver<'a>,
+    stack: Vec<Grid>,
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Grid;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(mut grid) = self.stack.pop() {
+            if self.solver.probe(&mut grid).is_err() {
+                continue;
+            }
+            match grid.first_unknown() {
+                None => return Some(grid),
+                Some((row, col)) => {
+                    let branches = grid.values()
+                        .filter(|&field| grid.can_be(row, col, field))
+                        .filter_map(|field| self.solver.assume(&grid, row, col, field).ok())
+                        .collect::<Vec<_>>();
+                    self.stack.extend(branches);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests_solver {
+    use super::{Solver, Uniqueness};
+    use crate::generator::{grade, Difficulty};
+    use crate::grid::Grid;
+    use crate::nonogram::{BlockSpec, Nonogram, RowSpec};
+
+    fn spec(lines: &[&str]) -> RowSpec {
+        RowSpec(lines.iter().map(|&line| line.parse::<BlockSpec>().unwrap()).collect())
+    }
+
+    fn solve(rows: &RowSpec, cols: &RowSpec) -> Vec<String> {
+        let solution = Solver::new(rows, cols).solve(Grid::new(rows.0.len(), cols.0.len())).unwrap();
+        solution.to_string().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_solved_by_propagation() {
+        let rows = spec(&["5", "1 1 1", "3", "2 2", "5"]);
+        let cols = spec(&["2 2", "1 3", "3 1", "1 3", "2 2"]);
+        assert_eq!(solve(&rows, &cols), ["#####", "#.#.#", ".###.", "##.##", "#####"]);
+    }
+
+    #[test]
+    fn test_ambiguous_needs_search() {
+        let rows = spec(&["1", "1"]);
+        let cols = spec(&["1", "1"]);
+        let rendered = solve(&rows, &cols);
+        assert!(rendered == ["#.", ".#"] || rendered == [".#", "#."]);
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        let rows = spec(&["2", ""]);
+        let cols = spec(&["1", ""]);
+        assert!(Solver::new(&rows, &cols).solve(Grid::new(2, 2)).is_err());
+    }
+
+    #[test]
+    fn test_backtracking_puzzle() {
+        let mut nonogram = concat!(
+            "20 15\n", "3\n", "1 2\n", "1 4\n", "1 1 2\n", "1 1 1 1\n", "1 3 2\n", "2 3 1\n", "1 1 1 2\n",
+            "2 2 2\n", "1 1 2 2\n", "1 1 2 2\n", "1 1 1 1\n", "4 1 1\n", "2 2 2 1\n", "2 3 3\n", "2 2 3\n",
+            "1 3 1 1\n", "2 1 1 1 2\n", "1 2 3\n", "1 6\n", "4 3\n", "6 1 2 3\n", "2 3\n", "6\n", "1 2 2\n",
+            "1 1 2\n", "2 4 1 1\n", "1 1 2 2 2 1\n", "1 1 1 2 1 1\n", "1 3 2 3\n", "3 2 2\n", "4 3 4 2\n",
+            "1 3 4 5\n", "2 2\n", "3\n",
+        ).parse::<Nonogram>().unwrap();
+        assert_eq!(grade(&nonogram), Some(Difficulty::Probing));
+        nonogram.solve().unwrap();
+        assert!(nonogram.is_solved());
+        assert_eq!(nonogram.to_string(), concat!(
+            "..........###..\n", ".#........##...\n", ".#......####...\n", ".#.....#...##..\n", ".#....#.#...#..\n",
+            ".#..###....##..\n", ".##....###.#...\n", "..#.#....#.##..\n", "...##...##..##.\n", "#..#...##...##.\n",
+            "#..#..##...##..\n", "#..#..#....#...\n", "####..#....#...\n", "..##..##...##.#\n", ".##....###..###\n",
+            "##.......##.###\n", "#....###..#.#..\n", "##..#..#.#.##..\n", ".#..##...###...\n", ".#...######....\n",
+        ));
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let rows = spec(&["1", "1", "1"]);
+        let cols = spec(&["1", "1", "1"]);
+        let solver = Solver::new(&rows, &cols);
+        assert_eq!(solver.count_solutions(Grid::new(3, 3), 100), 6);
+        assert_eq!(solver.count_solutions(Grid::new(3, 3), 4), 4);
+    }
+
+    #[test]
+    fn test_solutions_are_distinct() {
+        let rows = spec(&["1", "1", "1"]);
+        let cols = spec(&["1", "1", "1"]);
+        let solutions = Solver::new(&rows, &cols).solutions(Grid::new(3, 3)).collect::<Vec<_>>();
+        for (index, solution) in solutions.iter().enumerate() {
+            assert!(solution.is_complete());
+            assert!(!solutions[index + 1..].contains(solution));
+        }
+    }
+
+    #[test]
+    fn test_unique() {
+        let rows = spec(&["5", "1 1 1", "3", "2 2", "5"]);
+        let cols = spec(&["2 2", "1 3", "3 1", "1 3", "2 2"]);
+        assert!(matches!(Solver::new(&rows, &cols).uniqueness(Grid::new(5, 5)), Uniqueness::Unique(_)));
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let rows = spec(&["1", "1", ""]);
+        let cols = spec(&["1", "1", ""]);
+        match Solver::new(&rows, &cols).uniqueness(Grid::new(3, 3)) {
+            Uniqueness::Ambiguous { first, second, differences } => {
+                assert_ne!(first, second);
+                assert_eq!(differences, [(0, 0), (0, 1), (1, 0), (1, 1)]);
+            }
+            other => panic!("expected ambiguous puzzle, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unsolvable_has_no_solutions() {
+        let rows = spec(&["2", ""]);
+        let cols = spec(&["1", ""]);
+        let solver = Solver::new(&rows, &cols);
+        assert_eq!(solver.count_solutions(Grid::new(2, 2), 10), 0);
+        assert_eq!(solver.uniqueness(Grid::new(2, 2)), Uniqueness::Unsolvable);
+    }
+
+    #[test]
+    fn test_colored_puzzle() {
+        let mut nonogram = "3 3\n2r 1b\n2b\n1r 1b\n1r 1r\n1r 1b\n3b\n".parse::<Nonogram>().unwrap();
+        nonogram.solve().unwrap();
+        assert!(nonogram.is_solved());
+        assert_eq!(nonogram.to_string(), "rrb\n.bb\nr.b\n");
+        assert_eq!(nonogram.count_solutions(10), 1);
+    }
+
+    #[test]
+    fn test_colored_solutions() {
+        let nonogram = "2 2\n1r 1b\n1b 1r\n1r 1b\n1b 1r\n".parse::<Nonogram>().unwrap();
+        assert_eq!(nonogram.solutions().map(|grid| grid.to_string()).collect::<Vec<_>>(), ["rb\nbr\n"]);
+        let nonogram = "2 2\n1r\n1r\n1r\n1r\n".parse::<Nonogram>().unwrap();
+        assert_eq!(nonogram.count_solutions(10), 2);
+        let nonogram = "2 2\n1r\n1b\n1r\n1b\n".parse::<Nonogram>().unwrap();
+        assert_eq!(nonogram.solutions().map(|grid| grid.to_string()).collect::<Vec<_>>(), ["r.\n.b\n"]);
+    }
+}
@@ -0,0 +1,118 @@
+use sat::cardinality::exactly_one;
+use sat::cnf::Cnf;
+use sat::lit::{Lit, Var};
+use sat::model::Model;
+use sat::solver::{SolveResult, Solver};
+
+use crate::model::Model as SudokuModel;
+use crate::sudoku::Sudoku;
+
+/// Sudoku encoded as a CNF formula.
+///
+/// Every cell has a variable per value. Every cell takes exactly one value and every value appears
+/// exactly once in each row, column and box. Given cells are fixed by unit clauses.
+pub struct SudokuEncoding {
+    cnf: Cnf,
+    box_size: usize,
+}
+
+impl SudokuEncoding {
+    pub fn new(sudoku: &Sudoku) -> Self {
+        let model = SudokuModel::new(sudoku.box_size());
+        let side = model.side();
+        let mut encoding = Self { cnf: Cnf::with_vars(model.cell_count() * side), box_size: sudoku.box_size() };
+
+        for cell in 0..model.cell_count() {
+            let lits = (1..=side as u8).map(|value| encoding.lit(cell, value)).collect::<Vec<_>>();
+            exactly_one(&mut encoding.cnf, &lits);
+        }
+        for constraint in model.constraints() {
+            for value in 1..=side as u8 {
+                let lits = constraint.cells.iter().map(|&cell| encoding.lit(cell, value)).collect::<Vec<_>>();
+                exactly_one(&mut encoding.cnf, &lits);
+            }
+        }
+        for (cell, &value) in sudoku.cells().iter().enumerate().filter(|(_, &value)| value != 0) {
+            let lit = encoding.lit(cell, value);
+            encoding.cnf.add_clause([lit]);
+        }
+        encoding
+    }
+
+    pub fn cnf(&self) -> &Cnf {
+        &self.cnf
+    }
+
+    fn side(&self) -> usize {
+        self.box_size * self.box_size
+    }
+
+    /// Literal true if the cell, numbered row by row, holds given value.
+    pub fn lit(&self, cell: usize, value: u8) -> Lit {
+        Var((cell * self.side() + value as usize - 1) as u32).positive()
+    }
+
+    /// Board described by the model.
+    pub fn decode(&self, model: &Model) -> Sudoku {
+        let cells = (0..self.side() * self.side()).map(|cell| {
+            (1..=self.side() as u8).find(|&value| model.satisfies(self.lit(cell, value))).expect("Every cell has a value.")
+        }).collect();
+        Sudoku::from_cells(self.box_size, cells)
+    }
+
+    /// Finds any completion of the board with the SAT solver.
+    pub fn solve(&self) -> Option<Sudoku> {
+        Solver::from_cnf(&self.cnf).solve().model().map(|model| self.decode(model))
+    }
+
+    /// Counts completions of the board with the SAT solver, stops counting after reaching `cap`.
+    pub fn count_solutions(&self, cap: usize) -> usize {
+        let mut solver = Solver::from_cnf(&self.cnf);
+        let mut count = 0;
+        while count < cap {
+            let SolveResult::Sat(model) = solver.solve() else { break };
+            count += 1;
+            let solution = self.decode(&model);
+            solver.add_clause(solution.cells().iter().enumerate().map(|(cell, &value)| !self.lit(cell, value)));
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests_encoding {
+    use super::SudokuEncoding;
+    use crate::solver::Solver;
+    use crate::sudoku::Sudoku;
+
+    const BOARDS: [&str; 3] = [
+        "..3.2.6..\n9..3.5..1\n..18.64..\n..81.29..\n7.......8\n..67.82..\n..26.95..\n8..2.3..9\n..5.1.3..\n",
+        "8........\n..36.....\n.7..9.2..\n.5...7...\n....457..\n...1...3.\n..1....68\n..85...1.\n.9....4..\n",
+        "89.356.1.\n3...1.49.\n....2985.\n9.7.6432.\n.........\n.6389.1.4\n.3298....\n.78.4....\n.5.637.48\n",
+    ];
+
+    #[test]
+    fn test_solves_like_propagation() {
+        for board in BOARDS {
+            let sudoku = board.parse::<Sudoku>().unwrap();
+            let encoding = SudokuEncoding::new(&sudoku);
+            let solver = Solver::new(3);
+            assert_eq!(encoding.count_solutions(3), solver.count_solutions(&sudoku, 3));
+            let solution = encoding.solve().unwrap();
+            assert!(solution.is_complete());
+            assert!(sudoku.cells().iter().zip(solution.cells()).all(|(&given, &value)| given == 0 || given == value));
+            if solver.count_solutions(&sudoku, 2) == 1 {
+                assert_eq!(Some(solution), sudoku.solve().ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_boards() {
+        assert_eq!(SudokuEncoding::new(&Sudoku::new(2)).count_solutions(1000), 288);
+        assert!(SudokuEncoding::new(&"11..\n....\n....\n....\n".parse().unwrap()).solve().is_none());
+        let solution = SudokuEncoding::new(&Sudoku::new(4)).solve().unwrap();
+        assert!(solution.is_complete());
+        assert_eq!(Solver::new(4).count_solutions(&solution, 2), 1);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod encoding;
+pub mod model;
+pub mod parse;
+pub mod solver;
+pub mod sudoku;
@@ -0,0 +1,16 @@
+use std::fs;
+use std::process;
+
+use ex4::sudoku::Sudoku;
+
+fn main() {
+    let sudoku = fs::read_to_string("zad_input.txt")
+        .map_err(|error| error.to_string())
+        .and_then(|text| text.parse::<Sudoku>().map_err(|error| error.to_string()))
+        .unwrap_or_else(|error| {
+            eprintln!("Could not read zad_input.txt: {}", error);
+            process::exit(1);
+        });
+    let solution = sudoku.solve().expect("Sudoku has no solution.");
+    fs::write("zad_output.txt", solution.prolog_program()).expect("Could not write zad_output.txt");
+}
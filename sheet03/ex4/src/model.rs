@@ -0,0 +1,91 @@
+/// Constraint that all cells of a row, column or box hold different values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllDifferent {
+    pub cells: Vec<usize>,
+}
+
+/// Constraints of a sudoku board of side `n²` made of `n × n` boxes.
+///
+/// Cells are numbered row by row. Every cell belongs to exactly three constraints:
+/// its row, its column and its box. Peers of a cell are the other cells of those constraints.
+#[derive(Debug, Clone)]
+pub struct Model {
+    box_size: usize,
+    constraints: Vec<AllDifferent>,
+    constraints_of: Vec<[usize; 3]>,
+    peers: Vec<Vec<usize>>,
+}
+
+impl Model {
+    pub fn new(box_size: usize) -> Self {
+        let side = box_size * box_size;
+        let rows = (0..side).map(|row| (0..side).map(|col| row * side + col).collect());
+        let cols = (0..side).map(|col| (0..side).map(|row| row * side + col).collect());
+        let boxes = (0..side).map(|index| {
+            let (top, left) = (index / box_size * box_size, index % box_size * box_size);
+            (0..side).map(|offset| (top + offset / box_size) * side + left + offset % box_size).collect()
+        });
+        let constraints = rows.chain(cols).chain(boxes).map(|cells| AllDifferent { cells }).collect::<Vec<_>>();
+
+        let constraints_of = (0..side * side).map(|cell| {
+            let (row, col) = (cell / side, cell % side);
+            [row, side + col, 2 * side + row / box_size * box_size + col / box_size]
+        }).collect::<Vec<_>>();
+        let peers = constraints_of.iter().enumerate().map(|(cell, indices)| {
+            let mut peers = indices.iter().flat_map(|&index| constraints[index].cells.iter().copied()).collect::<Vec<_>>();
+            peers.sort_unstable();
+            peers.dedup();
+            peers.retain(|&peer| peer != cell);
+            peers
+        }).collect();
+        Self { box_size, constraints, constraints_of, peers }
+    }
+
+    pub fn box_size(&self) -> usize {
+        self.box_size
+    }
+
+    pub fn side(&self) -> usize {
+        self.box_size * self.box_size
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.side() * self.side()
+    }
+
+    pub fn constraints(&self) -> &[AllDifferent] {
+        &self.constraints
+    }
+
+    /// Indices of the row, column and box constraints of the cell.
+    pub fn constraints_of(&self, cell: usize) -> [usize; 3] {
+        self.constraints_of[cell]
+    }
+
+    pub fn peers(&self, cell: usize) -> &[usize] {
+        &self.peers[cell]
+    }
+}
+
+#[cfg(test)]
+mod tests_model {
+    use super::Model;
+
+    #[test]
+    fn test_classic_board() {
+        let model = Model::new(3);
+        assert_eq!(model.constraints().len(), 27);
+        assert!(model.constraints().iter().all(|constraint| constraint.cells.len() == 9));
+        assert!((0..81).all(|cell| model.peers(cell).len() == 20));
+        assert_eq!(model.constraints()[18 + 4].cells, [30, 31, 32, 39, 40, 41, 48, 49, 50]);
+        assert_eq!(model.constraints_of(80), [8, 17, 26]);
+    }
+
+    #[test]
+    fn test_generalized_board() {
+        let model = Model::new(4);
+        assert_eq!(model.side(), 16);
+        assert_eq!(model.constraints().len(), 48);
+        assert!((0..256).all(|cell| model.peers(cell).len() == 15 + 15 + 9));
+    }
+}
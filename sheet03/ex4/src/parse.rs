@@ -0,0 +1,158 @@
+use std::fmt::{Display, Formatter};
+
+use crate::sudoku::{Sudoku, MAX_BOX_SIZE};
+
+/// Reason why the sudoku could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Input does not contain any row.
+    MissingRows,
+    /// Number of cells in a row is not a square of a supported box size.
+    InvalidSide(usize),
+    /// Cell is neither a value nor a blank.
+    InvalidCell(String),
+    /// Value does not fit on the board.
+    ValueTooLarge { value: usize, side: usize },
+    /// Row has different number of cells than the first one.
+    InvalidLineLength { expected: usize, found: usize },
+    /// Input contains more rows than the board has.
+    TooManyRows { expected: usize },
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::MissingRows => write!(f, "missing sudoku rows"),
+            ParseErrorKind::InvalidSide(side) => {
+                write!(f, "row of {} cells does not make a board of n^2 x n^2 cells with n up to {}", side, MAX_BOX_SIZE)
+            }
+            ParseErrorKind::InvalidCell(cell) => write!(f, "expected a value or '.', got: {:?}", cell),
+            ParseErrorKind::ValueTooLarge { value, side } => write!(f, "value {} does not fit on board of side {}", value, side),
+            ParseErrorKind::InvalidLineLength { expected, found } => {
+                write!(f, "expected row of {} cells, found: {}", expected, found)
+            }
+            ParseErrorKind::TooManyRows { expected } => write!(f, "expected at most {} rows", expected),
+        }
+    }
+}
+
+/// Parse error with position of its cause. Lines and columns are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, kind: ParseErrorKind) -> Self {
+        Self { line, column, kind }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Cells of a single row together with their columns.
+///
+/// Rows containing whitespace are read as space separated numbers, other rows cell by cell,
+/// where values above `9` are written as letters from `A`. Blank cells are `.` or `0` in both forms.
+fn cells(line: &str) -> Vec<(usize, &str)> {
+    if line.trim().contains(char::is_whitespace) {
+        line.split_whitespace().map(|token| {
+            let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+            (line[..offset].chars().count() + 1, token)
+        }).collect()
+    } else {
+        line.char_indices().enumerate().map(|(column, (offset, character))| {
+            (column + 1, &line[offset..offset + character.len_utf8()])
+        }).collect()
+    }
+}
+
+fn parse_cell(line_number: usize, column: usize, cell: &str, side: usize) -> Result<u8, ParseError> {
+    let value = match cell {
+        "." => Some(0),
+        _ if cell.chars().count() == 1 => cell.chars().next().and_then(|digit| digit.to_digit(36)).map(|value| value as usize),
+        _ => cell.parse::<usize>().ok(),
+    };
+    match value {
+        None => Err(ParseError::new(line_number, column, ParseErrorKind::InvalidCell(cell.to_string()))),
+        Some(value) if value > side => Err(ParseError::new(line_number, column, ParseErrorKind::ValueTooLarge { value, side })),
+        Some(value) => Ok(value as u8),
+    }
+}
+
+/// Parses sudoku written row by row with `.` for blank cells, like the sheet03 input.
+///
+/// Side of the board is the number of cells in the first row. Blank lines are ignored
+/// and rows missing at the end of the input are blank.
+pub fn parse_sudoku(text: &str) -> Result<Sudoku, ParseError> {
+    let mut rows = text.lines().enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end()))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .peekable();
+    let &(first_line, first) = rows.peek().ok_or(ParseError::new(1, 1, ParseErrorKind::MissingRows))?;
+    let side = cells(first).len();
+    let box_size = (1..=MAX_BOX_SIZE).find(|&size| size * size == side)
+        .ok_or(ParseError::new(first_line, 1, ParseErrorKind::InvalidSide(side)))?;
+
+    let mut values = vec![0; side * side];
+    for (row, (line_number, line)) in rows.enumerate() {
+        if row == side {
+            return Err(ParseError::new(line_number, 1, ParseErrorKind::TooManyRows { expected: side }));
+        }
+        let cells = cells(line);
+        if cells.len() != side {
+            let kind = ParseErrorKind::InvalidLineLength { expected: side, found: cells.len() };
+            return Err(ParseError::new(line_number, 1, kind));
+        }
+        for (col, (column, cell)) in cells.into_iter().enumerate() {
+            values[row * side + col] = parse_cell(line_number, column, cell, side)?;
+        }
+    }
+    Ok(Sudoku::from_cells(box_size, values))
+}
+
+#[cfg(test)]
+mod tests_parse {
+    use super::{parse_sudoku, ParseError, ParseErrorKind};
+
+    #[test]
+    fn test_dotted_grid() {
+        let sudoku = parse_sudoku("1...\n..2.\n.3..\n...4\n").unwrap();
+        assert_eq!(sudoku.box_size(), 2);
+        assert_eq!(sudoku.cells(), [1, 0, 0, 0, 0, 0, 2, 0, 0, 3, 0, 0, 0, 0, 0, 4]);
+    }
+
+    #[test]
+    fn test_missing_rows_are_blank() {
+        let sudoku = parse_sudoku("12.4\n0...\n").unwrap();
+        assert_eq!(sudoku.cells()[..5], [1, 2, 0, 4, 0]);
+        assert!(sudoku.cells()[5..].iter().all(|&value| value == 0));
+    }
+
+    #[test]
+    fn test_numbers_separated_with_spaces() {
+        let sudoku = parse_sudoku("4 . 0 1\n\n2 3 . .\n").unwrap();
+        assert_eq!(sudoku.cells()[..8], [4, 0, 0, 1, 2, 3, 0, 0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_sudoku(" \n"), Err(ParseError::new(1, 1, ParseErrorKind::MissingRows)));
+        assert_eq!(parse_sudoku("\n123\n"), Err(ParseError::new(2, 1, ParseErrorKind::InvalidSide(3))));
+        assert_eq!(parse_sudoku("1..!\n"), Err(ParseError::new(1, 4, ParseErrorKind::InvalidCell(String::from("!")))));
+        assert_eq!(parse_sudoku("1..x\n"), Err(ParseError::new(1, 4, ParseErrorKind::ValueTooLarge { value: 33, side: 4 })));
+        assert_eq!(parse_sudoku("1..5\n"), Err(ParseError::new(1, 4, ParseErrorKind::ValueTooLarge { value: 5, side: 4 })));
+        let kind = ParseErrorKind::InvalidLineLength { expected: 4, found: 3 };
+        assert_eq!(parse_sudoku("1...\n...\n"), Err(ParseError::new(2, 1, kind)));
+        let kind = ParseErrorKind::TooManyRows { expected: 1 };
+        assert_eq!(parse_sudoku("1\n1\n"), Err(ParseError::new(2, 1, kind)));
+    }
+}
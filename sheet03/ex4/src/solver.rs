@@ -0,0 +1,282 @@
+use crate::model::Model;
+use crate::sudoku::Sudoku;
+
+/// Returned when the board cannot be completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contradiction;
+
+/// Candidate values of every cell, value `v` is bit `v - 1` of the cell's mask.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidates {
+    masks: Vec<u64>,
+    /// Cells that became fixed and whose value was not yet removed from their peers.
+    pending: Vec<usize>,
+}
+
+impl Candidates {
+    pub fn get(&self, cell: usize) -> u64 {
+        self.masks[cell]
+    }
+
+    /// Value of the cell if only one candidate is left.
+    pub fn value(&self, cell: usize) -> Option<u8> {
+        let mask = self.masks[cell];
+        (mask.count_ones() == 1).then(|| mask.trailing_zeros() as u8 + 1)
+    }
+
+    /// Keeps only allowed candidates of the cell. Returns whether any candidate was removed.
+    fn restrict(&mut self, cell: usize, allowed: u64) -> Result<bool, Contradiction> {
+        let mask = self.masks[cell] & allowed;
+        if mask == self.masks[cell] {
+            return Ok(false);
+        }
+        if mask == 0 {
+            return Err(Contradiction);
+        }
+        self.masks[cell] = mask;
+        if mask.count_ones() == 1 {
+            self.pending.push(cell);
+        }
+        Ok(true)
+    }
+}
+
+/// Sudoku solver propagating all-different constraints and searching when propagation gets stuck.
+///
+/// Propagation repeats the following rules until none of them changes anything:
+/// - naked single - value of a fixed cell is removed from its peers,
+/// - hidden single - value that fits only one cell of a row, column or box is placed there,
+/// - naked pair - two cells of a constraint with the same two candidates take both values,
+///   which are removed from the other cells of the constraint,
+/// - hidden pair - two values that fit only the same two cells of a constraint leave no room
+///   for other candidates in those cells.
+///
+/// Search picks the cell with the fewest candidates and tries each of them in turn.
+pub struct Solver {
+    model: Model,
+}
+
+impl Solver {
+    pub fn new(box_size: usize) -> Self {
+        Self { model: Model::new(box_size) }
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Candidates of a board with given cells filled in and propagated.
+    pub fn candidates(&self, sudoku: &Sudoku) -> Result<Candidates, Contradiction> {
+        assert_eq!(sudoku.box_size(), self.model.box_size(), "solver built for another board size");
+        let all = u64::MAX >> (64 - self.model.side());
+        let mut candidates = Candidates { masks: vec![all; self.model.cell_count()], pending: Vec::new() };
+        for (cell, &value) in sudoku.cells().iter().enumerate().filter(|(_, &value)| value != 0) {
+            candidates.restrict(cell, 1 << (value - 1))?;
+        }
+        self.propagate(&mut candidates)?;
+        Ok(candidates)
+    }
+
+    /// Finds any completion of the board.
+    pub fn solve(&self, sudoku: &Sudoku) -> Result<Sudoku, Contradiction> {
+        let candidates = self.search(self.candidates(sudoku)?)?;
+        let cells = (0..self.model.cell_count()).map(|cell| candidates.value(cell).expect("Search fixes every cell.")).collect();
+        Ok(Sudoku::from_cells(self.model.box_size(), cells))
+    }
+
+    /// Counts completions of the board, stops counting after reaching `cap`.
+    pub fn count_solutions(&self, sudoku: &Sudoku, cap: usize) -> usize {
+        let mut count = 0;
+        if let Ok(candidates) = self.candidates(sudoku) {
+            self.count(candidates, cap, &mut count);
+        }
+        count
+    }
+
+    fn count(&self, candidates: Candidates, cap: usize, count: &mut usize) {
+        match self.branching_cell(&candidates) {
+            None => *count += 1,
+            Some(cell) => {
+                for guess in self.guesses(&candidates, cell) {
+                    if *count >= cap {
+                        return;
+                    }
+                    self.count(guess, cap, count);
+                }
+            }
+        }
+    }
+
+    /// Unfixed cell with the fewest candidates.
+    fn branching_cell(&self, candidates: &Candidates) -> Option<usize> {
+        (0..self.model.cell_count())
+            .filter(|&cell| candidates.get(cell).count_ones() > 1)
+            .min_by_key(|&cell| candidates.get(cell).count_ones())
+    }
+
+    /// Propagated candidates for every value of the cell that does not lead to contradiction.
+    fn guesses<'a>(&'a self, candidates: &'a Candidates, cell: usize) -> impl Iterator<Item=Candidates> + 'a {
+        let mask = candidates.get(cell);
+        (0..self.model.side()).filter(move |bit| mask & (1 << bit) != 0).filter_map(move |bit| {
+            let mut guess = candidates.clone();
+            guess.restrict(cell, 1 << bit).ok()?;
+            self.propagate(&mut guess).ok()?;
+            Some(guess)
+        })
+    }
+
+    fn search(&self, candidates: Candidates) -> Result<Candidates, Contradiction> {
+        match self.branching_cell(&candidates) {
+            None => Ok(candidates),
+            Some(cell) => self.guesses(&candidates, cell).find_map(|guess| self.search(guess).ok()).ok_or(Contradiction),
+        }
+    }
+
+    /// Applies propagation rules until a fixpoint.
+    pub fn propagate(&self, candidates: &mut Candidates) -> Result<(), Contradiction> {
+        loop {
+            self.remove_fixed_values(candidates)?;
+            let mut progress = false;
+            for constraint in 0..self.model.constraints().len() {
+                progress |= self.hidden_singles(candidates, constraint)?;
+                progress |= self.naked_pairs(candidates, constraint)?;
+                progress |= self.hidden_pairs(candidates, constraint)?;
+            }
+            if !progress && candidates.pending.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Removes values of newly fixed cells from their peers.
+    fn remove_fixed_values(&self, candidates: &mut Candidates) -> Result<(), Contradiction> {
+        while let Some(cell) = candidates.pending.pop() {
+            let value = candidates.get(cell);
+            for &peer in self.model.peers(cell) {
+                candidates.restrict(peer, !value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Cells of the constraint where the value (as a bit mask) can still be placed, as a mask of positions.
+    fn places(&self, candidates: &Candidates, constraint: usize, value: u64) -> u64 {
+        let cells = &self.model.constraints()[constraint].cells;
+        cells.iter().enumerate().filter(|(_, &cell)| candidates.get(cell) & value != 0).fold(0, |places, (index, _)| places | 1 << index)
+    }
+
+    fn hidden_singles(&self, candidates: &mut Candidates, constraint: usize) -> Result<bool, Contradiction> {
+        let mut progress = false;
+        for bit in 0..self.model.side() {
+            let places = self.places(candidates, constraint, 1 << bit);
+            match places.count_ones() {
+                0 => return Err(Contradiction),
+                1 => {
+                    let cell = self.model.constraints()[constraint].cells[places.trailing_zeros() as usize];
+                    progress |= candidates.restrict(cell, 1 << bit)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(progress)
+    }
+
+    fn naked_pairs(&self, candidates: &mut Candidates, constraint: usize) -> Result<bool, Contradiction> {
+        let cells = &self.model.constraints()[constraint].cells;
+        let mut progress = false;
+        for (index, &first) in cells.iter().enumerate() {
+            let pair = candidates.get(first);
+            if pair.count_ones() != 2 {
+                continue;
+            }
+            let Some(&second) = cells[index + 1..].iter().find(|&&cell| candidates.get(cell) == pair) else { continue };
+            for &other in cells.iter().filter(|&&cell| cell != first && cell != second) {
+                progress |= candidates.restrict(other, !pair)?;
+            }
+        }
+        Ok(progress)
+    }
+
+    fn hidden_pairs(&self, candidates: &mut Candidates, constraint: usize) -> Result<bool, Contradiction> {
+        let cells = &self.model.constraints()[constraint].cells;
+        let places = (0..self.model.side()).map(|bit| self.places(candidates, constraint, 1 << bit)).collect::<Vec<_>>();
+        let mut progress = false;
+        for first in 0..places.len() {
+            if places[first].count_ones() != 2 {
+                continue;
+            }
+            for second in first + 1..places.len() {
+                if places[second] == places[first] {
+                    let pair = 1 << first | 1 << second;
+                    let mut positions = places[first];
+                    while positions != 0 {
+                        let index = positions.trailing_zeros() as usize;
+                        progress |= candidates.restrict(cells[index], pair)?;
+                        positions &= positions - 1;
+                    }
+                }
+            }
+        }
+        Ok(progress)
+    }
+}
+
+#[cfg(test)]
+mod tests_solver {
+    use super::{Contradiction, Solver};
+    use crate::sudoku::Sudoku;
+
+    const EASY: &str = "..3.2.6..\n9..3.5..1\n..18.64..\n..81.29..\n7.......8\n..67.82..\n..26.95..\n8..2.3..9\n..5.1.3..\n";
+
+    fn solve(text: &str) -> Result<String, Contradiction> {
+        text.parse::<Sudoku>().unwrap().solve().map(|solution| solution.to_prolog_list())
+    }
+
+    #[test]
+    fn test_propagation_solves_easy_board() {
+        let sudoku = EASY.parse::<Sudoku>().unwrap();
+        let solver = Solver::new(3);
+        let candidates = solver.candidates(&sudoku).unwrap();
+        assert!((0..81).all(|cell| candidates.value(cell).is_some()));
+        assert_eq!(
+            solve(EASY).unwrap(),
+            "[4,8,3,9,2,1,6,5,7,9,6,7,3,4,5,8,2,1,2,5,1,8,7,6,4,9,3,5,4,8,1,3,2,9,7,6,7,2,9,5,6,4,1,3,8,1,3,6,7,9,8,2,4,5,3,7,2,6,8,9,5,1,4,8,1,4,2,5,3,7,6,9,6,9,5,4,1,7,3,8,2]"
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        /* Needs guessing, known as one of the hardest boards for simple propagation. */
+        let hard = "8........\n..36.....\n.7..9.2..\n.5...7...\n....457..\n...1...3.\n..1....68\n..85...1.\n.9....4..\n";
+        assert_eq!(
+            solve(hard).unwrap(),
+            "[8,1,2,7,5,3,6,4,9,9,4,3,6,8,2,1,7,5,6,7,5,4,9,1,2,8,3,1,5,4,2,3,7,8,9,6,3,6,9,8,4,5,7,2,1,2,8,7,1,6,9,5,3,4,5,2,1,9,7,4,3,6,8,4,3,8,5,2,6,9,1,7,7,9,6,3,1,8,4,5,2]"
+        );
+    }
+
+    #[test]
+    fn test_contradiction() {
+        assert_eq!(solve("11..\n....\n....\n....\n"), Err(Contradiction));
+        assert_eq!(solve("12..\n..3.\n....\n....\n"), Err(Contradiction));
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let solver = Solver::new(2);
+        assert_eq!(solver.count_solutions(&Sudoku::new(2), 1000), 288);
+        assert_eq!(solver.count_solutions(&Sudoku::new(2), 5), 5);
+        assert_eq!(Solver::new(3).count_solutions(&EASY.parse().unwrap(), 5), 1);
+    }
+
+    #[test]
+    fn test_generalized_board() {
+        let solution = Solver::new(4).solve(&Sudoku::new(4)).unwrap();
+        assert!(solution.is_complete());
+        let model = Solver::new(4);
+        for constraint in model.model().constraints() {
+            let mut values = constraint.cells.iter().map(|&cell| solution.cells()[cell]).collect::<Vec<_>>();
+            values.sort_unstable();
+            assert_eq!(values, (1..=16).collect::<Vec<_>>());
+        }
+    }
+}
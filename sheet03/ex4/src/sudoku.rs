@@ -0,0 +1,126 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::parse::{parse_sudoku, ParseError};
+use crate::solver::{Contradiction, Solver};
+
+/// Largest supported box size, candidates of a cell are kept in a 64 bit mask.
+pub const MAX_BOX_SIZE: usize = 8;
+
+/// Sudoku board of side `n²` split into `n × n` boxes. Cells hold values `1..=n²`, `0` marks a blank cell.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sudoku {
+    box_size: usize,
+    cells: Vec<u8>,
+}
+
+impl Sudoku {
+    /// Creates board with all cells blank.
+    pub fn new(box_size: usize) -> Self {
+        assert!((1..=MAX_BOX_SIZE).contains(&box_size), "box size must be between 1 and {}", MAX_BOX_SIZE);
+        let side = box_size * box_size;
+        Self { box_size, cells: vec![0; side * side] }
+    }
+
+    /// Builds board from values checked by the parser.
+    pub(crate) fn from_cells(box_size: usize, cells: Vec<u8>) -> Self {
+        debug_assert_eq!(cells.len(), box_size.pow(4));
+        Self { box_size, cells }
+    }
+
+    pub fn box_size(&self) -> usize {
+        self.box_size
+    }
+
+    pub fn side(&self) -> usize {
+        self.box_size * self.box_size
+    }
+
+    /// Values of cells row by row, `0` for blank cells.
+    pub fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<u8> {
+        Some(self.cells[row * self.side() + col]).filter(|&value| value != 0)
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: Option<u8>) {
+        assert!(value.is_none_or(|value| (1..=self.side()).contains(&(value as usize))), "value out of range");
+        let side = self.side();
+        self.cells[row * side + col] = value.unwrap_or(0);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.cells.iter().all(|&value| value != 0)
+    }
+
+    /// Finds any completion of the board, keeping the filled cells.
+    pub fn solve(&self) -> Result<Sudoku, Contradiction> {
+        Solver::new(self.box_size).solve(self)
+    }
+
+    /// Values of all cells as a flat Prolog list, like `[8,9,4,...]`.
+    pub fn to_prolog_list(&self) -> String {
+        let values = self.cells.iter().map(u8::to_string).collect::<Vec<_>>();
+        format!("[{}]", values.join(","))
+    }
+
+    /// Prolog program printing the board as a flat list, the sheet03 answer format.
+    pub fn prolog_program(&self) -> String {
+        format!(":- write({}), nl.\n", self.to_prolog_list())
+    }
+}
+
+/// Character of a value in the dotted format: `.` for blank, digits and then letters from `A` for `10`.
+pub(crate) fn value_char(value: u8) -> char {
+    match value {
+        0 => '.',
+        _ => char::from_digit(value as u32, 36).map_or('?', |digit| digit.to_ascii_uppercase()),
+    }
+}
+
+/// Writes board in the dotted input format. Boards with values above `35` use space separated numbers.
+impl Display for Sudoku {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.cells.chunks(self.side()) {
+            let line = match self.side() {
+                side if side < 36 => row.iter().map(|&value| value_char(value)).collect::<String>(),
+                _ => row.iter().map(|&value| if value == 0 { String::from(".") } else { value.to_string() }).collect::<Vec<_>>().join(" "),
+            };
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Sudoku {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_sudoku(text)
+    }
+}
+
+#[cfg(test)]
+mod tests_sudoku {
+    use super::Sudoku;
+
+    #[test]
+    fn test_prolog_list() {
+        let mut sudoku = Sudoku::new(1);
+        assert_eq!(sudoku.to_prolog_list(), "[0]");
+        sudoku.set(0, 0, Some(1));
+        assert_eq!(sudoku.prolog_program(), ":- write([1]), nl.\n");
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let mut sudoku = Sudoku::new(4);
+        sudoku.set(0, 0, Some(16));
+        sudoku.set(15, 15, Some(9));
+        let text = sudoku.to_string();
+        assert!(text.starts_with("G..............."));
+        assert_eq!(text.parse::<Sudoku>().unwrap(), sudoku);
+    }
+}
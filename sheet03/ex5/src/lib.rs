@@ -0,0 +1,4 @@
+pub mod model;
+pub mod parse;
+pub mod solver;
+pub mod storm;
@@ -0,0 +1,129 @@
+/// Constraint over cells of the board, cells are numbered row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// Exactly `count` of the cells are filled.
+    Sum { cells: Vec<usize>, count: usize },
+    /// Cells form one of the allowed patterns, bit `i` of a pattern is the value of `cells[i]`.
+    Pattern { cells: Vec<usize>, allowed: Vec<u8> },
+}
+
+impl Constraint {
+    pub fn cells(&self) -> &[usize] {
+        match self {
+            Constraint::Sum { cells, .. } | Constraint::Pattern { cells, .. } => cells,
+        }
+    }
+}
+
+/// Checks 2 × 2 square given row by row. Storms are rectangles that do not touch,
+/// so no square has exactly three filled cells or two filled cells on a diagonal only.
+pub fn is_valid_square(square: [bool; 4]) -> bool {
+    square.iter().filter(|&&filled| filled).count() != 3 && square != [true, false, false, true] && square != [false, true, true, false]
+}
+
+/// Checks three consecutive cells of a row or column. Storms are at least two cells wide and high.
+fn is_valid_segment(segment: [bool; 3]) -> bool {
+    segment != [false, true, false]
+}
+
+/// Constraints of the storm puzzle.
+///
+/// Row and column counts become sums. Storm rules are local: every 2 × 2 square must be valid
+/// and no storm is a single cell wide, which is checked on every three consecutive cells.
+/// Windows reaching outside of the board treat outer cells as empty.
+#[derive(Debug, Clone)]
+pub struct Model {
+    height: usize,
+    width: usize,
+    constraints: Vec<Constraint>,
+    constraints_of: Vec<Vec<usize>>,
+}
+
+impl Model {
+    pub fn new(row_counts: &[usize], col_counts: &[usize]) -> Self {
+        let (height, width) = (row_counts.len(), col_counts.len());
+        let mut model = Self { height, width, constraints: Vec::new(), constraints_of: vec![Vec::new(); height * width] };
+
+        for (row, &count) in row_counts.iter().enumerate() {
+            model.add(Constraint::Sum { cells: (0..width).map(|col| row * width + col).collect(), count });
+        }
+        for (col, &count) in col_counts.iter().enumerate() {
+            model.add(Constraint::Sum { cells: (0..height).map(|row| row * width + col).collect(), count });
+        }
+        for row in -1..height as isize {
+            for col in -1..width as isize {
+                model.add_window(&[(row, col), (row, col + 1), (row + 1, col), (row + 1, col + 1)], |values| {
+                    is_valid_square([values[0], values[1], values[2], values[3]])
+                });
+                model.add_window(&[(row, col), (row, col + 1), (row, col + 2)], |values| {
+                    is_valid_segment([values[0], values[1], values[2]])
+                });
+                model.add_window(&[(row, col), (row + 1, col), (row + 2, col)], |values| {
+                    is_valid_segment([values[0], values[1], values[2]])
+                });
+            }
+        }
+        model
+    }
+
+    fn add(&mut self, constraint: Constraint) {
+        for &cell in constraint.cells() {
+            self.constraints_of[cell].push(self.constraints.len());
+        }
+        self.constraints.push(constraint);
+    }
+
+    /// Adds pattern constraint over the cells of the window that lie on the board.
+    fn add_window(&mut self, window: &[(isize, isize)], is_valid: impl Fn(&[bool]) -> bool) {
+        let on_board = |&(row, col): &(isize, isize)| (0..self.height as isize).contains(&row) && (0..self.width as isize).contains(&col);
+        let inside = window.iter().map(on_board).collect::<Vec<_>>();
+        let cells = window.iter().filter(|position| on_board(position))
+            .map(|&(row, col)| row as usize * self.width + col as usize)
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            return;
+        }
+
+        let allowed = (0..1u8 << cells.len()).filter(|pattern| {
+            let mut bits = (0..cells.len()).map(|bit| pattern & 1 << bit != 0);
+            let values = inside.iter().map(|&inside| inside && bits.next().unwrap()).collect::<Vec<_>>();
+            is_valid(&values)
+        }).collect::<Vec<_>>();
+        if allowed.len() < 1 << cells.len() {
+            self.add(Constraint::Pattern { cells, allowed });
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// Indices of constraints the cell takes part in.
+    pub fn constraints_of(&self, cell: usize) -> &[usize] {
+        &self.constraints_of[cell]
+    }
+}
+
+#[cfg(test)]
+mod tests_model {
+    use super::{Constraint, Model};
+
+    #[test]
+    fn test_windows() {
+        /* Board of a single column of two cells. Windows along the one cell wide rows allow only empty cells,
+           the window starting above the column forbids the first cell filled without the second one. */
+        let model = Model::new(&[1, 1], &[2]);
+        assert_eq!(model.constraints().len(), 3 + 4);
+        assert!(model.constraints().contains(&Constraint::Pattern { cells: vec![0], allowed: vec![0] }));
+        assert!(model.constraints().contains(&Constraint::Pattern { cells: vec![0, 1], allowed: vec![0, 2, 3] }));
+        assert_eq!(model.constraints_of(1).len(), 5);
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::{Display, Formatter};
+
+use crate::storm::{Given, Storm};
+
+/// Reason why the puzzle could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Input ends before the line of row or column counts.
+    MissingCounts(&'static str),
+    /// Token is not a non-negative integer.
+    InvalidNumber(String),
+    /// Row or column needs more filled cells than it has.
+    CountTooLarge { count: usize, length: usize },
+    /// Line of a given cell does not consist of row, column and value.
+    InvalidGivenLine { found: usize },
+    /// Value of a given cell is neither `0` nor `1`.
+    InvalidValue(usize),
+    /// Given cell lies outside of the board.
+    CellOutOfBoard { row: usize, col: usize },
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::MissingCounts(line) => write!(f, "missing {} counts", line),
+            ParseErrorKind::InvalidNumber(token) => write!(f, "expected a non-negative integer, got: {:?}", token),
+            ParseErrorKind::CountTooLarge { count, length } => write!(f, "{} filled cells do not fit in line of length {}", count, length),
+            ParseErrorKind::InvalidGivenLine { found } => write!(f, "expected row, column and value, found {} numbers", found),
+            ParseErrorKind::InvalidValue(value) => write!(f, "expected value 0 or 1, got: {}", value),
+            ParseErrorKind::CellOutOfBoard { row, col } => write!(f, "cell ({}, {}) is outside of the board", row, col),
+        }
+    }
+}
+
+/// Parse error with position of its cause. Lines and columns are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, kind: ParseErrorKind) -> Self {
+        Self { line, column, kind }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Numbers of the line together with their columns.
+fn numbers(line_number: usize, line: &str) -> Result<Vec<(usize, usize)>, ParseError> {
+    line.split_whitespace().map(|token| {
+        let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+        let column = line[..offset].chars().count() + 1;
+        token.parse::<usize>()
+            .map(|number| (column, number))
+            .map_err(|_| ParseError::new(line_number, column, ParseErrorKind::InvalidNumber(token.to_string())))
+    }).collect()
+}
+
+/// Parses the sheet03 input: row counts, column counts and then given cells as `row column value`,
+/// with rows and columns numbered from 0. Blank lines are ignored.
+pub fn parse_storm(text: &str) -> Result<Storm, ParseError> {
+    let mut lines = text.lines().enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let last_line = text.lines().count().max(1);
+    let mut counts = |name| {
+        let (line_number, line) = lines.next().ok_or(ParseError::new(last_line, 1, ParseErrorKind::MissingCounts(name)))?;
+        numbers(line_number, line).map(|numbers| (line_number, numbers))
+    };
+    let (row_line, row_counts) = counts("row")?;
+    let (col_line, col_counts) = counts("column")?;
+
+    let fits = |line_number, counts: &[(usize, usize)], length| match counts.iter().find(|&&(_, count)| count > length) {
+        Some(&(column, count)) => Err(ParseError::new(line_number, column, ParseErrorKind::CountTooLarge { count, length })),
+        None => Ok(()),
+    };
+    fits(row_line, &row_counts, col_counts.len())?;
+    fits(col_line, &col_counts, row_counts.len())?;
+    let values = |counts: Vec<(usize, usize)>| counts.into_iter().map(|(_, count)| count).collect();
+    let mut storm = Storm::new(values(row_counts), values(col_counts));
+
+    for (line_number, line) in lines {
+        let (row, col, filled) = match numbers(line_number, line)?.as_slice() {
+            &[(_, row), (_, col), (column, value)] => match value {
+                0 | 1 => (row, col, value == 1),
+                _ => return Err(ParseError::new(line_number, column, ParseErrorKind::InvalidValue(value))),
+            },
+            numbers => return Err(ParseError::new(line_number, 1, ParseErrorKind::InvalidGivenLine { found: numbers.len() })),
+        };
+        if row >= storm.height() || col >= storm.width() {
+            return Err(ParseError::new(line_number, 1, ParseErrorKind::CellOutOfBoard { row, col }));
+        }
+        storm.add_given(Given { row, col, filled });
+    }
+    Ok(storm)
+}
+
+#[cfg(test)]
+mod tests_parse {
+    use super::{parse_storm, ParseError, ParseErrorKind};
+    use crate::storm::Given;
+
+    #[test]
+    fn test_validator_input() {
+        let storm = parse_storm("3 4 2\n0 2 3 2\n\n0 3 0\n2 1 1\n").unwrap();
+        assert_eq!(storm.row_counts(), [3, 4, 2]);
+        assert_eq!(storm.col_counts(), [0, 2, 3, 2]);
+        assert_eq!(storm.givens(), [Given { row: 0, col: 3, filled: false }, Given { row: 2, col: 1, filled: true }]);
+        assert_eq!(parse_storm(&storm.to_string()).unwrap(), storm);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_storm(""), Err(ParseError::new(1, 1, ParseErrorKind::MissingCounts("row"))));
+        assert_eq!(parse_storm("1 2\n\n"), Err(ParseError::new(2, 1, ParseErrorKind::MissingCounts("column"))));
+        let kind = ParseErrorKind::InvalidNumber(String::from("-1"));
+        assert_eq!(parse_storm("1  -1\n"), Err(ParseError::new(1, 4, kind)));
+        let kind = ParseErrorKind::CountTooLarge { count: 3, length: 2 };
+        assert_eq!(parse_storm("1 1\n0 3\n"), Err(ParseError::new(2, 3, kind)));
+        let kind = ParseErrorKind::InvalidGivenLine { found: 2 };
+        assert_eq!(parse_storm("0 0\n0 0\n1 1\n"), Err(ParseError::new(3, 1, kind)));
+        assert_eq!(parse_storm("0 0\n0 0\n1 1 2\n"), Err(ParseError::new(3, 5, ParseErrorKind::InvalidValue(2))));
+        let kind = ParseErrorKind::CellOutOfBoard { row: 0, col: 2 };
+        assert_eq!(parse_storm("0 0\n0 0\n0 2 1\n"), Err(ParseError::new(3, 1, kind)));
+    }
+}
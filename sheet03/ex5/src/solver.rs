@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+
+use crate::model::{Constraint, Model};
+use crate::storm::{Board, Storm};
+
+/// Returned when the puzzle cannot be solved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contradiction;
+
+/// Values of the cells, `None` for the unknown ones.
+pub type Cells = Vec<Option<bool>>;
+
+/// Storm solver propagating constraints of the [`Model`] and backtracking when propagation gets stuck.
+///
+/// A sum fills or empties all unknown cells of its line once the count leaves no choice,
+/// a pattern fixes cells that have the same value in all allowed patterns matching the known cells.
+pub struct Solver<'a> {
+    storm: &'a Storm,
+    model: Model,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(storm: &'a Storm) -> Self {
+        Self { storm, model: Model::new(storm.row_counts(), storm.col_counts()) }
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Cells with the givens set and propagated.
+    pub fn initial_cells(&self) -> Result<Cells, Contradiction> {
+        let mut cells = vec![None; self.model.height() * self.model.width()];
+        for given in self.storm.givens() {
+            let cell = &mut cells[given.row * self.model.width() + given.col];
+            if cell.is_some_and(|filled| filled != given.filled) {
+                return Err(Contradiction);
+            }
+            *cell = Some(given.filled);
+        }
+        self.propagate(&mut cells, (0..self.model.constraints().len()).collect())?;
+        Ok(cells)
+    }
+
+    /// Finds any board satisfying all constraints.
+    pub fn solve(&self) -> Result<Board, Contradiction> {
+        let cells = self.search(self.initial_cells()?)?;
+        let cells = cells.into_iter().map(|cell| cell.expect("Search fixes every cell.")).collect();
+        Ok(Board::from_cells(self.model.height(), self.model.width(), cells))
+    }
+
+    /// Counts solutions of the puzzle, stops counting after reaching `cap`.
+    pub fn count_solutions(&self, cap: usize) -> usize {
+        let mut count = 0;
+        if let Ok(cells) = self.initial_cells() {
+            self.count(cells, cap, &mut count);
+        }
+        count
+    }
+
+    fn count(&self, cells: Cells, cap: usize, count: &mut usize) {
+        match cells.iter().position(Option::is_none) {
+            None => *count += 1,
+            Some(cell) => {
+                for filled in [true, false] {
+                    if *count >= cap {
+                        return;
+                    }
+                    if let Ok(assumed) = self.assume(&cells, cell, filled) {
+                        self.count(assumed, cap, count);
+                    }
+                }
+            }
+        }
+    }
+
+    fn search(&self, cells: Cells) -> Result<Cells, Contradiction> {
+        match cells.iter().position(Option::is_none) {
+            None => Ok(cells),
+            Some(cell) => [true, false].into_iter()
+                .find_map(|filled| self.assume(&cells, cell, filled).and_then(|assumed| self.search(assumed)).ok())
+                .ok_or(Contradiction),
+        }
+    }
+
+    /// Returns propagated copy of the cells with given cell set to given value.
+    fn assume(&self, cells: &Cells, cell: usize, filled: bool) -> Result<Cells, Contradiction> {
+        let mut cells = cells.clone();
+        cells[cell] = Some(filled);
+        self.propagate(&mut cells, self.model.constraints_of(cell).iter().copied().collect())?;
+        Ok(cells)
+    }
+
+    /// Revises queued constraints until no new cell gets fixed.
+    fn propagate(&self, cells: &mut Cells, mut queue: VecDeque<usize>) -> Result<(), Contradiction> {
+        let mut queued = vec![false; self.model.constraints().len()];
+        queue.retain(|&constraint| !std::mem::replace(&mut queued[constraint], true));
+
+        while let Some(constraint) = queue.pop_front() {
+            queued[constraint] = false;
+            for cell in revise(&self.model.constraints()[constraint], cells)? {
+                for &other in self.model.constraints_of(cell) {
+                    if !std::mem::replace(&mut queued[other], true) {
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fixes cells forced by the constraint. Returns fixed cells.
+fn revise(constraint: &Constraint, values: &mut Cells) -> Result<Vec<usize>, Contradiction> {
+    let mut fixed = Vec::new();
+    match constraint {
+        Constraint::Sum { cells, count } => {
+            let filled = cells.iter().filter(|&&cell| values[cell] == Some(true)).count();
+            let unknown = cells.iter().filter(|&&cell| values[cell].is_none()).count();
+            if filled > *count || filled + unknown < *count {
+                return Err(Contradiction);
+            }
+            if unknown > 0 && (filled == *count || filled + unknown == *count) {
+                fixed.extend(cells.iter().filter(|&&cell| values[cell].is_none()));
+                for &cell in &fixed {
+                    values[cell] = Some(filled < *count);
+                }
+            }
+        }
+        Constraint::Pattern { cells, allowed } => {
+            let (mut known, mut pattern) = (0u8, 0u8);
+            for (bit, &cell) in cells.iter().enumerate() {
+                if let Some(filled) = values[cell] {
+                    known |= 1 << bit;
+                    pattern |= u8::from(filled) << bit;
+                }
+            }
+            let mut matching = allowed.iter().filter(|&&allowed| allowed & known == pattern);
+            let first = *matching.next().ok_or(Contradiction)?;
+            let (all, any) = matching.fold((first, first), |(all, any), &allowed| (all & allowed, any | allowed));
+            for (bit, &cell) in cells.iter().enumerate().filter(|&(bit, _)| known & 1 << bit == 0) {
+                if all & 1 << bit != 0 || any & 1 << bit == 0 {
+                    values[cell] = Some(all & 1 << bit != 0);
+                    fixed.push(cell);
+                }
+            }
+        }
+    }
+    Ok(fixed)
+}
+
+#[cfg(test)]
+mod tests_solver {
+    use super::{Contradiction, Solver};
+    use crate::storm::Storm;
+
+    #[test]
+    fn test_small_puzzle() {
+        let storm = "4 4 0 5 5 5\n5 5 3 5 5 0\n5 5 0\n".parse::<Storm>().unwrap();
+        let board = storm.solve().unwrap();
+        assert_eq!(board.to_string(), "##.##.\n##.##.\n......\n#####.\n#####.\n#####.\n");
+        assert!(storm.is_solved_by(&board));
+    }
+
+    #[test]
+    fn test_givens() {
+        let text = "3 8 5 3 3 5 5 5 2 2\n0 2 7 7 2 2 0 7 7 7\n0 5 0\n0 6 0\n1 5 1\n1 6 0\n";
+        let storm = text.parse::<Storm>().unwrap();
+        let board = storm.solve().unwrap();
+        assert!(storm.is_solved_by(&board));
+        assert_eq!(
+            board.to_prolog_list(),
+            "[0,0,0,0,0,0,0,1,1,1,0,1,1,1,1,1,0,1,1,1,0,1,1,1,1,1,0,0,0,0,0,0,0,0,0,0,0,1,1,1,0,0,0,0,0,0,0,1,1,1,0,0,1,1,0,0,0,1,1,1,0,0,1,1,0,0,0,1,1,1,0,0,1,1,0,0,0,1,1,1,0,0,1,1,0,0,0,0,0,0,0,0,1,1,0,0,0,0,0,0]"
+        );
+    }
+
+    #[test]
+    fn test_contradiction() {
+        /* Single filled cell cannot form a storm. */
+        assert_eq!("1 0\n1 0\n".parse::<Storm>().unwrap().solve(), Err(Contradiction));
+        assert_eq!("2 2\n2 2\n0 0 0\n".parse::<Storm>().unwrap().solve(), Err(Contradiction));
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        /* Two storms on opposite corners of the board, either diagonal works. */
+        let storm = "2 2 0 2 2\n2 2 0 2 2\n".parse::<Storm>().unwrap();
+        assert_eq!(Solver::new(&storm).count_solutions(10), 2);
+        assert_eq!(Solver::new(&storm).count_solutions(1), 1);
+        let storm = "2 2 0 0\n2 2\n".parse::<Storm>().unwrap();
+        assert_eq!(Solver::new(&storm).count_solutions(10), 1);
+        /* Storms are at least two cells wide. */
+        let storm = "1 1 0 1 1\n0 2 2\n".parse::<Storm>().unwrap();
+        assert_eq!(Solver::new(&storm).count_solutions(10), 0);
+    }
+}
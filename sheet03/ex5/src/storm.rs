@@ -0,0 +1,186 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::parse::{parse_storm, ParseError};
+use crate::solver::{Contradiction, Solver};
+
+/// Cell whose value is known before solving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Given {
+    pub row: usize,
+    pub col: usize,
+    pub filled: bool,
+}
+
+/// Storm puzzle: fill cells of the board so that every row and column has the required number of filled cells.
+///
+/// Filled cells form storms - rectangles of at least 2 × 2 cells. Storms do not touch each other,
+/// not even by corners. Some cells may be given up front.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Storm {
+    row_counts: Vec<usize>,
+    col_counts: Vec<usize>,
+    givens: Vec<Given>,
+}
+
+impl Storm {
+    pub fn new(row_counts: Vec<usize>, col_counts: Vec<usize>) -> Self {
+        Self { row_counts, col_counts, givens: Vec::new() }
+    }
+
+    pub fn height(&self) -> usize {
+        self.row_counts.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.col_counts.len()
+    }
+
+    pub fn row_counts(&self) -> &[usize] {
+        &self.row_counts
+    }
+
+    pub fn col_counts(&self) -> &[usize] {
+        &self.col_counts
+    }
+
+    pub fn givens(&self) -> &[Given] {
+        &self.givens
+    }
+
+    /// Fixes value of the cell before solving.
+    pub fn add_given(&mut self, given: Given) {
+        assert!(given.row < self.height() && given.col < self.width(), "given cell is outside of the board");
+        self.givens.push(given);
+    }
+
+    /// Finds any board satisfying the counts, the givens and the storm rules.
+    pub fn solve(&self) -> Result<Board, Contradiction> {
+        Solver::new(self).solve()
+    }
+
+    /// Checks if the board is a solution of the puzzle.
+    pub fn is_solved_by(&self, board: &Board) -> bool {
+        board.height == self.height() && board.width == self.width()
+            && (0..self.height()).all(|row| (0..self.width()).filter(|&col| board.get(row, col)).count() == self.row_counts[row])
+            && (0..self.width()).all(|col| (0..self.height()).filter(|&row| board.get(row, col)).count() == self.col_counts[col])
+            && self.givens.iter().all(|given| board.get(given.row, given.col) == given.filled)
+            && board.has_valid_storms()
+    }
+}
+
+/// Writes the puzzle in the sheet03 input format.
+impl Display for Storm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let join = |counts: &[usize]| counts.iter().map(usize::to_string).collect::<Vec<_>>().join(" ");
+        writeln!(f, "{}", join(&self.row_counts))?;
+        writeln!(f, "{}", join(&self.col_counts))?;
+        for given in &self.givens {
+            writeln!(f, "{} {} {}", given.row, given.col, u8::from(given.filled))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Storm {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_storm(text)
+    }
+}
+
+/// Fully filled board.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+    height: usize,
+    width: usize,
+    cells: Vec<bool>,
+}
+
+impl Board {
+    pub(crate) fn from_cells(height: usize, width: usize, cells: Vec<bool>) -> Self {
+        debug_assert_eq!(cells.len(), height * width);
+        Self { height, width, cells }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        self.cells[row * self.width + col]
+    }
+
+    /// Checks that filled cells form rectangles of at least 2 × 2 cells that do not touch each other.
+    pub fn has_valid_storms(&self) -> bool {
+        /* Cells outside of the board count as empty. */
+        let at = |row: isize, col: isize| {
+            (0..self.height as isize).contains(&row) && (0..self.width as isize).contains(&col) && self.get(row as usize, col as usize)
+        };
+        (-1..self.height as isize).all(|row| (-1..self.width as isize).all(|col| {
+            let square = [at(row, col), at(row, col + 1), at(row + 1, col), at(row + 1, col + 1)];
+            let horizontal = [at(row, col), at(row, col + 1), at(row, col + 2)];
+            let vertical = [at(row, col), at(row + 1, col), at(row + 2, col)];
+            crate::model::is_valid_square(square) && horizontal != [false, true, false] && vertical != [false, true, false]
+        }))
+    }
+
+    /// Cells row by row as a flat Prolog list of zeros and ones.
+    pub fn to_prolog_list(&self) -> String {
+        let values = self.cells.iter().map(|&filled| if filled { "1" } else { "0" }).collect::<Vec<_>>();
+        format!("[{}]", values.join(","))
+    }
+
+    /// Prolog program printing the board as a flat list, the sheet03 answer format.
+    pub fn prolog_program(&self) -> String {
+        format!(":- write({}), nl.\n", self.to_prolog_list())
+    }
+}
+
+/// Writes the board with `#` for filled and `.` for empty cells.
+impl Display for Board {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.cells.chunks(self.width.max(1)) {
+            writeln!(f, "{}", row.iter().map(|&filled| if filled { '#' } else { '.' }).collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_storm {
+    use super::{Board, Given, Storm};
+
+    fn board(text: &str) -> Board {
+        let rows = text.lines().collect::<Vec<_>>();
+        let cells = rows.iter().flat_map(|row| row.chars().map(|cell| cell == '#')).collect();
+        Board::from_cells(rows.len(), rows[0].len(), cells)
+    }
+
+    #[test]
+    fn test_storm_rules() {
+        assert!(board("##.\n##.\n...\n").has_valid_storms());
+        assert!(board("##.##\n##.##\n").has_valid_storms());
+        /* Too thin, touching by corner and not a rectangle. */
+        assert!(!board("#..\n#..\n").has_valid_storms());
+        assert!(!board("##..\n##..\n..##\n..##\n").has_valid_storms());
+        assert!(!board("###\n###\n##.\n").has_valid_storms());
+    }
+
+    #[test]
+    fn test_prolog_list_and_display() {
+        let board = board("##.\n##.\n");
+        assert_eq!(board.prolog_program(), ":- write([1,1,0,1,1,0]), nl.\n");
+        assert_eq!(board.to_string(), "##.\n##.\n");
+        let mut storm = Storm::new(vec![2, 2], vec![2, 2, 0]);
+        assert!(storm.is_solved_by(&board));
+        storm.add_given(Given { row: 1, col: 2, filled: true });
+        assert!(!storm.is_solved_by(&board));
+        assert_eq!(storm.to_string(), "2 2\n2 2 0\n1 2 1\n");
+    }
+}